@@ -1,3 +1,6 @@
+-- Original schema. Columns and tables added since are applied on startup
+-- by the migrations in src/database.rs (tracked with PRAGMA user_version).
+
 CREATE TABLE IF NOT EXISTS bugs (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
     title TEXT NOT NULL,
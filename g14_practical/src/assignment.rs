@@ -0,0 +1,261 @@
+use crate::config::CapacityPolicy;
+use crate::database;
+use crate::models::{
+    AssigneeCandidate, AssignmentConfig, AssignmentStrategyKind, Bug, DeveloperSummary,
+};
+use crate::state::AppState;
+
+// Auto-assignment strategies. Each strategy only ranks the active
+// developers; capacity handling and the actual write are shared below so
+// every strategy treats full developers the same way.
+
+pub struct AssignmentContext<'a> {
+    pub bug: &'a Bug,
+    pub developers: &'a [DeveloperSummary],
+    pub config: &'a AssignmentConfig,
+    // Developer who got the project's previous auto-assigned bug.
+    pub last_assigned: Option<i64>,
+}
+
+pub trait AssignmentStrategy: Sync {
+    // Developer ids best-first, each with the reason it was ranked there.
+    fn rank(&self, ctx: &AssignmentContext) -> Vec<(i64, String)>;
+}
+
+pub struct RoundRobin;
+pub struct LeastLoaded;
+pub struct RoutingRules;
+
+pub fn strategy_for(kind: AssignmentStrategyKind) -> Option<&'static dyn AssignmentStrategy> {
+    match kind {
+        AssignmentStrategyKind::Manual => None,
+        AssignmentStrategyKind::RoundRobin => Some(&RoundRobin),
+        AssignmentStrategyKind::LeastLoaded => Some(&LeastLoaded),
+        AssignmentStrategyKind::Rules => Some(&RoutingRules),
+    }
+}
+
+impl AssignmentStrategy for RoundRobin {
+    fn rank(&self, ctx: &AssignmentContext) -> Vec<(i64, String)> {
+        let mut ids: Vec<i64> = ctx.developers.iter().map(|d| d.developer.id).collect();
+        ids.sort_unstable();
+
+        let start = ctx.last_assigned
+            .and_then(|last| ids.iter().position(|id| *id > last))
+            .unwrap_or(0);
+        ids.rotate_left(start);
+
+        ids.into_iter()
+            .enumerate()
+            .map(|(i, id)| {
+                let reason = if i == 0 {
+                    "Next in the project's round-robin rotation".to_string()
+                } else {
+                    format!("{} place(s) later in the round-robin rotation", i)
+                };
+                (id, reason)
+            })
+            .collect()
+    }
+}
+
+// Whether `term` appears in `text` as a whole word, so the skill "go"
+// doesn't match "good" but "c++" still matches "a c++ crash".
+fn mentions(text: &str, term: &str) -> bool {
+    !term.is_empty() && text.match_indices(term).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + term.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+impl AssignmentStrategy for LeastLoaded {
+    fn rank(&self, ctx: &AssignmentContext) -> Vec<(i64, String)> {
+        by_load(ctx.developers)
+            .into_iter()
+            .map(|d| (d.developer.id, load_reason(d)))
+            .collect()
+    }
+}
+
+// Routes on the bug's component and text. Component owners come first,
+// then developers picked by keyword rules, then developers whose skills
+// match the component or appear in the text; everyone else follows by load.
+impl AssignmentStrategy for RoutingRules {
+    fn rank(&self, ctx: &AssignmentContext) -> Vec<(i64, String)> {
+        let text = format!("{} {}", ctx.bug.title, ctx.bug.description.as_deref().unwrap_or("")).to_lowercase();
+        let component = ctx.bug.component.as_deref();
+
+        let mut scored: Vec<(&DeveloperSummary, bool, Vec<&str>, Vec<&str>)> = by_load(ctx.developers)
+            .into_iter()
+            .map(|d| {
+                let owns = component.is_some_and(|c| d.developer.components.iter().any(|owned| owned == c));
+                let keywords: Vec<&str> = ctx.config.rules.iter()
+                    .filter(|rule| rule.developer_id == d.developer.id)
+                    .flat_map(|rule| rule.keywords.iter())
+                    .map(|k| k.trim())
+                    .filter(|k| !k.is_empty() && text.contains(&k.to_lowercase()))
+                    .collect();
+                let skills: Vec<&str> = d.developer.skills.iter()
+                    .map(String::as_str)
+                    .filter(|skill| component == Some(*skill) || mentions(&text, skill))
+                    .collect();
+                (d, owns, keywords, skills)
+            })
+            .collect();
+        // Stable sort, so ties keep the lighter load first.
+        scored.sort_by_key(|(_, owns, keywords, skills)| {
+            std::cmp::Reverse((*owns, keywords.len(), skills.len()))
+        });
+
+        scored.into_iter()
+            .map(|(d, owns, keywords, skills)| {
+                let mut reasons = Vec::new();
+                if owns {
+                    reasons.push(format!("Owns component '{}'", component.unwrap_or_default()));
+                }
+                if !keywords.is_empty() {
+                    reasons.push(format!("Routing rule matched keyword(s): {}", keywords.join(", ")));
+                }
+                if !skills.is_empty() {
+                    reasons.push(format!("Has matching skill(s): {}", skills.join(", ")));
+                }
+                let reason = if reasons.is_empty() {
+                    format!("No component, rule or skill matched; {}", load_reason(d).to_lowercase())
+                } else {
+                    reasons.join("; ")
+                };
+                (d.developer.id, reason)
+            })
+            .collect()
+    }
+}
+
+fn by_load(developers: &[DeveloperSummary]) -> Vec<&DeveloperSummary> {
+    let mut sorted: Vec<&DeveloperSummary> = developers.iter().collect();
+    sorted.sort_by_key(|d| (d.weighted_load, d.open_bugs, d.developer.id));
+    sorted
+}
+
+fn load_reason(d: &DeveloperSummary) -> String {
+    format!("Weighted load {} across {} open bug(s)", d.weighted_load, d.open_bugs)
+}
+
+fn is_over_capacity(d: &DeveloperSummary) -> bool {
+    d.developer.max_open_bugs.is_some_and(|max| d.open_bugs >= max)
+}
+
+// Runs a strategy over the active developers and turns its ranking into
+// candidates. Developers at capacity keep their relative order but drop
+// below everyone who still has room.
+pub fn rank_candidates(
+    strategy: &dyn AssignmentStrategy,
+    bug: &Bug,
+    developers: &[DeveloperSummary],
+    config: &AssignmentConfig,
+    last_assigned: Option<i64>,
+) -> Vec<AssigneeCandidate> {
+    let active: Vec<DeveloperSummary> = developers.iter()
+        .filter(|d| d.developer.active)
+        .cloned()
+        .collect();
+    let ctx = AssignmentContext { bug, developers: &active, config, last_assigned };
+
+    let (mut candidates, full): (Vec<AssigneeCandidate>, Vec<AssigneeCandidate>) = strategy.rank(&ctx)
+        .into_iter()
+        .filter_map(|(id, reason)| {
+            let d = active.iter().find(|d| d.developer.id == id)?;
+            let over_capacity = is_over_capacity(d);
+            let reason = match d.developer.max_open_bugs {
+                Some(max) if over_capacity => format!("{} (at capacity: {}/{})", reason, d.open_bugs, max),
+                _ => reason,
+            };
+            Some(AssigneeCandidate {
+                rank: 0,
+                developer_id: id,
+                name: d.developer.name.clone(),
+                open_bugs: d.open_bugs,
+                weighted_load: d.weighted_load,
+                over_capacity,
+                reason,
+            })
+        })
+        .partition(|c| !c.over_capacity);
+
+    candidates.extend(full);
+    for (i, candidate) in candidates.iter_mut().enumerate() {
+        candidate.rank = i + 1;
+    }
+    candidates
+}
+
+pub struct AutoAssignment {
+    pub developer_id: i64,
+    pub reason: String,
+    pub warning: Option<String>,
+}
+
+// Assigns a freshly created bug using its project's strategy. Bugs that
+// already have a developer, have no project, or belong to a `manual`
+// project are left alone. Full developers are only picked under the
+// `warn` capacity policy, and only when nobody else has room.
+pub async fn auto_assign(app_state: &AppState, bug: &Bug) -> Result<Option<AutoAssignment>, sqlx::Error> {
+    let (Some(bug_id), None, Some(project_id)) = (bug.id, bug.developer_id, bug.project_id) else {
+        return Ok(None);
+    };
+    let Some(project) = app_state.get_project(project_id).await else {
+        return Ok(None);
+    };
+    let Some(strategy) = strategy_for(project.assignment.strategy) else {
+        return Ok(None);
+    };
+
+    // Held across the write so concurrent creates advance the rotation
+    // one developer at a time.
+    let mut round_robin = app_state.round_robin.lock().await;
+    let developers = database::get_developer_summaries(&app_state.db).await?;
+    let candidates = rank_candidates(
+        strategy,
+        bug,
+        &developers,
+        &project.assignment,
+        round_robin.get(&project_id).copied(),
+    );
+
+    let policy = app_state.config.capacity_policy;
+    for candidate in candidates {
+        if candidate.over_capacity && policy == CapacityPolicy::Refuse {
+            break;
+        }
+        match database::assign_bug(&app_state.db, bug_id, candidate.developer_id, policy).await? {
+            Ok(warning) => {
+                round_robin.insert(project_id, candidate.developer_id);
+                return Ok(Some(AutoAssignment {
+                    developer_id: candidate.developer_id,
+                    reason: candidate.reason,
+                    warning,
+                }));
+            }
+            Err(error) => {
+                eprintln!("⚠️  Skipping auto-assign candidate: {}", error.message());
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skills_match_whole_words_only() {
+        assert!(mentions("login fails in go client", "go"));
+        assert!(!mentions("good first issue", "go"));
+        assert!(!mentions("cargo build breaks", "go"));
+        assert!(mentions("a c++ crash", "c++"));
+        assert!(mentions("go", "go"));
+        assert!(!mentions("anything", ""));
+    }
+}
@@ -0,0 +1,282 @@
+use actix_multipart::Multipart;
+use actix_web::web;
+use futures_util::StreamExt;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use crate::database;
+use crate::models::Attachment;
+use crate::state::AppState;
+
+// Attachment files live under `ATTACHMENTS_DIR`, named by the SHA-256 of
+// their contents (`ab/abcdef...`), so identical uploads share one file.
+// Metadata rows point at the hash; a file is removed once no row does.
+// `AppState::blobs` keeps a removal from deleting a file that an upload
+// has just found already stored but not yet recorded.
+
+#[derive(Debug)]
+pub enum UploadError {
+    BugNotFound(i64),
+    NoFiles,
+    TooLarge { filename: String, limit: usize },
+    Multipart(String),
+    Storage(std::io::Error),
+    Database(sqlx::Error),
+}
+
+impl UploadError {
+    pub fn message(&self) -> String {
+        match self {
+            UploadError::BugNotFound(id) => format!("Bug #{} not found", id),
+            UploadError::NoFiles => "No files found in the upload".to_string(),
+            UploadError::TooLarge { filename, limit } => {
+                format!("{} is larger than the {} byte attachment limit", filename, limit)
+            }
+            UploadError::Multipart(e) => format!("Malformed upload: {}", e),
+            UploadError::Storage(_) => "Failed to store attachment".to_string(),
+            UploadError::Database(_) => "Failed to save attachment".to_string(),
+        }
+    }
+}
+
+impl From<sqlx::Error> for UploadError {
+    fn from(e: sqlx::Error) -> Self {
+        UploadError::Database(e)
+    }
+}
+
+// Reads every file field of a multipart upload, stores the contents and
+// records them against the bug in one transaction. Non-file fields are
+// ignored. If anything fails, files written by this upload that nothing
+// else references are removed again.
+pub async fn receive(
+    app_state: &AppState,
+    bug_id: i64,
+    mut payload: Multipart,
+) -> Result<Vec<Attachment>, UploadError> {
+    if database::get_bug_by_id(&app_state.db, bug_id).await?.is_none() {
+        return Err(UploadError::BugNotFound(bug_id));
+    }
+    let dir = &app_state.config.attachments_dir;
+    let limit = app_state.config.max_attachment_bytes;
+
+    let mut files = Vec::new();
+    let result = async {
+        let _writing = app_state.blobs.read().await;
+        while let Some(field) = payload.next().await {
+            let mut field = field.map_err(|e| UploadError::Multipart(e.to_string()))?;
+            let filename = field.content_disposition()
+                .and_then(|cd| cd.get_filename())
+                .map(sanitize_filename);
+
+            let mut bytes = Vec::new();
+            while let Some(chunk) = field.next().await {
+                let chunk = chunk.map_err(|e| UploadError::Multipart(e.to_string()))?;
+                if filename.is_some() && bytes.len() + chunk.len() > limit {
+                    return Err(UploadError::TooLarge { filename: filename.unwrap_or_default(), limit });
+                }
+                if filename.is_some() {
+                    bytes.extend_from_slice(&chunk);
+                }
+            }
+            let Some(filename) = filename else {
+                continue;
+            };
+
+            let sha256 = hex::encode(Sha256::digest(&bytes));
+            let content_type = sniff_content_type(&bytes);
+            let size = bytes.len() as i64;
+            write_blob(dir, &sha256, bytes).await.map_err(UploadError::Storage)?;
+            files.push((filename, content_type, size, sha256));
+        }
+        if files.is_empty() {
+            return Err(UploadError::NoFiles);
+        }
+        Ok(database::create_attachments(&app_state.db, bug_id, &files).await?)
+    }.await;
+
+    if result.is_err() {
+        discard_unreferenced(app_state, &files).await;
+    }
+    result
+}
+
+// Stores files that are already in memory, such as the attachments of an
+// inbound email, the same way `receive` stores an upload.
+pub async fn store(
+    app_state: &AppState,
+    bug_id: i64,
+    uploads: Vec<(String, Vec<u8>)>,
+) -> Result<Vec<Attachment>, UploadError> {
+    check_sizes(app_state, &uploads)?;
+    let dir = &app_state.config.attachments_dir;
+
+    let mut files = Vec::new();
+    let result = async {
+        let _writing = app_state.blobs.read().await;
+        for (filename, bytes) in uploads {
+            let sha256 = hex::encode(Sha256::digest(&bytes));
+            let content_type = sniff_content_type(&bytes);
+            let size = bytes.len() as i64;
+            write_blob(dir, &sha256, bytes).await.map_err(UploadError::Storage)?;
+            files.push((sanitize_filename(&filename), content_type, size, sha256));
+        }
+        Ok(database::create_attachments(&app_state.db, bug_id, &files).await?)
+    }.await;
+
+    if result.is_err() {
+        discard_unreferenced(app_state, &files).await;
+    }
+    result
+}
+
+// Lets callers refuse oversized files before saving anything else.
+pub fn check_sizes(app_state: &AppState, uploads: &[(String, Vec<u8>)]) -> Result<(), UploadError> {
+    let limit = app_state.config.max_attachment_bytes;
+    match uploads.iter().find(|(_, bytes)| bytes.len() > limit) {
+        Some((filename, _)) => Err(UploadError::TooLarge { filename: filename.clone(), limit }),
+        None => Ok(()),
+    }
+}
+
+// Removes files written for a failed upload that nothing else references.
+async fn discard_unreferenced(app_state: &AppState, files: &[(String, String, i64, String)]) {
+    let written: Vec<String> = files.iter().map(|(_, _, _, sha)| sha.clone()).collect();
+    remove_blobs(app_state, &written).await;
+}
+
+pub fn blob_path(dir: &Path, sha256: &str) -> PathBuf {
+    dir.join(&sha256[..2]).join(sha256)
+}
+
+// Writes to a temporary name first so a half-written file never sits at
+// the final path. Content that's already stored is left alone.
+async fn write_blob(dir: &Path, sha256: &str, bytes: Vec<u8>) -> std::io::Result<()> {
+    let path = blob_path(dir, sha256);
+    web::block(move || {
+        if path.exists() {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+        std::fs::write(&temp, bytes)?;
+        std::fs::rename(&temp, &path)
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+pub async fn read_blob(dir: &Path, sha256: &str) -> std::io::Result<Vec<u8>> {
+    let path = blob_path(dir, sha256);
+    web::block(move || std::fs::read(path))
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+// Deletes the stored files of `hashes` that no attachment row references.
+// References are counted again with uploads held off, since one may have
+// reused a file after the caller found it orphaned.
+pub async fn remove_blobs(app_state: &AppState, hashes: &[String]) {
+    if hashes.is_empty() {
+        return;
+    }
+    let _removing = app_state.blobs.write().await;
+    let orphaned = match database::unreferenced_hashes(&app_state.db, hashes).await {
+        Ok(orphaned) => orphaned,
+        Err(e) => {
+            eprintln!("⚠️  Failed to check attachment files before removing them: {}", e);
+            return;
+        }
+    };
+    let dir = &app_state.config.attachments_dir;
+    let paths: Vec<PathBuf> = orphaned.iter().map(|sha| blob_path(dir, sha)).collect();
+    let result = web::block(move || {
+        for path in paths {
+            match std::fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => eprintln!("⚠️  Failed to remove {}: {}", path.display(), e),
+            }
+        }
+    })
+    .await;
+    if let Err(e) = result {
+        eprintln!("⚠️  Failed to remove attachment files: {}", e);
+    }
+}
+
+// The browser-supplied type isn't trusted: known binary formats are
+// detected from their magic bytes, anything else that is valid UTF-8 is
+// plain text, and the rest is an opaque download.
+fn sniff_content_type(bytes: &[u8]) -> String {
+    if let Some(kind) = infer::get(bytes) {
+        kind.mime_type().to_string()
+    } else if std::str::from_utf8(bytes).is_ok() {
+        "text/plain; charset=utf-8".to_string()
+    } else {
+        "application/octet-stream".to_string()
+    }
+}
+
+// Raster images are shown in the browser; everything else downloads.
+pub fn is_inline(content_type: &str) -> bool {
+    matches!(content_type, "image/png" | "image/jpeg" | "image/gif" | "image/webp" | "image/bmp")
+}
+
+// Keeps only the final path component and drops characters that would
+// break a Content-Disposition header.
+fn sanitize_filename(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or("");
+    let cleaned: String = base.chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .take(255)
+        .collect();
+    let cleaned = cleaned.trim();
+    if cleaned.is_empty() || cleaned == "." || cleaned == ".." {
+        "attachment".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_filename_keeps_only_the_last_path_component() {
+        assert_eq!(sanitize_filename("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_filename("C:\\Users\\dana\\screen shot.png"), "screen shot.png");
+        assert_eq!(sanitize_filename("report.pdf"), "report.pdf");
+    }
+
+    #[test]
+    fn sanitize_filename_drops_header_breaking_characters() {
+        assert_eq!(sanitize_filename("a\"b\r\nc.txt"), "abc.txt");
+        assert_eq!(sanitize_filename("  spaced.txt  "), "spaced.txt");
+        assert_eq!(sanitize_filename(&"x".repeat(300)).len(), 255);
+    }
+
+    #[test]
+    fn sanitize_filename_falls_back_for_empty_names() {
+        for name in ["", "   ", ".", "..", "dir/", "dir/..", "\u{7}"] {
+            assert_eq!(sanitize_filename(name), "attachment", "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn content_type_is_sniffed_from_the_bytes() {
+        assert_eq!(sniff_content_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), "image/png");
+        assert_eq!(sniff_content_type("log line ✓\n".as_bytes()), "text/plain; charset=utf-8");
+        assert_eq!(sniff_content_type(&[0xff, 0xfe, 0x00, 0x80]), "application/octet-stream");
+        assert!(is_inline("image/png"));
+        assert!(!is_inline("image/svg+xml"));
+    }
+
+    #[test]
+    fn blobs_are_spread_over_directories_by_hash_prefix() {
+        let path = blob_path(Path::new("/data"), "abcdef");
+        assert_eq!(path, Path::new("/data/ab/abcdef"));
+    }
+}
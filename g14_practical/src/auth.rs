@@ -1,6 +1,7 @@
 use bcrypt::{hash, verify, DEFAULT_COST};
 use jsonwebtoken::{encode, decode, Header, EncodingKey, DecodingKey, Validation};
 use chrono::{Utc, Duration};
+use actix_web::HttpRequest;
 use crate::models::{User, Claims};
 
 pub fn hash_password(password: &str) -> Result<String, bcrypt::BcryptError> {
@@ -49,15 +50,33 @@ pub fn verify_jwt(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
     Ok(token_data.claims)
 }
 
+// Compares shared secrets without leaking how much of them matched.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// Reads and verifies the `Authorization: Bearer <token>` header. The error
+// is the message to send back with a 401.
+pub fn claims_from_request(req: &HttpRequest) -> Result<Claims, &'static str> {
+    let auth_header = match req.headers().get("Authorization") {
+        Some(header) => header.to_str().unwrap_or(""),
+        None => return Err("Missing Authorization header"),
+    };
+    let Some(token) = auth_header.strip_prefix("Bearer ") else {
+        return Err("Invalid Authorization format");
+    };
+    verify_jwt(token).map_err(|_| "Invalid or expired token")
+}
+
 pub async fn authenticate_user(
     pool: &sqlx::SqlitePool,
     username: &str,
     password: &str,
 ) -> Result<Option<User>, Box<dyn std::error::Error>> {
-    if let Some(user) = crate::database::get_user_by_username(pool, username).await? {
-        if verify_password(password, &user.password_hash)? {
-            return Ok(Some(user));
-        }
+    if let Some(user) = crate::database::get_user_by_username(pool, username).await?
+        && verify_password(password, &user.password_hash)?
+    {
+        return Ok(Some(user));
     }
     Ok(None)
 }
\ No newline at end of file
@@ -0,0 +1,103 @@
+use crate::models::{Board, BoardCell, BoardColumn, BoardSwimlane, Bug, Developer, Project, STATUSES};
+
+pub const GROUP_BY_STATUS: &str = "status";
+pub const GROUP_BY_DEVELOPER: &str = "developer";
+pub const UNASSIGNED_COLUMN: &str = "unassigned";
+
+pub fn parse_group_by(value: Option<&str>) -> Option<&'static str> {
+    match value {
+        None | Some(GROUP_BY_STATUS) => Some(GROUP_BY_STATUS),
+        Some(GROUP_BY_DEVELOPER) => Some(GROUP_BY_DEVELOPER),
+        Some(_) => None,
+    }
+}
+
+pub fn column_key(bug: &Bug, group_by: &str) -> String {
+    if group_by == GROUP_BY_DEVELOPER {
+        bug.developer_id
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| UNASSIGNED_COLUMN.to_string())
+    } else {
+        bug.status.clone()
+    }
+}
+
+// Lays bugs out as project swimlanes (rows) crossed with status or
+// developer columns. Only projects that have bugs get a lane; bugs without
+// a known project land in a trailing "No project" lane.
+pub fn build_board(
+    bugs: Vec<Bug>,
+    developers: &[Developer],
+    projects: &[Project],
+    group_by: &str,
+) -> Board {
+    let mut columns: Vec<BoardColumn> = if group_by == GROUP_BY_DEVELOPER {
+        developers.iter()
+            .filter(|d| d.active)
+            .map(|d| BoardColumn { key: d.id.to_string(), title: d.name.clone() })
+            .chain(std::iter::once(BoardColumn {
+                key: UNASSIGNED_COLUMN.to_string(),
+                title: "Unassigned".to_string(),
+            }))
+            .collect()
+    } else {
+        STATUSES.iter()
+            .map(|s| BoardColumn { key: s.to_string(), title: s.to_string() })
+            .collect()
+    };
+
+    // Keep bugs that point at an unlisted column (e.g. an inactive
+    // developer) visible instead of silently dropping them.
+    for bug in &bugs {
+        let key = column_key(bug, group_by);
+        if !columns.iter().any(|c| c.key == key) {
+            let title = if group_by == GROUP_BY_DEVELOPER {
+                developers.iter()
+                    .find(|d| d.id.to_string() == key)
+                    .map(|d| format!("{} (inactive)", d.name))
+                    .unwrap_or_else(|| format!("Developer #{}", key))
+            } else {
+                key.clone()
+            };
+            columns.push(BoardColumn { key, title });
+        }
+    }
+
+    let mut lanes: Vec<(Option<i64>, String)> = projects.iter()
+        .map(|p| (Some(p.id), p.name.clone()))
+        .collect();
+    lanes.push((None, "No project".to_string()));
+
+    let swimlanes = lanes.into_iter()
+        .filter_map(|(project_id, project_name)| {
+            let lane_bugs: Vec<&Bug> = bugs.iter()
+                .filter(|b| lane_for(b, projects) == project_id)
+                .collect();
+            if lane_bugs.is_empty() {
+                return None;
+            }
+
+            let cells = columns.iter()
+                .map(|column| BoardCell {
+                    column: column.key.clone(),
+                    bugs: lane_bugs.iter()
+                        .filter(|b| column_key(b, group_by) == column.key)
+                        .map(|b| (*b).clone())
+                        .collect(),
+                })
+                .collect();
+
+            Some(BoardSwimlane { project_id, project_name, cells })
+        })
+        .collect();
+
+    Board {
+        group_by: group_by.to_string(),
+        columns,
+        swimlanes,
+    }
+}
+
+fn lane_for(bug: &Bug, projects: &[Project]) -> Option<i64> {
+    bug.project_id.filter(|id| projects.iter().any(|p| p.id == *id))
+}
@@ -0,0 +1,158 @@
+use crate::assignment::{self, AutoAssignment};
+use crate::database::{self, StatusChange};
+use crate::events::{self, EventKind};
+use crate::models::{
+    canonical_status, normalize_component, validate_bug_fields, validate_component, AssignmentError, Bug,
+    FieldError, NewBug, STATUSES,
+};
+use crate::state::AppState;
+
+// Creating, saving and moving bugs, shared by the REST API, the web forms,
+// GraphQL and the integrations so they check the same things. The callers
+// only turn the outcome into their own kind of response.
+
+#[derive(Debug)]
+pub enum BugError {
+    // The first field that failed validation.
+    Invalid(FieldError),
+    NotFound,
+    Assignment(AssignmentError),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for BugError {
+    fn from(e: sqlx::Error) -> Self {
+        BugError::Database(e)
+    }
+}
+
+pub struct CreatedBug {
+    pub bug: Bug,
+    // Set when the project's strategy picked a developer.
+    pub assigned: Option<AutoAssignment>,
+}
+
+pub struct UpdatedBug {
+    pub bug: Bug,
+    // The capacity warning under the `warn` policy.
+    pub warning: Option<String>,
+}
+
+// Validates and inserts a new bug, publishes `bug.created`, then
+// auto-assigns it by its project's strategy (publishing `bug.assigned`).
+// A failed auto-assignment is logged and leaves the bug unassigned.
+pub async fn create(app_state: &AppState, new_bug: &NewBug) -> Result<CreatedBug, BugError> {
+    let projects = app_state.projects.read().await.clone();
+    if let Some(error) = new_bug.validate(&projects).into_iter().next() {
+        return Err(BugError::Invalid(error));
+    }
+
+    let mut bug = database::create_bug(&app_state.db, new_bug).await?;
+    let bug_id = bug.id.unwrap_or_default();
+    println!("✅ Created bug #{}: {}", bug_id, bug.title);
+    events::bug_event(app_state, EventKind::BugCreated, &bug).await;
+
+    let assigned = match assignment::auto_assign(app_state, &bug).await {
+        Ok(Some(assigned)) => {
+            println!("🤖 Auto-assigned bug #{} to developer #{}: {}", bug_id, assigned.developer_id, assigned.reason);
+            if let Some(warning) = &assigned.warning {
+                println!("⚠️  {}", warning);
+            }
+            bug.developer_id = Some(assigned.developer_id);
+            events::bug_event(app_state, EventKind::BugAssigned, &bug).await;
+            Some(assigned)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            eprintln!("❌ Auto-assignment failed for bug #{}: {}", bug_id, e);
+            None
+        }
+    };
+    Ok(CreatedBug { bug, assigned })
+}
+
+// Validates and saves every field of `bug`, including its developer under
+// the capacity policy, and publishes `bug.updated` (plus `bug.assigned`
+// when the developer changed).
+pub async fn update(app_state: &AppState, bug_id: i64, bug: &Bug) -> Result<UpdatedBug, BugError> {
+    if let Some(error) = validate_bug_fields(&bug.title, bug.severity.as_deref()).into_iter().next() {
+        return Err(BugError::Invalid(error));
+    }
+    let projects = app_state.projects.read().await.clone();
+    if let Some(project_id) = bug.project_id
+        && !projects.iter().any(|p| p.id == project_id)
+    {
+        return Err(BugError::Invalid(FieldError::new(
+            "project_id",
+            &format!("Project #{} does not exist", project_id),
+        )));
+    }
+    // A component the project has since dropped stays valid until the
+    // bug's component or project is changed.
+    let Some(current) = database::get_bug_by_id(&app_state.db, bug_id).await? else {
+        return Err(BugError::NotFound);
+    };
+    let component = normalize_component(bug.component.as_deref());
+    if (bug.project_id != current.project_id || component != current.component)
+        && let Some(message) = validate_component(&projects, bug.project_id, component.as_deref())
+    {
+        return Err(BugError::Invalid(FieldError::new("component", &message)));
+    }
+
+    let saved = match database::update_bug(&app_state.db, bug_id, bug, app_state.config.capacity_policy).await? {
+        Ok(saved) => saved,
+        Err(AssignmentError::BugNotFound(_)) => return Err(BugError::NotFound),
+        Err(error) => return Err(BugError::Assignment(error)),
+    };
+    let database::SavedBug { bug: updated, previous_developer_id, warning } = saved;
+    println!("✏️  Updated bug #{}", bug_id);
+    if let Some(warning) = &warning {
+        println!("⚠️  {}", warning);
+    }
+    events::bug_event(app_state, EventKind::BugUpdated, &updated).await;
+    if updated.developer_id.is_some() && updated.developer_id != previous_developer_id {
+        events::bug_event(app_state, EventKind::BugAssigned, &updated).await;
+    }
+    Ok(UpdatedBug { bug: updated, warning })
+}
+
+pub enum TransitionOutcome {
+    // The bug as saved, and the status it left.
+    Moved(Box<Bug>, String),
+    NotFound,
+    Rejected(String),
+    // Reopening the bug would put its developer over capacity.
+    Refused(AssignmentError),
+}
+
+// Moves a bug along the workflow if `requested` (any case) is allowed from
+// its current status, checking its developer's capacity when it reopens.
+// Publishing the change is left to the caller.
+pub async fn transition(
+    app_state: &AppState,
+    bug_id: i64,
+    requested: &str,
+) -> Result<TransitionOutcome, sqlx::Error> {
+    let Some(status) = canonical_status(requested) else {
+        if database::get_bug_by_id(&app_state.db, bug_id).await?.is_none() {
+            return Ok(TransitionOutcome::NotFound);
+        }
+        return Ok(TransitionOutcome::Rejected(format!(
+            "Unknown status '{}'; expected one of: {}", requested, STATUSES.join(", ")
+        )));
+    };
+
+    match database::transition_bug(&app_state.db, bug_id, status, app_state.config.capacity_policy).await? {
+        StatusChange::Moved { bug, from, warning } => {
+            if let Some(warning) = &warning {
+                println!("⚠️  {}", warning);
+            }
+            Ok(TransitionOutcome::Moved(bug, from))
+        }
+        StatusChange::NotFound => Ok(TransitionOutcome::NotFound),
+        StatusChange::NotAllowed { from } => Ok(TransitionOutcome::Rejected(format!(
+            "Cannot move bug #{} from {} to {}", bug_id, from, status
+        ))),
+        StatusChange::Refused(error) => Ok(TransitionOutcome::Refused(error)),
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+use std::io::Read;
+use crate::attachments::UploadError;
+use crate::config::Config;
+use crate::database;
+use crate::import::mapping::{Defaults, MappingFile, Mapper, Unmapped};
+use crate::import::{self, bugzilla, github, jira, TrackerImport};
+use crate::inbound::{self, InboundError, InboundOutcome};
+use crate::models::ExternalBug;
+use crate::state::AppState;
+
+// Command-line tools that work on the database directly instead of
+// starting the server: `g14_practical <command> [arguments]`. Each returns
+// the process exit code.
+
+const USAGE: &str = "\
+Usage: g14_practical [command]
+
+Without a command the web server starts.
+
+Commands:
+  import-github <issues.json> [--comments comments.json] [--repo owner/name]
+                [--project ID] [--dry-run]
+      Import a GitHub Issues JSON export. Re-importing updates bugs and
+      comments that were imported before instead of duplicating them.
+
+  import-bugzilla <bug.xml>... [--mapping mapping.json] [--source name]
+                  [--project ID] [--dry-run]
+      Import Bugzilla show_bug.cgi?ctype=xml files. --source names the
+      Bugzilla instance when the files have no urlbase.
+
+  import-jira <export.csv>... [--mapping mapping.json] [--project ID] [--dry-run]
+      Import Jira CSV exports (all fields).
+
+  receive-email [message.eml] [--project ID]
+      Open a bug from a raw email, or add it as a comment when the subject
+      has a [Bug #N] token. Reads standard input without a file, so an MTA
+      can pipe mail to it. Exits 65 for unreadable mail, 77 for a rejected
+      sender and 75 when it should be retried later.
+
+  The mapping file is JSON with optional \"severity\", \"status\" and
+  \"people\" objects mapping the tracker's values onto ours; see README.txt.
+  Values with no mapping are listed after the import.
+";
+
+pub async fn run(args: &[String]) -> i32 {
+    match args[0].as_str() {
+        "import-github" => exit_code(try_import_github(&args[1..]).await),
+        "import-bugzilla" => exit_code(try_import_tracker(Tracker::Bugzilla, &args[1..]).await),
+        "import-jira" => exit_code(try_import_tracker(Tracker::Jira, &args[1..]).await),
+        "receive-email" => receive_email(&args[1..]).await,
+        "help" | "--help" | "-h" => {
+            print!("{}", USAGE);
+            0
+        }
+        other => {
+            eprintln!("❌ Unknown command '{}'\n", other);
+            eprint!("{}", USAGE);
+            2
+        }
+    }
+}
+
+struct Arguments {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Arguments {
+    // `--name value` options, plus the `--name` switches listed in `flags`.
+    fn parse(args: &[String], flags: &[&str]) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                positional.push(arg.clone());
+                continue;
+            };
+            let value = if flags.contains(&name) {
+                String::new()
+            } else {
+                args.next().ok_or_else(|| format!("--{} needs a value", name))?.clone()
+            };
+            options.insert(name.to_string(), value);
+        }
+        Ok(Self { positional, options })
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+}
+
+fn read_file(path: &str) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| format!("Can't read {}: {}", path, e))
+}
+
+fn exit_code(result: Result<(), String>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(message) => {
+            eprintln!("❌ {}", message);
+            1
+        }
+    }
+}
+
+fn project_option(args: &Arguments) -> Result<Option<i64>, String> {
+    args.option("project")
+        .map(|id| id.parse::<i64>().map_err(|_| format!("--project '{}' is not a number", id)))
+        .transpose()
+}
+
+async fn open_state(project_id: Option<i64>) -> Result<AppState, String> {
+    let db = database::create_connection().await.map_err(|e| format!("Database error: {}", e))?;
+    let app_state = AppState::new(db, Config::from_env());
+    app_state.load_project_settings().await.map_err(|e| format!("Database error: {}", e))?;
+    if let Some(project_id) = project_id
+        && !app_state.has_project(project_id).await
+    {
+        return Err(format!("Project #{} does not exist", project_id));
+    }
+    Ok(app_state)
+}
+
+#[derive(Default)]
+struct Saved {
+    created: usize,
+    updated: usize,
+    comments: usize,
+}
+
+// Saves the bugs in batches, or on a dry run only counts which are new.
+async fn save_external_bugs(app_state: &AppState, bugs: &[ExternalBug], dry_run: bool) -> Result<Saved, String> {
+    let mut saved = Saved::default();
+    if dry_run {
+        let refs: Vec<String> = bugs.iter().map(|b| b.external_ref.clone()).collect();
+        let existing = database::existing_external_refs(&app_state.db, &refs).await
+            .map_err(|e| format!("Database error: {}", e))?;
+        saved.updated = existing.len();
+        saved.created = refs.len() - saved.updated;
+        saved.comments = bugs.iter().map(|b| b.comments.len()).sum();
+        return Ok(saved);
+    }
+    for batch in bugs.chunks(import::BATCH_SIZE) {
+        let batch = database::save_external_bugs(&app_state.db, batch).await
+            .map_err(|e| format!("Import failed after {} bug(s): {}", saved.created + saved.updated, e))?;
+        for bug in batch {
+            if bug.created { saved.created += 1 } else { saved.updated += 1 }
+            saved.comments += bug.new_comments;
+        }
+    }
+    Ok(saved)
+}
+
+fn print_saved(what: &str, saved: &Saved, dry_run: bool) {
+    if dry_run {
+        println!("📥 Would import {} {}: {} new, {} already imported, {} comment(s)",
+            saved.created + saved.updated, what, saved.created, saved.updated, saved.comments);
+    } else {
+        println!("📥 Imported {} {}: {} new, {} updated, {} new comment(s)",
+            saved.created + saved.updated, what, saved.created, saved.updated, saved.comments);
+    }
+}
+
+async fn try_import_github(args: &[String]) -> Result<(), String> {
+    let args = Arguments::parse(args, &["dry-run"])?;
+    let [issues_path] = args.positional.as_slice() else {
+        return Err("import-github takes exactly one issues file".to_string());
+    };
+    let issues_json = read_file(issues_path)?;
+    let comments_json = args.option("comments").map(read_file).transpose()?;
+    let project_id = project_option(&args)?;
+    let dry_run = args.flag("dry-run");
+
+    let app_state = open_state(project_id).await?;
+    let developers = database::get_all_developers(&app_state.db).await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let converted = github::convert(
+        &issues_json, comments_json.as_deref(), args.option("repo"), &developers, project_id,
+    )?;
+
+    let saved = save_external_bugs(&app_state, &converted.bugs, dry_run).await?;
+    print_saved("GitHub issue(s)", &saved, dry_run);
+    if converted.skipped_pull_requests > 0 {
+        println!("   Skipped {} pull request(s)", converted.skipped_pull_requests);
+    }
+    if converted.orphaned_comments > 0 {
+        println!("   Skipped {} comment(s) on issues not in the issues file", converted.orphaned_comments);
+    }
+    for skipped in &converted.skipped {
+        println!("   ⚠️  Skipped {}", skipped);
+    }
+    if !converted.unmatched_assignees.is_empty() {
+        let logins: Vec<&str> = converted.unmatched_assignees.iter().map(String::as_str).collect();
+        println!("   ⚠️  No active developer named: {} (left unassigned)", logins.join(", "));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy)]
+enum Tracker {
+    Bugzilla,
+    Jira,
+}
+
+impl Tracker {
+    fn command(self) -> &'static str {
+        match self {
+            Tracker::Bugzilla => "import-bugzilla",
+            Tracker::Jira => "import-jira",
+        }
+    }
+
+    fn defaults(self) -> &'static Defaults {
+        match self {
+            Tracker::Bugzilla => &bugzilla::DEFAULTS,
+            Tracker::Jira => &jira::DEFAULTS,
+        }
+    }
+}
+
+async fn try_import_tracker(tracker: Tracker, args: &[String]) -> Result<(), String> {
+    let args = Arguments::parse(args, &["dry-run"])?;
+    if args.positional.is_empty() {
+        return Err(format!("{} needs at least one export file", tracker.command()));
+    }
+    let mapping = match args.option("mapping") {
+        Some(path) => MappingFile::parse(&read_file(path)?)?,
+        None => MappingFile::default(),
+    };
+    let project_id = project_option(&args)?;
+    let dry_run = args.flag("dry-run");
+
+    let app_state = open_state(project_id).await?;
+    let developers = database::get_all_developers(&app_state.db).await
+        .map_err(|e| format!("Database error: {}", e))?;
+    let usernames = database::get_usernames(&app_state.db).await
+        .map_err(|e| format!("Database error: {}", e))?;
+    let mut mapper = Mapper::new(tracker.defaults(), mapping, &developers, &usernames);
+
+    let mut converted = TrackerImport::default();
+    for path in &args.positional {
+        let file = match tracker {
+            Tracker::Bugzilla => bugzilla::convert(&read_file(path)?, args.option("source"), &mut mapper, project_id),
+            Tracker::Jira => {
+                let csv = std::fs::read(path).map_err(|e| format!("Can't read {}: {}", path, e))?;
+                jira::convert(&csv, &mut mapper, project_id)
+            }
+        }.map_err(|e| format!("{}: {}", path, e))?;
+        converted.bugs.extend(file.bugs);
+        converted.skipped.extend(file.skipped);
+    }
+
+    let saved = save_external_bugs(&app_state, &converted.bugs, dry_run).await?;
+    let what = match tracker {
+        Tracker::Bugzilla => "Bugzilla bug(s)",
+        Tracker::Jira => "Jira issue(s)",
+    };
+    print_saved(what, &saved, dry_run);
+    for skipped in &converted.skipped {
+        println!("   ⚠️  Skipped {}", skipped);
+    }
+    print_unmapped(&mapper.unmapped);
+    Ok(())
+}
+
+fn print_unmapped(unmapped: &Unmapped) {
+    if unmapped.is_empty() {
+        return;
+    }
+    println!("   Unmapped values (add them to a --mapping file):");
+    let sections = [
+        ("severity", &unmapped.severities, "imported as Medium"),
+        ("status", &unmapped.statuses, "imported as Open"),
+        ("assignee", &unmapped.assignees, "no active developer; left unassigned"),
+        ("reporter", &unmapped.reporters, "no user account; kept as written"),
+    ];
+    for (name, values, fallback) in sections {
+        if values.is_empty() {
+            continue;
+        }
+        let values: Vec<String> = values.iter().map(|(value, count)| format!("'{}' ×{}", value, count)).collect();
+        println!("   ⚠️  {} ({}): {}", name, fallback, values.join(", "));
+    }
+}
+
+// Exit codes from sysexits.h, which MTAs use to bounce or retry a message.
+const EX_DATAERR: i32 = 65;
+const EX_TEMPFAIL: i32 = 75;
+const EX_NOPERM: i32 = 77;
+
+async fn receive_email(args: &[String]) -> i32 {
+    let (raw, project_id) = match read_email_arguments(args) {
+        Ok(read) => read,
+        Err(message) => {
+            eprintln!("❌ {}", message);
+            return EX_DATAERR;
+        }
+    };
+    let app_state = match open_state(project_id).await {
+        Ok(app_state) => app_state,
+        Err(message) => {
+            eprintln!("❌ {}", message);
+            return EX_TEMPFAIL;
+        }
+    };
+
+    match inbound::receive(&app_state, &raw, project_id).await {
+        Ok(InboundOutcome::Created { bug, attachments }) => {
+            println!("📧 Opened bug #{} with {} attachment(s)", bug.id.unwrap_or(0), attachments.len());
+            0
+        }
+        Ok(InboundOutcome::Commented { comment, attachments }) => {
+            println!("📧 Added comment #{} to bug #{} with {} attachment(s)", comment.id, comment.bug_id, attachments.len());
+            0
+        }
+        Ok(InboundOutcome::Duplicate { bug_id }) => {
+            println!("📧 Already received (bug #{}); nothing saved", bug_id);
+            0
+        }
+        Err(error) => {
+            eprintln!("❌ {}", error.message());
+            match error {
+                InboundError::Unreadable(_) | InboundError::Empty => EX_DATAERR,
+                InboundError::Upload(UploadError::TooLarge { .. }) => EX_DATAERR,
+                InboundError::UnknownSender(_) => EX_NOPERM,
+                InboundError::Upload(_) => EX_TEMPFAIL,
+                InboundError::Database(e) => {
+                    eprintln!("   {}", e);
+                    EX_TEMPFAIL
+                }
+            }
+        }
+    }
+}
+
+fn read_email_arguments(args: &[String]) -> Result<(Vec<u8>, Option<i64>), String> {
+    let args = Arguments::parse(args, &[])?;
+    let raw = match args.positional.as_slice() {
+        [] => {
+            let mut raw = Vec::new();
+            std::io::stdin().read_to_end(&mut raw).map_err(|e| format!("Can't read standard input: {}", e))?;
+            raw
+        }
+        [path] => std::fs::read(path).map_err(|e| format!("Can't read {}: {}", path, e))?,
+        _ => return Err("receive-email takes at most one message file".to_string()),
+    };
+    Ok((raw, project_option(&args)?))
+}
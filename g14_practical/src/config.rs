@@ -0,0 +1,227 @@
+// Runtime settings read from the environment (and `.env`) at startup.
+
+use std::path::PathBuf;
+use crate::models::canonical_status;
+
+// What `assign_bug` does when a developer is already at their
+// `max_open_bugs` capacity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CapacityPolicy {
+    Warn,
+    Refuse,
+}
+
+// How notification emails reach the SMTP server.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmtpTls {
+    // Plain connection, e.g. a local MailHog.
+    None,
+    StartTls,
+    // TLS from the start (usually port 465).
+    Tls,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    // Both or neither.
+    pub credentials: Option<(String, String)>,
+    pub from: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub capacity_policy: CapacityPolicy,
+    // Where attachment files are stored, named by their SHA-256.
+    pub attachments_dir: PathBuf,
+    pub max_attachment_bytes: usize,
+    // How long deleted bugs stay in the trash before an admin purge
+    // removes them for good.
+    pub trash_retention_days: i64,
+    // Webhook deliveries are retried with exponential backoff starting at
+    // `webhook_retry_base_seconds`, and given up after `webhook_max_attempts`.
+    pub webhook_max_attempts: i64,
+    pub webhook_retry_base_seconds: i64,
+    // Email notifications are off unless `SMTP_HOST` is set.
+    pub smtp: Option<SmtpConfig>,
+    // Developers on digest delivery get at most one email per this many
+    // minutes.
+    pub notification_digest_minutes: i64,
+    // Where the tracker is reached from outside, for links in emails.
+    pub public_url: String,
+    // Inbound email: with `inbound_reject_unknown`, only senders matching
+    // `inbound_allowed_senders` (addresses or `@domain`) or a developer's
+    // notification address may open bugs or comment.
+    pub inbound_reject_unknown: bool,
+    pub inbound_allowed_senders: Vec<String>,
+    // `POST /inbound/email` requires it in `X-Inbound-Token`; while it is
+    // unset the endpoint is off.
+    pub inbound_email_token: Option<String>,
+    // Verifies push webhooks from GitHub, Gitea and GitLab; the push
+    // endpoint refuses everything while it is unset.
+    pub git_webhook_secret: Option<String>,
+    // Where a `fixes #N` commit on the default branch moves the bug.
+    pub git_fix_status: &'static str,
+}
+
+const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+
+const DEFAULT_WEBHOOK_MAX_ATTEMPTS: i64 = 8;
+
+const DEFAULT_WEBHOOK_RETRY_BASE_SECONDS: i64 = 30;
+
+const DEFAULT_NOTIFICATION_DIGEST_MINUTES: i64 = 60;
+
+const DEFAULT_GIT_FIX_STATUS: &str = "Resolved";
+
+const DEFAULT_MAX_ATTACHMENT_BYTES: usize = 10 * 1024 * 1024;
+
+impl Config {
+    pub fn from_env() -> Self {
+        dotenv::dotenv().ok();
+
+        let capacity_policy = match std::env::var("CAPACITY_POLICY").as_deref() {
+            Ok("refuse") => CapacityPolicy::Refuse,
+            Ok("warn") | Err(_) => CapacityPolicy::Warn,
+            Ok(other) => {
+                eprintln!("⚠️  Unknown CAPACITY_POLICY '{}', falling back to 'warn'", other);
+                CapacityPolicy::Warn
+            }
+        };
+
+        let attachments_dir = std::env::var("ATTACHMENTS_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from("attachments"));
+
+        let max_attachment_bytes = match std::env::var("MAX_ATTACHMENT_BYTES") {
+            Err(_) => DEFAULT_MAX_ATTACHMENT_BYTES,
+            Ok(value) => value.parse().unwrap_or_else(|_| {
+                eprintln!("⚠️  Invalid MAX_ATTACHMENT_BYTES '{}', using {}", value, DEFAULT_MAX_ATTACHMENT_BYTES);
+                DEFAULT_MAX_ATTACHMENT_BYTES
+            }),
+        };
+
+        let trash_retention_days = match std::env::var("TRASH_RETENTION_DAYS") {
+            Err(_) => DEFAULT_TRASH_RETENTION_DAYS,
+            Ok(value) => value.parse().ok().filter(|days| *days >= 0).unwrap_or_else(|| {
+                eprintln!("⚠️  Invalid TRASH_RETENTION_DAYS '{}', using {}", value, DEFAULT_TRASH_RETENTION_DAYS);
+                DEFAULT_TRASH_RETENTION_DAYS
+            }),
+        };
+
+        let webhook_max_attempts = match std::env::var("WEBHOOK_MAX_ATTEMPTS") {
+            Err(_) => DEFAULT_WEBHOOK_MAX_ATTEMPTS,
+            Ok(value) => value.parse().ok().filter(|attempts| *attempts >= 1).unwrap_or_else(|| {
+                eprintln!("⚠️  Invalid WEBHOOK_MAX_ATTEMPTS '{}', using {}", value, DEFAULT_WEBHOOK_MAX_ATTEMPTS);
+                DEFAULT_WEBHOOK_MAX_ATTEMPTS
+            }),
+        };
+
+        let webhook_retry_base_seconds = match std::env::var("WEBHOOK_RETRY_BASE_SECONDS") {
+            Err(_) => DEFAULT_WEBHOOK_RETRY_BASE_SECONDS,
+            Ok(value) => value.parse().ok().filter(|seconds| *seconds >= 1).unwrap_or_else(|| {
+                eprintln!("⚠️  Invalid WEBHOOK_RETRY_BASE_SECONDS '{}', using {}", value, DEFAULT_WEBHOOK_RETRY_BASE_SECONDS);
+                DEFAULT_WEBHOOK_RETRY_BASE_SECONDS
+            }),
+        };
+
+        let notification_digest_minutes = match std::env::var("NOTIFY_DIGEST_MINUTES") {
+            Err(_) => DEFAULT_NOTIFICATION_DIGEST_MINUTES,
+            Ok(value) => value.parse().ok().filter(|minutes| *minutes >= 1).unwrap_or_else(|| {
+                eprintln!("⚠️  Invalid NOTIFY_DIGEST_MINUTES '{}', using {}", value, DEFAULT_NOTIFICATION_DIGEST_MINUTES);
+                DEFAULT_NOTIFICATION_DIGEST_MINUTES
+            }),
+        };
+
+        let public_url = std::env::var("PUBLIC_URL")
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|_| "http://127.0.0.1:8080".to_string());
+
+        let inbound_reject_unknown = match std::env::var("INBOUND_REJECT_UNKNOWN").as_deref() {
+            Ok("true") => true,
+            Ok("false") | Err(_) => false,
+            Ok(other) => {
+                eprintln!("⚠️  Invalid INBOUND_REJECT_UNKNOWN '{}', falling back to 'false'", other);
+                false
+            }
+        };
+
+        let inbound_allowed_senders = std::env::var("INBOUND_ALLOWED_SENDERS")
+            .map(|senders| {
+                senders.split(',')
+                    .map(|sender| sender.trim().to_lowercase())
+                    .filter(|sender| !sender.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let inbound_email_token = std::env::var("INBOUND_EMAIL_TOKEN").ok().filter(|token| !token.is_empty());
+
+        let git_webhook_secret = std::env::var("GIT_WEBHOOK_SECRET").ok().filter(|secret| !secret.is_empty());
+
+        let git_fix_status = match std::env::var("GIT_FIX_STATUS") {
+            Err(_) => DEFAULT_GIT_FIX_STATUS,
+            Ok(value) => canonical_status(&value).unwrap_or_else(|| {
+                eprintln!("⚠️  Invalid GIT_FIX_STATUS '{}', using {}", value, DEFAULT_GIT_FIX_STATUS);
+                DEFAULT_GIT_FIX_STATUS
+            }),
+        };
+
+        Self {
+            capacity_policy,
+            attachments_dir,
+            max_attachment_bytes,
+            trash_retention_days,
+            webhook_max_attempts,
+            webhook_retry_base_seconds,
+            smtp: smtp_from_env(),
+            notification_digest_minutes,
+            public_url,
+            inbound_reject_unknown,
+            inbound_allowed_senders,
+            inbound_email_token,
+            git_webhook_secret,
+            git_fix_status,
+        }
+    }
+}
+
+fn smtp_from_env() -> Option<SmtpConfig> {
+    let host = std::env::var("SMTP_HOST").ok().filter(|host| !host.trim().is_empty())?;
+
+    let tls = match std::env::var("SMTP_TLS").as_deref() {
+        Ok("none") => SmtpTls::None,
+        Ok("starttls") | Err(_) => SmtpTls::StartTls,
+        Ok("tls") => SmtpTls::Tls,
+        Ok(other) => {
+            eprintln!("⚠️  Unknown SMTP_TLS '{}', falling back to 'starttls'", other);
+            SmtpTls::StartTls
+        }
+    };
+    let default_port = match tls {
+        SmtpTls::None | SmtpTls::StartTls => 587,
+        SmtpTls::Tls => 465,
+    };
+    let port = match std::env::var("SMTP_PORT") {
+        Err(_) => default_port,
+        Ok(value) => value.parse().unwrap_or_else(|_| {
+            eprintln!("⚠️  Invalid SMTP_PORT '{}', using {}", value, default_port);
+            default_port
+        }),
+    };
+
+    let credentials = match (std::env::var("SMTP_USERNAME"), std::env::var("SMTP_PASSWORD")) {
+        (Ok(username), Ok(password)) => Some((username, password)),
+        (Err(_), Err(_)) => None,
+        _ => {
+            eprintln!("⚠️  SMTP_USERNAME and SMTP_PASSWORD must be set together; connecting without login");
+            None
+        }
+    };
+
+    let from = std::env::var("SMTP_FROM").unwrap_or_else(|_| "Bug Tracker <bugs@localhost>".to_string());
+
+    Some(SmtpConfig { host: host.trim().to_string(), port, tls, credentials, from })
+}
@@ -1,5 +1,20 @@
-use sqlx::SqlitePool;
-use crate::models::{Bug, NewBug, Developer, NewDeveloper, User};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::types::Json;
+use sqlx::{Connection, Executor, QueryBuilder, Row, Sqlite, SqlitePool};
+use std::str::FromStr;
+use crate::config::CapacityPolicy;
+use crate::models::{
+    canonical_severity, capacity_message, severity_weight, AssignmentError, Attachment, Bug, BugFilter, BugLinkView, DeletePolicy, Developer,
+    DeveloperSummary, DeveloperWorkload, Label, NewBug, NewDeveloper, NewLabel, OrphanedReference,
+    UpdateDeveloper, UpdateLabel, User, normalize_component, normalize_tags, split_label_names,
+    DEFAULT_LABEL_COLOR, OPEN_STATUSES, ExportRow, LinkError, LinkKind, NewBugLink, BulkItemResult, BulkOperation,
+    BulkResult, allowed_transitions, Comment, ExternalBug, SavedExternalBug, Webhook, UpdateWebhook,
+    WebhookDelivery, DueWebhookDelivery, WebhookAttempt, NotificationSettings, PendingNotification, LoggedEvent,
+    AssignmentConfig, ProjectSettings,
+};
+use std::collections::{BTreeMap, HashMap};
+use futures_util::TryStreamExt;
+use tokio::sync::mpsc;
 use bcrypt::{hash, DEFAULT_COST};
 
 pub async fn create_connection() -> Result<SqlitePool, sqlx::Error> {
@@ -7,23 +22,12 @@ pub async fn create_connection() -> Result<SqlitePool, sqlx::Error> {
     let database_url = std::env::var("DATABASE_URL")
         .unwrap_or_else(|_| "sqlite:bugs.db".to_string());
     
-    let pool = SqlitePool::connect(&database_url).await?;
+    let options = SqliteConnectOptions::from_str(&database_url)?
+        .create_if_missing(true)
+        .foreign_keys(true);
+    let pool = SqlitePool::connect_with(options).await?;
     
-    // Create your existing tables (bugs and developers already exist)
-    
-    // Add users table for authentication
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS users (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            username TEXT UNIQUE NOT NULL,
-            password_hash TEXT NOT NULL,
-            role TEXT DEFAULT 'developer'
-        )
-        "#,
-    )
-    .execute(&pool)
-    .await?;
+    run_migrations(&pool).await?;
 
     // Insert default users if they don't exist
     let admin_hash = hash("admin123", DEFAULT_COST).unwrap();
@@ -60,14 +64,319 @@ pub async fn create_connection() -> Result<SqlitePool, sqlx::Error> {
     Ok(pool)
 }
 
+// Schema changes, applied in order. `PRAGMA user_version` stores how many
+// have already run, so existing databases are upgraded in place.
+const MIGRATIONS: &[&str] = &[
+    // 1: bugs and developers (schema.sql) plus users for authentication
+    r#"
+    CREATE TABLE IF NOT EXISTS bugs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        title TEXT NOT NULL,
+        description TEXT,
+        reported_by TEXT,
+        severity TEXT,
+        developer_id INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS developers (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS users (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        username TEXT UNIQUE NOT NULL,
+        password_hash TEXT NOT NULL,
+        role TEXT DEFAULT 'developer'
+    );
+    "#,
+    // 2: workflow status and owning project for the board
+    r#"
+    ALTER TABLE bugs ADD COLUMN status TEXT NOT NULL DEFAULT 'Open';
+    ALTER TABLE bugs ADD COLUMN project_id INTEGER;
+    "#,
+    // 3: soft deactivation of developers
+    r#"
+    ALTER TABLE developers ADD COLUMN active INTEGER NOT NULL DEFAULT 1;
+    "#,
+    // 4: bugs.developer_id becomes a real foreign key. SQLite can't add a
+    // constraint in place, so the table is rebuilt. Existing orphans are
+    // copied as-is and reported by `check_integrity` on startup.
+    r#"
+    CREATE TABLE bugs_new (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        title TEXT NOT NULL,
+        description TEXT,
+        reported_by TEXT,
+        severity TEXT,
+        developer_id INTEGER REFERENCES developers(id),
+        status TEXT NOT NULL DEFAULT 'Open',
+        project_id INTEGER
+    );
+    INSERT INTO bugs_new (id, title, description, reported_by, severity, developer_id, status, project_id)
+        SELECT id, title, description, reported_by, severity, developer_id, status, project_id FROM bugs;
+    DROP TABLE bugs;
+    ALTER TABLE bugs_new RENAME TO bugs;
+    CREATE INDEX idx_bugs_developer_id ON bugs(developer_id);
+    "#,
+    // 5: per-developer open bug capacity
+    r#"
+    ALTER TABLE developers ADD COLUMN max_open_bugs INTEGER;
+    "#,
+    // 6: developer skills, component ownership and bug components
+    r#"
+    CREATE TABLE developer_skills (
+        developer_id INTEGER NOT NULL REFERENCES developers(id) ON DELETE CASCADE,
+        skill TEXT NOT NULL,
+        PRIMARY KEY (developer_id, skill)
+    );
+    CREATE TABLE component_owners (
+        developer_id INTEGER NOT NULL REFERENCES developers(id) ON DELETE CASCADE,
+        component TEXT NOT NULL,
+        PRIMARY KEY (developer_id, component)
+    );
+    CREATE INDEX idx_component_owners_component ON component_owners(component);
+    ALTER TABLE bugs ADD COLUMN component TEXT;
+    "#,
+    // 7: labels and the bug_labels join table
+    r#"
+    CREATE TABLE labels (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+        color TEXT NOT NULL DEFAULT '#6c757d',
+        description TEXT
+    );
+    CREATE TABLE bug_labels (
+        bug_id INTEGER NOT NULL REFERENCES bugs(id) ON DELETE CASCADE,
+        label_id INTEGER NOT NULL REFERENCES labels(id) ON DELETE CASCADE,
+        PRIMARY KEY (bug_id, label_id)
+    );
+    CREATE INDEX idx_bug_labels_label_id ON bug_labels(label_id);
+    INSERT INTO labels (name, color, description) VALUES
+        ('regression', '#d73a4a', 'Worked in an earlier release'),
+        ('security', '#b60205', 'Security impact'),
+        ('customer-reported', '#0075ca', 'Raised by a customer');
+    "#,
+    // 8: typed links between bugs
+    r#"
+    CREATE TABLE bug_links (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        source_id INTEGER NOT NULL REFERENCES bugs(id) ON DELETE CASCADE,
+        target_id INTEGER NOT NULL REFERENCES bugs(id) ON DELETE CASCADE,
+        kind TEXT NOT NULL CHECK (kind IN ('duplicate_of', 'blocks', 'related')),
+        UNIQUE (source_id, target_id, kind)
+    );
+    CREATE INDEX idx_bug_links_target_id ON bug_links(target_id);
+    "#,
+    // 9: attachment metadata; file contents live on disk by SHA-256
+    r#"
+    CREATE TABLE attachments (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        bug_id INTEGER NOT NULL REFERENCES bugs(id) ON DELETE CASCADE,
+        filename TEXT NOT NULL,
+        content_type TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        sha256 TEXT NOT NULL,
+        uploaded_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX idx_attachments_bug_id ON attachments(bug_id);
+    CREATE INDEX idx_attachments_sha256 ON attachments(sha256);
+    "#,
+    // 10: soft delete; deleted bugs stay in the trash until purged
+    r#"
+    ALTER TABLE bugs ADD COLUMN deleted_at TEXT;
+    ALTER TABLE bugs ADD COLUMN deleted_by TEXT;
+    CREATE INDEX idx_bugs_deleted_at ON bugs(deleted_at);
+    "#,
+    // 11: comments, and where imported bugs and comments came from
+    r#"
+    ALTER TABLE bugs ADD COLUMN external_ref TEXT;
+    CREATE UNIQUE INDEX idx_bugs_external_ref ON bugs(external_ref);
+    CREATE TABLE comments (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        bug_id INTEGER NOT NULL REFERENCES bugs(id) ON DELETE CASCADE,
+        author TEXT NOT NULL,
+        body TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        external_ref TEXT UNIQUE
+    );
+    CREATE INDEX idx_comments_bug_id ON comments(bug_id);
+    "#,
+    // 12: outgoing webhooks and their delivery queue / log
+    r#"
+    CREATE TABLE webhooks (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        url TEXT NOT NULL,
+        secret TEXT NOT NULL,
+        events TEXT NOT NULL,
+        active INTEGER NOT NULL DEFAULT 1,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE webhook_deliveries (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        webhook_id INTEGER NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+        event TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'delivered', 'failed')),
+        attempts INTEGER NOT NULL DEFAULT 0,
+        next_attempt_at TEXT DEFAULT CURRENT_TIMESTAMP,
+        last_attempt_at TEXT,
+        response_status INTEGER,
+        response_body TEXT,
+        error TEXT,
+        redelivery_of INTEGER REFERENCES webhook_deliveries(id) ON DELETE SET NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX idx_webhook_deliveries_due ON webhook_deliveries(status, next_attempt_at);
+    CREATE INDEX idx_webhook_deliveries_webhook_id ON webhook_deliveries(webhook_id);
+    "#,
+    // 13: email notification preferences and the outgoing notification queue
+    r#"
+    CREATE TABLE notification_settings (
+        developer_id INTEGER PRIMARY KEY REFERENCES developers(id) ON DELETE CASCADE,
+        email TEXT NOT NULL,
+        delivery TEXT NOT NULL DEFAULT 'immediate' CHECK (delivery IN ('immediate', 'digest', 'off')),
+        on_assigned INTEGER NOT NULL DEFAULT 1,
+        on_status_change INTEGER NOT NULL DEFAULT 1,
+        on_comment INTEGER NOT NULL DEFAULT 1
+    );
+    CREATE TABLE notifications (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        developer_id INTEGER NOT NULL REFERENCES developers(id) ON DELETE CASCADE,
+        bug_id INTEGER NOT NULL REFERENCES bugs(id) ON DELETE CASCADE,
+        kind TEXT NOT NULL CHECK (kind IN ('assigned', 'status_changed', 'comment')),
+        summary TEXT NOT NULL,
+        detail TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        sent_at TEXT,
+        attempts INTEGER NOT NULL DEFAULT 0,
+        error TEXT
+    );
+    CREATE INDEX idx_notifications_unsent ON notifications(sent_at, developer_id);
+    "#,
+    // 14: recent events, replayed to live streams that reconnect
+    r#"
+    CREATE TABLE event_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        event TEXT NOT NULL,
+        project_id INTEGER,
+        payload TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    "#,
+    // 15: failed notification emails wait before they are retried; NULL
+    // means due now
+    r#"
+    ALTER TABLE notifications ADD COLUMN next_attempt_at TEXT;
+    "#,
+    // 16: fixes already applied from commits, so a commit fixes each bug
+    // once however often it is pushed
+    r#"
+    CREATE TABLE git_fixes (
+        repository TEXT NOT NULL,
+        commit_id TEXT NOT NULL,
+        bug_id INTEGER NOT NULL REFERENCES bugs(id) ON DELETE CASCADE,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (repository, commit_id, bug_id)
+    );
+    "#,
+    // 17: settings changed on the in-memory projects, applied again at
+    // startup
+    r#"
+    CREATE TABLE project_settings (
+        project_id INTEGER PRIMARY KEY,
+        assignment TEXT
+    );
+    "#,
+    // 18: project components, as a JSON list
+    r#"
+    ALTER TABLE project_settings ADD COLUMN components TEXT;
+    "#,
+];
+
+// A private in-memory database with every migration applied. One
+// connection, kept open, since each connection would get its own database.
+#[cfg(test)]
+pub async fn test_pool() -> SqlitePool {
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(SqliteConnectOptions::from_str("sqlite::memory:").unwrap().foreign_keys(true))
+        .await
+        .unwrap();
+    run_migrations(&pool).await.unwrap();
+    pool
+}
+
+// Foreign keys are switched off while migrating (the pragma is ignored
+// inside a transaction) so tables can be rebuilt, as SQLite recommends.
+async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    let applied: i64 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(&mut *conn)
+        .await?;
+    if applied as usize >= MIGRATIONS.len() {
+        return Ok(());
+    }
+
+    conn.execute("PRAGMA foreign_keys = OFF").await?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(applied as usize) {
+        let version = index + 1;
+        let mut tx = conn.begin().await?;
+        tx.execute(*migration).await?;
+        tx.execute(format!("PRAGMA user_version = {}", version).as_str()).await?;
+        tx.commit().await?;
+        println!("🛠️  Applied database migration {}", version);
+    }
+    conn.execute("PRAGMA foreign_keys = ON").await?;
+    Ok(())
+}
+
+// Rows whose foreign keys point at missing parents, plus bugs that
+// reference projects unknown to the in-memory project list.
+pub async fn check_integrity(
+    pool: &SqlitePool,
+    known_project_ids: &[i64],
+) -> Result<Vec<OrphanedReference>, sqlx::Error> {
+    let mut orphans: Vec<OrphanedReference> = sqlx::query("PRAGMA foreign_key_check")
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|row| OrphanedReference {
+            table: row.get::<String, _>(0),
+            row_id: row.get::<Option<i64>, _>(1).unwrap_or(0),
+            references: row.get::<String, _>(2),
+        })
+        .collect();
+
+    let project_refs: Vec<(i64, i64)> = sqlx::query_as(
+        "SELECT id, project_id FROM bugs WHERE project_id IS NOT NULL"
+    )
+    .fetch_all(pool)
+    .await?;
+    orphans.extend(
+        project_refs.into_iter()
+            .filter(|(_, project_id)| !known_project_ids.contains(project_id))
+            .map(|(bug_id, _)| OrphanedReference {
+                table: "bugs".to_string(),
+                row_id: bug_id,
+                references: "projects".to_string(),
+            }),
+    );
+
+    Ok(orphans)
+}
+
+// Severities are stored in the case `SEVERITIES` uses; a blank one is NULL.
 pub async fn create_bug(pool: &SqlitePool, bug: &NewBug) -> Result<Bug, sqlx::Error> {
     let result = sqlx::query(
-        "INSERT INTO bugs (title, description, reported_by, severity) VALUES (?, ?, ?, ?)"
+        "INSERT INTO bugs (title, description, reported_by, severity, project_id, component) VALUES (?, ?, ?, ?, ?, ?)"
     )
     .bind(&bug.title)
     .bind(&bug.description)
     .bind(&bug.reported_by)
-    .bind(&bug.severity)
+    .bind(canonical_severity(&bug.severity))
+    .bind(bug.project_id)
+    .bind(normalize_component(bug.component.as_deref()))
     .execute(pool)
     .await?;
 
@@ -83,92 +392,2228 @@ pub async fn create_bug(pool: &SqlitePool, bug: &NewBug) -> Result<Bug, sqlx::Er
     Ok(created_bug)
 }
 
+// Inserts a batch of already validated bugs in one transaction and
+// returns their ids in order.
+pub async fn import_bugs(pool: &SqlitePool, bugs: &[NewBug]) -> Result<Vec<i64>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut ids = Vec::with_capacity(bugs.len());
+    for bug in bugs {
+        let result = sqlx::query(
+            "INSERT INTO bugs (title, description, reported_by, severity, project_id, component) VALUES (?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&bug.title)
+        .bind(&bug.description)
+        .bind(&bug.reported_by)
+        .bind(canonical_severity(&bug.severity))
+        .bind(bug.project_id)
+        .bind(normalize_component(bug.component.as_deref()))
+        .execute(&mut *tx)
+        .await?;
+        ids.push(result.last_insert_rowid());
+    }
+    tx.commit().await?;
+    Ok(ids)
+}
+
+// Creates or updates bugs from another tracker, one transaction per call.
+// Bugs and comments are matched on `external_ref`, so saving the same
+// export twice changes nothing. Labels are created as needed and only ever
+// added. A missing project or developer keeps whatever the bug has now.
+pub async fn save_external_bugs(
+    pool: &SqlitePool,
+    bugs: &[ExternalBug],
+) -> Result<Vec<SavedExternalBug>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut saved = Vec::with_capacity(bugs.len());
+
+    for bug in bugs {
+        let existing: Option<i64> = sqlx::query_scalar("SELECT id FROM bugs WHERE external_ref = ?")
+            .bind(&bug.external_ref)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let bug_id = match existing {
+            Some(bug_id) => {
+                sqlx::query(
+                    "UPDATE bugs SET title = ?, description = ?, reported_by = ?, severity = ?, status = ?,
+                     developer_id = COALESCE(?, developer_id), project_id = COALESCE(?, project_id) WHERE id = ?"
+                )
+                .bind(&bug.title)
+                .bind(&bug.description)
+                .bind(&bug.reported_by)
+                .bind(&bug.severity)
+                .bind(&bug.status)
+                .bind(bug.developer_id)
+                .bind(bug.project_id)
+                .bind(bug_id)
+                .execute(&mut *tx)
+                .await?;
+                bug_id
+            }
+            None => {
+                sqlx::query(
+                    "INSERT INTO bugs (title, description, reported_by, severity, status, developer_id, project_id, external_ref)
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+                )
+                .bind(&bug.title)
+                .bind(&bug.description)
+                .bind(&bug.reported_by)
+                .bind(&bug.severity)
+                .bind(&bug.status)
+                .bind(bug.developer_id)
+                .bind(bug.project_id)
+                .bind(&bug.external_ref)
+                .execute(&mut *tx)
+                .await?
+                .last_insert_rowid()
+            }
+        };
+
+        for label in &bug.labels {
+            sqlx::query("INSERT INTO labels (name, color, description) VALUES (?, ?, ?) ON CONFLICT(name) DO NOTHING")
+                .bind(label.name.trim())
+                .bind(label.color.as_deref().unwrap_or(DEFAULT_LABEL_COLOR))
+                .bind(&label.description)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(
+                "INSERT OR IGNORE INTO bug_labels (bug_id, label_id) SELECT ?, id FROM labels WHERE name = ?"
+            )
+            .bind(bug_id)
+            .bind(label.name.trim())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let mut new_comments = 0;
+        for comment in &bug.comments {
+            let updated = sqlx::query("UPDATE comments SET author = ?, body = ? WHERE external_ref = ?")
+                .bind(&comment.author)
+                .bind(&comment.body)
+                .bind(&comment.external_ref)
+                .execute(&mut *tx)
+                .await?;
+            if updated.rows_affected() > 0 {
+                continue;
+            }
+            sqlx::query(
+                "INSERT INTO comments (bug_id, author, body, created_at, external_ref)
+                 VALUES (?, ?, ?, COALESCE(datetime(?), CURRENT_TIMESTAMP), ?)"
+            )
+            .bind(bug_id)
+            .bind(&comment.author)
+            .bind(&comment.body)
+            .bind(&comment.created_at)
+            .bind(&comment.external_ref)
+            .execute(&mut *tx)
+            .await?;
+            new_comments += 1;
+        }
+
+        saved.push(SavedExternalBug { bug_id, created: existing.is_none(), new_comments });
+    }
+
+    tx.commit().await?;
+    Ok(saved)
+}
+
+// Which of these external references are already imported.
+pub async fn existing_external_refs(pool: &SqlitePool, refs: &[String]) -> Result<Vec<String>, sqlx::Error> {
+    if refs.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT external_ref FROM bugs WHERE external_ref IN (");
+    let mut separated = query.separated(", ");
+    for external_ref in refs {
+        separated.push_bind(external_ref);
+    }
+    query.push(")");
+    query.build_query_scalar().fetch_all(pool).await
+}
+
+// Includes bugs in the trash.
+pub async fn get_bug_id_by_external_ref(pool: &SqlitePool, external_ref: &str) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT id FROM bugs WHERE external_ref = ?")
+        .bind(external_ref)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn get_comment_by_external_ref(pool: &SqlitePool, external_ref: &str) -> Result<Option<Comment>, sqlx::Error> {
+    sqlx::query_as::<_, Comment>("SELECT * FROM comments WHERE external_ref = ?")
+        .bind(external_ref)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn get_comments(pool: &SqlitePool, bug_id: i64) -> Result<Vec<Comment>, sqlx::Error> {
+    sqlx::query_as::<_, Comment>("SELECT * FROM comments WHERE bug_id = ? ORDER BY created_at, id")
+        .bind(bug_id)
+        .fetch_all(pool)
+        .await
+}
+
+// Comments on any of `bug_ids`, oldest first.
+pub async fn get_comments_for_bugs(pool: &SqlitePool, bug_ids: &[i64]) -> Result<Vec<Comment>, sqlx::Error> {
+    if bug_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM comments WHERE bug_id IN (");
+    let mut separated = query.separated(", ");
+    for id in bug_ids {
+        separated.push_bind(*id);
+    }
+    query.push(") ORDER BY created_at, id");
+    query.build_query_as::<Comment>().fetch_all(pool).await
+}
+
+// Returns `None` if the bug doesn't exist or is in the trash.
+// `external_ref` records where the comment came from, e.g. the email it
+// was sent as.
+pub async fn create_comment(
+    pool: &SqlitePool,
+    bug_id: i64,
+    author: &str,
+    body: &str,
+    external_ref: Option<&str>,
+) -> Result<Option<Comment>, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO comments (bug_id, author, body, external_ref)
+         SELECT id, ?, ?, ? FROM bugs WHERE id = ? AND deleted_at IS NULL"
+    )
+    .bind(author)
+    .bind(body)
+    .bind(external_ref)
+    .bind(bug_id)
+    .execute(pool)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Ok(None);
+    }
+
+    sqlx::query_as::<_, Comment>("SELECT * FROM comments WHERE id = ?")
+        .bind(result.last_insert_rowid())
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn delete_comment(pool: &SqlitePool, comment_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM comments WHERE id = ?")
+        .bind(comment_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
 pub async fn get_all_bugs(pool: &SqlitePool) -> Result<Vec<Bug>, sqlx::Error> {
-    let bugs = sqlx::query_as::<_, Bug>("SELECT * FROM bugs ORDER BY id DESC")
+    let mut bugs = sqlx::query_as::<_, Bug>("SELECT * FROM bugs WHERE deleted_at IS NULL ORDER BY id DESC")
+        .fetch_all(pool)
+        .await?;
+    load_bug_labels(pool, &mut bugs).await?;
+    Ok(bugs)
+}
+
+pub async fn get_bugs(pool: &SqlitePool, filter: &BugFilter) -> Result<Vec<Bug>, sqlx::Error> {
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM bugs WHERE deleted_at IS NULL");
+    push_bug_filter(&mut query, filter);
+    query.push(" ORDER BY id DESC");
+
+    let mut bugs = query.build_query_as::<Bug>()
+        .fetch_all(pool)
+        .await?;
+    load_bug_labels(pool, &mut bugs).await?;
+    Ok(bugs)
+}
+
+// One page of `get_bugs`, and how many bugs match in all.
+pub async fn get_bugs_page(
+    pool: &SqlitePool,
+    filter: &BugFilter,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<Bug>, i64), sqlx::Error> {
+    let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM bugs WHERE deleted_at IS NULL");
+    push_bug_filter(&mut count, filter);
+    let total: i64 = count.build_query_scalar().fetch_one(pool).await?;
+
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM bugs WHERE deleted_at IS NULL");
+    push_bug_filter(&mut query, filter);
+    query.push(" ORDER BY id DESC LIMIT ").push_bind(limit).push(" OFFSET ").push_bind(offset);
+    let mut bugs = query.build_query_as::<Bug>()
         .fetch_all(pool)
         .await?;
+    load_bug_labels(pool, &mut bugs).await?;
+    Ok((bugs, total))
+}
+
+// Live bugs assigned to any of `developer_ids`, newest first.
+pub async fn get_bugs_for_developers(pool: &SqlitePool, developer_ids: &[i64]) -> Result<Vec<Bug>, sqlx::Error> {
+    get_bugs_where_in(pool, "developer_id", developer_ids).await
+}
+
+// Live bugs in any of `project_ids`, newest first.
+pub async fn get_bugs_for_projects(pool: &SqlitePool, project_ids: &[i64]) -> Result<Vec<Bug>, sqlx::Error> {
+    get_bugs_where_in(pool, "project_id", project_ids).await
+}
+
+// `column` is always one of the fixed callers' columns above.
+async fn get_bugs_where_in(pool: &SqlitePool, column: &str, ids: &[i64]) -> Result<Vec<Bug>, sqlx::Error> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut query = QueryBuilder::<Sqlite>::new(format!("SELECT * FROM bugs WHERE deleted_at IS NULL AND {} IN (", column));
+    let mut separated = query.separated(", ");
+    for id in ids {
+        separated.push_bind(*id);
+    }
+    query.push(") ORDER BY id DESC");
+    let mut bugs = query.build_query_as::<Bug>().fetch_all(pool).await?;
+    load_bug_labels(pool, &mut bugs).await?;
     Ok(bugs)
 }
 
+// Streams the filtered bugs, oldest first, into `rows` with developer
+// names and labels joined in. Stops early if the receiver goes away.
+pub async fn export_bugs(
+    pool: &SqlitePool,
+    filter: &BugFilter,
+    rows: &mpsc::Sender<Result<ExportRow, sqlx::Error>>,
+) -> Result<(), sqlx::Error> {
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "SELECT b.id, b.title, b.description, b.reported_by, b.severity, b.status, b.component,
+                b.developer_id, (SELECT d.name FROM developers d WHERE d.id = b.developer_id) AS developer_name,
+                b.project_id,
+                (SELECT GROUP_CONCAT(l.name, ', ') FROM bug_labels bl JOIN labels l ON l.id = bl.label_id
+                 WHERE bl.bug_id = b.id) AS labels
+         FROM bugs b WHERE b.deleted_at IS NULL"
+    );
+    push_bug_filter(&mut query, filter);
+    query.push(" ORDER BY b.id");
+
+    let mut stream = query.build_query_as::<ExportRow>().fetch(pool);
+    while let Some(row) = stream.try_next().await? {
+        if rows.send(Ok(row)).await.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// `GET /bugs` filters as `AND` clauses on the bugs table's columns.
+fn push_bug_filter<'a>(query: &mut QueryBuilder<'a, Sqlite>, filter: &'a BugFilter) {
+    if let Some(severity) = &filter.severity {
+        query.push(" AND severity = ").push_bind(severity).push(" COLLATE NOCASE");
+    }
+    if let Some(developer_id) = filter.developer_id {
+        query.push(" AND developer_id = ").push_bind(developer_id);
+    }
+    if filter.unassigned == Some(true) {
+        query.push(" AND developer_id IS NULL");
+    }
+    if let Some(reported_by) = &filter.reported_by {
+        query.push(" AND reported_by LIKE ").push_bind(format!("%{}%", reported_by));
+    }
+    if let Some(status) = &filter.status {
+        query.push(" AND status = ").push_bind(status).push(" COLLATE NOCASE");
+    }
+    if let Some(project_id) = filter.project_id {
+        query.push(" AND project_id = ").push_bind(project_id);
+    }
+    if let Some(component) = &filter.component {
+        query.push(" AND component = ").push_bind(component.trim().to_string()).push(" COLLATE NOCASE");
+    }
+    if let Some(names) = filter.labels_any.as_deref().map(split_label_names) {
+        query.push(" AND id IN (");
+        push_labelled_bug_ids(query, &names);
+        query.push(")");
+    }
+    if let Some(names) = filter.labels_all.as_deref().map(split_label_names) {
+        query.push(" AND id IN (");
+        push_labelled_bug_ids(query, &names);
+        query.push(" GROUP BY bl.bug_id HAVING COUNT(DISTINCT bl.label_id) = ").push_bind(names.len() as i64);
+        query.push(")");
+    }
+    if let Some(names) = filter.labels_none.as_deref().map(split_label_names) {
+        query.push(" AND id NOT IN (");
+        push_labelled_bug_ids(query, &names);
+        query.push(")");
+    }
+    if let Some(q) = &filter.q {
+        let pattern = format!("%{}%", q);
+        query.push(" AND (title LIKE ").push_bind(pattern.clone())
+            .push(" OR description LIKE ").push_bind(pattern).push(")");
+    }
+}
+
+// Subquery selecting the ids of bugs carrying any of `names`. An empty list
+// matches nothing.
+fn push_labelled_bug_ids(query: &mut QueryBuilder<Sqlite>, names: &[String]) {
+    query.push("SELECT bl.bug_id FROM bug_labels bl JOIN labels l ON l.id = bl.label_id WHERE l.name IN (");
+    if names.is_empty() {
+        query.push("NULL");
+    }
+    let mut separated = query.separated(", ");
+    for name in names {
+        separated.push_bind(name.clone());
+    }
+    query.push(")");
+}
+
+// Fills in `labels` for each bug with one query.
+async fn load_bug_labels(pool: &SqlitePool, bugs: &mut [Bug]) -> Result<(), sqlx::Error> {
+    let ids: Vec<i64> = bugs.iter().filter_map(|b| b.id).collect();
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "SELECT bl.bug_id, l.id, l.name, l.color, l.description FROM bug_labels bl
+         JOIN labels l ON l.id = bl.label_id WHERE bl.bug_id IN ("
+    );
+    let mut separated = query.separated(", ");
+    for id in &ids {
+        separated.push_bind(*id);
+    }
+    query.push(") ORDER BY l.name");
+
+    let rows = query.build().fetch_all(pool).await?;
+    for row in rows {
+        let bug_id: i64 = row.get("bug_id");
+        let label = Label {
+            id: row.get("id"),
+            name: row.get("name"),
+            color: row.get("color"),
+            description: row.get("description"),
+        };
+        if let Some(bug) = bugs.iter_mut().find(|b| b.id == Some(bug_id)) {
+            bug.labels.push(label);
+        }
+    }
+    Ok(())
+}
+
 pub async fn get_bug_by_id(pool: &SqlitePool, bug_id: i64) -> Result<Option<Bug>, sqlx::Error> {
-    let bug = sqlx::query_as::<_, Bug>("SELECT * FROM bugs WHERE id = ?")
+    let bug = sqlx::query_as::<_, Bug>("SELECT * FROM bugs WHERE id = ? AND deleted_at IS NULL")
         .bind(bug_id)
         .fetch_optional(pool)
         .await?;
-    Ok(bug)
+    let Some(bug) = bug else {
+        return Ok(None);
+    };
+    let mut bugs = vec![bug];
+    load_bug_labels(pool, &mut bugs).await?;
+    Ok(bugs.pop())
+}
+
+// A live bug without its labels, for checks inside a transaction.
+async fn fetch_bug(conn: &mut sqlx::SqliteConnection, bug_id: i64) -> Result<Option<Bug>, sqlx::Error> {
+    sqlx::query_as::<_, Bug>("SELECT * FROM bugs WHERE id = ? AND deleted_at IS NULL")
+        .bind(bug_id)
+        .fetch_optional(conn)
+        .await
+}
+
+pub struct SavedBug {
+    pub bug: Bug,
+    pub previous_developer_id: Option<i64>,
+    // The capacity warning under `Warn`.
+    pub warning: Option<String>,
 }
 
-pub async fn update_bug(pool: &SqlitePool, bug_id: i64, bug: &Bug) -> Result<Bug, sqlx::Error> {
+// Status is left alone here; it only moves through `transition_bug`. The
+// severity is stored as in `create_bug`, and the developer is checked as
+// in `check_assignment_on` within the same transaction as the write.
+pub async fn update_bug(
+    pool: &SqlitePool,
+    bug_id: i64,
+    bug: &Bug,
+    policy: CapacityPolicy,
+) -> Result<Result<SavedBug, AssignmentError>, sqlx::Error> {
+    let mut tx = begin_write(pool).await?;
+    let Some(current) = fetch_bug(&mut tx, bug_id).await? else {
+        return Ok(Err(AssignmentError::BugNotFound(bug_id)));
+    };
+    let mut warning = None;
+    if let Some(developer_id) = bug.developer_id {
+        match check_assignment_on(&mut tx, bug_id, developer_id, policy).await? {
+            Ok(capacity_warning) => warning = capacity_warning,
+            Err(e) => return Ok(Err(e)),
+        }
+    }
+
     sqlx::query(
-        "UPDATE bugs SET title = ?, description = ?, reported_by = ?, severity = ?, developer_id = ? WHERE id = ?"
+        "UPDATE bugs SET title = ?, description = ?, reported_by = ?, severity = ?, developer_id = ?, project_id = ?, component = ?
+         WHERE id = ? AND deleted_at IS NULL"
     )
     .bind(&bug.title)
     .bind(&bug.description)
     .bind(&bug.reported_by)
-    .bind(&bug.severity)
-    .bind(&bug.developer_id)
+    .bind(bug.severity.as_deref().and_then(canonical_severity))
+    .bind(bug.developer_id)
+    .bind(bug.project_id)
+    .bind(normalize_component(bug.component.as_deref()))
+    .bind(bug_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    let updated = get_bug_by_id(pool, bug_id).await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+    Ok(Ok(SavedBug { bug: updated, previous_developer_id: current.developer_id, warning }))
+}
+
+// Moves the bug to the trash. Returns false if it doesn't exist or is
+// already there.
+pub async fn delete_bug(pool: &SqlitePool, bug_id: i64, deleted_by: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE bugs SET deleted_at = CURRENT_TIMESTAMP, deleted_by = ? WHERE id = ? AND deleted_at IS NULL"
+    )
+    .bind(deleted_by)
     .bind(bug_id)
     .execute(pool)
     .await?;
 
-    let updated_bug = get_bug_by_id(pool, bug_id).await?
-        .ok_or_else(|| sqlx::Error::RowNotFound)?;
-    
-    Ok(updated_bug)
+    Ok(result.rows_affected() > 0)
 }
 
-pub async fn delete_bug(pool: &SqlitePool, bug_id: i64) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM bugs WHERE id = ?")
+// Deletes a bug outright, bypassing the trash; only for undoing a bug whose
+// creation failed part way.
+pub async fn remove_bug(pool: &SqlitePool, bug_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM bugs WHERE id = ?")
         .bind(bug_id)
         .execute(pool)
         .await?;
-    
-    Ok(result.rows_affected() > 0)
+    Ok(())
 }
 
-pub async fn assign_bug(pool: &SqlitePool, bug_id: i64, developer_id: i64) -> Result<bool, sqlx::Error> {
+pub async fn get_trash(pool: &SqlitePool) -> Result<Vec<Bug>, sqlx::Error> {
+    let mut bugs = sqlx::query_as::<_, Bug>(
+        "SELECT * FROM bugs WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC, id DESC"
+    )
+    .fetch_all(pool)
+    .await?;
+    load_bug_labels(pool, &mut bugs).await?;
+    Ok(bugs)
+}
+
+pub async fn restore_bug(pool: &SqlitePool, bug_id: i64) -> Result<bool, sqlx::Error> {
     let result = sqlx::query(
-        "UPDATE bugs SET developer_id = ? WHERE id = ?"
+        "UPDATE bugs SET deleted_at = NULL, deleted_by = NULL WHERE id = ? AND deleted_at IS NOT NULL"
     )
-    .bind(developer_id)
     .bind(bug_id)
     .execute(pool)
     .await?;
-    
+
     Ok(result.rows_affected() > 0)
 }
 
+// Permanently deletes trashed bugs deleted at least `older_than_days` days
+// ago, or only bug `bug_id` when given. Attachments, labels and links go
+// with them (ON DELETE CASCADE). Returns how many bugs were purged and the
+// attachment files nothing references any more, for the caller to remove
+// from disk; both are worked out in one transaction.
+pub async fn purge_trash(
+    pool: &SqlitePool,
+    bug_id: Option<i64>,
+    older_than_days: i64,
+) -> Result<(u64, Vec<String>), sqlx::Error> {
+    let cutoff = format!("-{} days", older_than_days);
+    let condition = "deleted_at IS NOT NULL AND deleted_at <= datetime('now', ?) AND (? IS NULL OR id = ?)";
+    let mut tx = begin_write(pool).await?;
+
+    let hashes: Vec<String> = sqlx::query_scalar(&format!(
+        "SELECT DISTINCT sha256 FROM attachments WHERE bug_id IN (SELECT id FROM bugs WHERE {})",
+        condition
+    ))
+    .bind(&cutoff)
+    .bind(bug_id)
+    .bind(bug_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let result = sqlx::query(&format!("DELETE FROM bugs WHERE {}", condition))
+        .bind(&cutoff)
+        .bind(bug_id)
+        .bind(bug_id)
+        .execute(&mut *tx)
+        .await?;
+    let unreferenced = unreferenced_hashes_on(&mut tx, &hashes).await?;
+    tx.commit().await?;
+
+    Ok((result.rows_affected(), unreferenced))
+}
+
+// Checks both sides of an assignment before it is written. A bug may keep
+// a developer it already has (even an inactive or full one), but can't be
+// newly given an inactive developer, or a full one under `Refuse`. On
+// success returns the over-capacity warning, if any, under `Warn`. Run it
+// in the transaction that writes the assignment, so it sees that
+// transaction's earlier assignments and no one else's half-done ones.
+async fn check_assignment_on(
+    conn: &mut sqlx::SqliteConnection,
+    bug_id: i64,
+    developer_id: i64,
+    policy: CapacityPolicy,
+) -> Result<Result<Option<String>, AssignmentError>, sqlx::Error> {
+    let Some(bug) = fetch_bug(conn, bug_id).await? else {
+        return Ok(Err(AssignmentError::BugNotFound(bug_id)));
+    };
+    let developer = sqlx::query_as::<_, Developer>("SELECT * FROM developers WHERE id = ?")
+        .bind(developer_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let Some(developer) = developer else {
+        return Ok(Err(AssignmentError::DeveloperNotFound(developer_id)));
+    };
+    if bug.developer_id == Some(developer_id) {
+        return Ok(Ok(None));
+    }
+    if !developer.active {
+        return Ok(Err(AssignmentError::DeveloperInactive(developer_id)));
+    }
+
+    if !OPEN_STATUSES.contains(&bug.status.as_str()) {
+        return Ok(Ok(None));
+    }
+    check_capacity_on(conn, &developer, 1, policy).await
+}
+
+// Whether `developer` can take `adding` more open bugs under `policy`.
+async fn check_capacity_on(
+    conn: &mut sqlx::SqliteConnection,
+    developer: &Developer,
+    adding: i64,
+    policy: CapacityPolicy,
+) -> Result<Result<Option<String>, AssignmentError>, sqlx::Error> {
+    let Some(max_open_bugs) = developer.max_open_bugs else {
+        return Ok(Ok(None));
+    };
+    let open_bugs = count_open_bugs(conn, developer.id).await?;
+    if open_bugs + adding <= max_open_bugs {
+        return Ok(Ok(None));
+    }
+    match policy {
+        CapacityPolicy::Refuse => Ok(Err(AssignmentError::OverCapacity {
+            developer_id: developer.id,
+            open_bugs,
+            max_open_bugs,
+        })),
+        CapacityPolicy::Warn => Ok(Ok(Some(capacity_message(developer.id, open_bugs, max_open_bugs)))),
+    }
+}
+
+// Moving an assigned bug from a closed status back to an open one needs
+// room under its developer's capacity, like a new assignment.
+async fn check_reopen_on(
+    conn: &mut sqlx::SqliteConnection,
+    bug: &Bug,
+    status: &str,
+    policy: CapacityPolicy,
+) -> Result<Result<Option<String>, AssignmentError>, sqlx::Error> {
+    let reopens = OPEN_STATUSES.contains(&status) && !OPEN_STATUSES.contains(&bug.status.as_str());
+    let Some(developer_id) = bug.developer_id.filter(|_| reopens) else {
+        return Ok(Ok(None));
+    };
+    let developer = sqlx::query_as::<_, Developer>("SELECT * FROM developers WHERE id = ?")
+        .bind(developer_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    match developer {
+        Some(developer) => check_capacity_on(conn, &developer, 1, policy).await,
+        None => Ok(Ok(None)),
+    }
+}
+
+// Starts a transaction that takes SQLite's write lock straight away (a
+// no-op write), so a check made in it can't be raced by another writer
+// between the read and the write.
+async fn begin_write(pool: &SqlitePool) -> Result<sqlx::Transaction<'_, Sqlite>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE bugs SET id = id WHERE 0").execute(&mut *tx).await?;
+    Ok(tx)
+}
+
+// Returns the capacity warning, if any, on success.
+pub async fn assign_bug(
+    pool: &SqlitePool,
+    bug_id: i64,
+    developer_id: i64,
+    policy: CapacityPolicy,
+) -> Result<Result<Option<String>, AssignmentError>, sqlx::Error> {
+    let mut tx = begin_write(pool).await?;
+    let warning = match check_assignment_on(&mut tx, bug_id, developer_id, policy).await? {
+        Ok(warning) => warning,
+        Err(e) => return Ok(Err(e)),
+    };
+
+    sqlx::query("UPDATE bugs SET developer_id = ? WHERE id = ?")
+        .bind(developer_id)
+        .bind(bug_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Ok(warning))
+}
+
+fn open_status_list() -> String {
+    OPEN_STATUSES.iter()
+        .map(|s| format!("'{}'", s))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+async fn count_open_bugs(conn: &mut sqlx::SqliteConnection, developer_id: i64) -> Result<i64, sqlx::Error> {
+    let sql = format!(
+        "SELECT COUNT(*) FROM bugs WHERE developer_id = ? AND deleted_at IS NULL AND status IN ({})",
+        open_status_list()
+    );
+    sqlx::query_scalar(&sql)
+        .bind(developer_id)
+        .fetch_one(conn)
+        .await
+}
+
+pub async fn get_developer_summaries(pool: &SqlitePool) -> Result<Vec<DeveloperSummary>, sqlx::Error> {
+    let developers = get_all_developers(pool).await?;
+    let sql = format!(
+        "SELECT developer_id, severity FROM bugs WHERE developer_id IS NOT NULL AND deleted_at IS NULL AND status IN ({})",
+        open_status_list()
+    );
+    let open: Vec<(i64, Option<String>)> = sqlx::query_as(&sql)
+        .fetch_all(pool)
+        .await?;
+
+    let mut totals: HashMap<i64, (i64, i64)> = HashMap::new();
+    for (developer_id, severity) in open {
+        let entry = totals.entry(developer_id).or_default();
+        entry.0 += 1;
+        entry.1 += severity_weight(severity.as_deref());
+    }
+
+    Ok(developers.into_iter()
+        .map(|developer| {
+            let (open_bugs, weighted_load) = totals.get(&developer.id).copied().unwrap_or_default();
+            DeveloperSummary { developer, open_bugs, weighted_load }
+        })
+        .collect())
+}
+
+pub async fn get_developer_workload(
+    pool: &SqlitePool,
+    developer_id: i64,
+) -> Result<Option<DeveloperWorkload>, sqlx::Error> {
+    let Some(developer) = get_developer_by_id(pool, developer_id).await? else {
+        return Ok(None);
+    };
+    let sql = format!(
+        "SELECT * FROM bugs WHERE developer_id = ? AND deleted_at IS NULL AND status IN ({}) ORDER BY id DESC",
+        open_status_list()
+    );
+    let mut bugs = sqlx::query_as::<_, Bug>(&sql)
+        .bind(developer_id)
+        .fetch_all(pool)
+        .await?;
+    load_bug_labels(pool, &mut bugs).await?;
+
+    let mut by_severity = BTreeMap::new();
+    let mut by_status = BTreeMap::new();
+    let mut weighted_load = 0;
+    for bug in &bugs {
+        let severity = bug.severity.clone().unwrap_or_else(|| "Unspecified".to_string());
+        *by_severity.entry(severity).or_insert(0) += 1;
+        *by_status.entry(bug.status.clone()).or_insert(0) += 1;
+        weighted_load += severity_weight(bug.severity.as_deref());
+    }
+    let open_bugs = bugs.len() as i64;
+    let remaining_capacity = developer.max_open_bugs.map(|max| (max - open_bugs).max(0));
+
+    Ok(Some(DeveloperWorkload {
+        developer,
+        open_bugs,
+        weighted_load,
+        remaining_capacity,
+        by_severity,
+        by_status,
+        bugs,
+    }))
+}
+
+pub enum StatusChange {
+    // The bug as saved, the status it left and any capacity warning.
+    Moved { bug: Box<Bug>, from: String, warning: Option<String> },
+    NotFound,
+    NotAllowed { from: String },
+    // Reopening it would put its developer over capacity.
+    Refused(AssignmentError),
+}
+
+// Moves a bug to `status` (already canonical) if the workflow allows it.
+// Reopening an assigned bug counts against its developer's capacity again,
+// so that's checked in the same transaction.
+pub async fn transition_bug(
+    pool: &SqlitePool,
+    bug_id: i64,
+    status: &str,
+    policy: CapacityPolicy,
+) -> Result<StatusChange, sqlx::Error> {
+    let mut tx = begin_write(pool).await?;
+    let Some(bug) = fetch_bug(&mut tx, bug_id).await? else {
+        return Ok(StatusChange::NotFound);
+    };
+    if !allowed_transitions(&bug.status).contains(&status) {
+        return Ok(StatusChange::NotAllowed { from: bug.status });
+    }
+    let warning = match check_reopen_on(&mut tx, &bug, status, policy).await? {
+        Ok(warning) => warning,
+        Err(e) => return Ok(StatusChange::Refused(e)),
+    };
+
+    sqlx::query("UPDATE bugs SET status = ? WHERE id = ?")
+        .bind(status)
+        .bind(bug_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let updated = get_bug_by_id(pool, bug_id).await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+    Ok(StatusChange::Moved { bug: Box::new(updated), from: bug.status, warning })
+}
+
 pub async fn get_all_developers(pool: &SqlitePool) -> Result<Vec<Developer>, sqlx::Error> {
-    let developers = sqlx::query_as::<_, Developer>("SELECT * FROM developers ORDER BY name")
+    let mut developers = sqlx::query_as::<_, Developer>("SELECT * FROM developers ORDER BY name")
         .fetch_all(pool)
         .await?;
+    load_developer_tags(pool, &mut developers).await?;
     Ok(developers)
 }
 
-pub async fn create_developer(pool: &SqlitePool, developer: &NewDeveloper) -> Result<Developer, sqlx::Error> {
-    let result = sqlx::query(
-        "INSERT INTO developers (name) VALUES (?)"
+pub async fn get_developer_by_id(pool: &SqlitePool, developer_id: i64) -> Result<Option<Developer>, sqlx::Error> {
+    let developer = sqlx::query_as::<_, Developer>("SELECT * FROM developers WHERE id = ?")
+        .bind(developer_id)
+        .fetch_optional(pool)
+        .await?;
+    let Some(developer) = developer else {
+        return Ok(None);
+    };
+    let mut developers = vec![developer];
+    load_developer_tags(pool, &mut developers).await?;
+    Ok(developers.pop())
+}
+
+pub async fn get_developers_by_ids(pool: &SqlitePool, ids: &[i64]) -> Result<Vec<Developer>, sqlx::Error> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM developers WHERE id IN (");
+    let mut separated = query.separated(", ");
+    for id in ids {
+        separated.push_bind(*id);
+    }
+    query.push(")");
+    let mut developers = query.build_query_as::<Developer>().fetch_all(pool).await?;
+    load_developer_tags(pool, &mut developers).await?;
+    Ok(developers)
+}
+
+// Fills in `skills` and `components`, two queries for the whole slice.
+async fn load_developer_tags(pool: &SqlitePool, developers: &mut [Developer]) -> Result<(), sqlx::Error> {
+    let skills: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT developer_id, skill FROM developer_skills ORDER BY skill"
     )
-    .bind(&developer.name)
+    .fetch_all(pool)
+    .await?;
+    let components: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT developer_id, component FROM component_owners ORDER BY component"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for developer in developers.iter_mut() {
+        developer.skills = skills.iter()
+            .filter(|(id, _)| *id == developer.id)
+            .map(|(_, skill)| skill.clone())
+            .collect();
+        developer.components = components.iter()
+            .filter(|(id, _)| *id == developer.id)
+            .map(|(_, component)| component.clone())
+            .collect();
+    }
+    Ok(())
+}
+
+// `table` and `column` are always one of the two fixed tag tables.
+async fn replace_developer_tags(
+    conn: &mut sqlx::SqliteConnection,
+    developer_id: i64,
+    table: &str,
+    column: &str,
+    tags: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!("DELETE FROM {} WHERE developer_id = ?", table))
+        .bind(developer_id)
+        .execute(&mut *conn)
+        .await?;
+    for tag in normalize_tags(tags) {
+        sqlx::query(&format!("INSERT INTO {} (developer_id, {}) VALUES (?, ?)", table, column))
+            .bind(developer_id)
+            .bind(tag)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn get_component_owners(
+    pool: &SqlitePool,
+    component: &str,
+) -> Result<(Vec<Developer>, Vec<Developer>), sqlx::Error> {
+    let component = component.trim().to_lowercase();
+    let (owners, others): (Vec<Developer>, Vec<Developer>) = get_all_developers(pool).await?
+        .into_iter()
+        .partition(|d| d.components.contains(&component));
+    let skilled = others.into_iter()
+        .filter(|d| d.skills.contains(&component))
+        .collect();
+    Ok((owners, skilled))
+}
+
+pub async fn get_project_settings(pool: &SqlitePool) -> Result<Vec<ProjectSettings>, sqlx::Error> {
+    sqlx::query_as::<_, ProjectSettings>("SELECT * FROM project_settings ORDER BY project_id")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn save_project_assignment(
+    pool: &SqlitePool,
+    project_id: i64,
+    assignment: &AssignmentConfig,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO project_settings (project_id, assignment) VALUES (?, ?)
+         ON CONFLICT (project_id) DO UPDATE SET assignment = excluded.assignment"
+    )
+    .bind(project_id)
+    .bind(Json(assignment))
     .execute(pool)
     .await?;
+    Ok(())
+}
 
-    let developer_id = result.last_insert_rowid();
-    
-    let created_developer = sqlx::query_as::<_, Developer>(
-        "SELECT * FROM developers WHERE id = ?"
+pub async fn save_project_components(
+    pool: &SqlitePool,
+    project_id: i64,
+    components: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO project_settings (project_id, components) VALUES (?, ?)
+         ON CONFLICT (project_id) DO UPDATE SET components = excluded.components"
+    )
+    .bind(project_id)
+    .bind(Json(components))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// Components set on the project's bugs outside the trash.
+pub async fn get_project_bug_components(pool: &SqlitePool, project_id: i64) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT DISTINCT component FROM bugs
+         WHERE project_id = ? AND component IS NOT NULL AND deleted_at IS NULL ORDER BY component"
+    )
+    .bind(project_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn update_developer(
+    pool: &SqlitePool,
+    developer_id: i64,
+    changes: &UpdateDeveloper,
+) -> Result<Option<Developer>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query(
+        "UPDATE developers SET name = COALESCE(?, name), active = COALESCE(?, active),
+         max_open_bugs = CASE WHEN ? THEN ? ELSE max_open_bugs END WHERE id = ?"
     )
+    .bind(changes.name.as_deref().map(str::trim))
+    .bind(changes.active)
+    .bind(changes.max_open_bugs.is_some())
+    .bind(changes.max_open_bugs.flatten())
     .bind(developer_id)
-    .fetch_one(pool)
+    .execute(&mut *tx)
     .await?;
 
-    Ok(created_developer)
+    if result.rows_affected() == 0 {
+        return Ok(None);
+    }
+    if let Some(skills) = &changes.skills {
+        replace_developer_tags(&mut tx, developer_id, "developer_skills", "skill", skills).await?;
+    }
+    if let Some(components) = &changes.components {
+        replace_developer_tags(&mut tx, developer_id, "component_owners", "component", components).await?;
+    }
+    tx.commit().await?;
+
+    get_developer_by_id(pool, developer_id).await
 }
 
-pub async fn get_user_by_username(pool: &SqlitePool, username: &str) -> Result<Option<User>, sqlx::Error> {
+pub enum DeleteDeveloperOutcome {
+    // The bugs that were unassigned or reassigned, and the capacity warning
+    // under the `warn` policy.
+    Deleted { bugs_moved: Vec<i64>, warning: Option<String> },
+    NotFound,
+    HasAssignedBugs(i64),
+    InvalidReassignTarget(String),
+    // The reassign target has no room for `moving` more open bugs.
+    Refused { moving: i64, error: AssignmentError },
+}
+
+// Deletes a developer and applies `policy` to their bugs in one
+// transaction, so a failure never leaves bugs pointing at a missing row.
+// Reassigning checks that the target has room for the open bugs under
+// `capacity_policy`.
+pub async fn delete_developer(
+    pool: &SqlitePool,
+    developer_id: i64,
+    policy: DeletePolicy,
+    reassign_to: Option<i64>,
+    capacity_policy: CapacityPolicy,
+) -> Result<DeleteDeveloperOutcome, sqlx::Error> {
+    let mut tx = begin_write(pool).await?;
+
+    let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM developers WHERE id = ?")
+        .bind(developer_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if exists.is_none() {
+        return Ok(DeleteDeveloperOutcome::NotFound);
+    }
+
+    let assigned: Vec<i64> = sqlx::query_scalar("SELECT id FROM bugs WHERE developer_id = ? ORDER BY id")
+        .bind(developer_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let mut warning = None;
+    match policy {
+        DeletePolicy::Reject => {
+            if !assigned.is_empty() {
+                return Ok(DeleteDeveloperOutcome::HasAssignedBugs(assigned.len() as i64));
+            }
+        }
+        DeletePolicy::Unassign => {
+            sqlx::query("UPDATE bugs SET developer_id = NULL WHERE developer_id = ?")
+                .bind(developer_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        DeletePolicy::Reassign => {
+            let Some(target_id) = reassign_to else {
+                return Ok(DeleteDeveloperOutcome::InvalidReassignTarget(
+                    "reassign_to is required when policy=reassign".to_string(),
+                ));
+            };
+            if target_id == developer_id {
+                return Ok(DeleteDeveloperOutcome::InvalidReassignTarget(
+                    "Cannot reassign bugs to the developer being deleted".to_string(),
+                ));
+            }
+            let target = sqlx::query_as::<_, Developer>("SELECT * FROM developers WHERE id = ?")
+                .bind(target_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+            let target = match target {
+                None => {
+                    return Ok(DeleteDeveloperOutcome::InvalidReassignTarget(
+                        format!("Developer #{} does not exist", target_id),
+                    ));
+                }
+                Some(target) if !target.active => {
+                    return Ok(DeleteDeveloperOutcome::InvalidReassignTarget(
+                        format!("Developer #{} is inactive", target_id),
+                    ));
+                }
+                Some(target) => target,
+            };
+            let moving = count_open_bugs(&mut tx, developer_id).await?;
+            if moving > 0 {
+                match check_capacity_on(&mut tx, &target, moving, capacity_policy).await? {
+                    Ok(capacity_warning) => warning = capacity_warning,
+                    Err(error) => return Ok(DeleteDeveloperOutcome::Refused { moving, error }),
+                }
+            }
+            sqlx::query("UPDATE bugs SET developer_id = ? WHERE developer_id = ?")
+                .bind(target_id)
+                .bind(developer_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    sqlx::query("DELETE FROM developers WHERE id = ?")
+        .bind(developer_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(DeleteDeveloperOutcome::Deleted { bugs_moved: assigned, warning })
+}
+
+pub async fn create_developer(pool: &SqlitePool, developer: &NewDeveloper) -> Result<Developer, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query(
+        "INSERT INTO developers (name) VALUES (?)"
+    )
+    .bind(&developer.name)
+    .execute(&mut *tx)
+    .await?;
+
+    let developer_id = result.last_insert_rowid();
+    replace_developer_tags(&mut tx, developer_id, "developer_skills", "skill", &developer.skills).await?;
+    replace_developer_tags(&mut tx, developer_id, "component_owners", "component", &developer.components).await?;
+    tx.commit().await?;
+
+    get_developer_by_id(pool, developer_id).await?
+        .ok_or(sqlx::Error::RowNotFound)
+}
+
+// Links touching `bug_id` in either direction, named from its side.
+pub async fn get_bug_links(pool: &SqlitePool, bug_id: i64) -> Result<Vec<BugLinkView>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT l.id, l.kind, l.source_id, b.id AS other_id, b.title, b.status FROM bug_links l
+         JOIN bugs b ON b.id = CASE WHEN l.source_id = ? THEN l.target_id ELSE l.source_id END
+         WHERE (l.source_id = ? OR l.target_id = ?) AND b.deleted_at IS NULL ORDER BY l.id"
+    )
+    .bind(bug_id)
+    .bind(bug_id)
+    .bind(bug_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter()
+        .map(|row| {
+            let kind: String = row.get("kind");
+            let outgoing = row.get::<i64, _>("source_id") == bug_id;
+            let relation = match (kind.as_str(), outgoing) {
+                ("duplicate_of", false) => "duplicated_by".to_string(),
+                ("blocks", false) => "blocked_by".to_string(),
+                _ => kind,
+            };
+            BugLinkView {
+                id: row.get("id"),
+                relation,
+                bug_id: row.get("other_id"),
+                title: row.get("title"),
+                status: row.get("status"),
+            }
+        })
+        .collect())
+}
+
+// Returns the (source, target) pairs of every link of one kind.
+async fn link_edges(conn: &mut sqlx::SqliteConnection, kind: &str) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+    sqlx::query_as("SELECT source_id, target_id FROM bug_links WHERE kind = ?")
+        .bind(kind)
+        .fetch_all(&mut *conn)
+        .await
+}
+
+// Creates a link from `bug_id`. `blocked_by` is stored as the reverse
+// `blocks`; blocking links may not form a cycle. Marking a bug as a
+// duplicate points it (and the bugs already marked as its duplicates) at
+// the root of any existing duplicate chain and closes it under the
+// workflow rules, all in one transaction. Returns the status the bug left
+// when it was closed.
+pub async fn create_bug_link(
+    pool: &SqlitePool,
+    bug_id: i64,
+    link: &NewBugLink,
+) -> Result<Result<Option<String>, LinkError>, sqlx::Error> {
+    if link.target_id == bug_id {
+        return Ok(Err(LinkError::SelfLink));
+    }
+    let mut tx = pool.begin().await?;
+
+    for (id, missing) in [(bug_id, LinkError::BugNotFound(bug_id)), (link.target_id, LinkError::TargetNotFound(link.target_id))] {
+        let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM bugs WHERE id = ? AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if exists.is_none() {
+            return Ok(Err(missing));
+        }
+    }
+
+    let (mut source, mut target) = (bug_id, link.target_id);
+    let kind = match link.kind {
+        LinkKind::BlockedBy => {
+            std::mem::swap(&mut source, &mut target);
+            LinkKind::Blocks
+        }
+        kind => kind,
+    };
+
+    match kind {
+        LinkKind::DuplicateOf => {
+            let duplicates = link_edges(&mut tx, "duplicate_of").await?;
+            if let Some((_, of)) = duplicates.iter().find(|(from, _)| *from == source) {
+                return Ok(Err(LinkError::AlreadyDuplicate { of: *of }));
+            }
+            // Each bug has at most one `duplicate_of`, so this walks a chain.
+            let mut chain = vec![source, target];
+            while let Some((_, next)) = duplicates.iter().find(|(from, _)| from == chain.last().unwrap()) {
+                if *next == source {
+                    chain.push(source);
+                    return Ok(Err(LinkError::Cycle(chain)));
+                }
+                chain.push(*next);
+            }
+            target = *chain.last().unwrap();
+        }
+        LinkKind::Blocks => {
+            let blocks = link_edges(&mut tx, "blocks").await?;
+            if let Some(mut path) = crate::graph::find_path(&blocks, target, source) {
+                path.insert(0, source);
+                return Ok(Err(LinkError::Cycle(path)));
+            }
+        }
+        _ => {}
+    }
+
+    let existing: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM bug_links WHERE kind = ? AND ((source_id = ? AND target_id = ?)
+         OR (kind = 'related' AND source_id = ? AND target_id = ?))"
+    )
+    .bind(kind.as_str())
+    .bind(source)
+    .bind(target)
+    .bind(target)
+    .bind(source)
+    .fetch_one(&mut *tx)
+    .await?;
+    if existing > 0 {
+        return Ok(Err(LinkError::AlreadyLinked));
+    }
+
+    sqlx::query("INSERT INTO bug_links (source_id, target_id, kind) VALUES (?, ?, ?)")
+        .bind(source)
+        .bind(target)
+        .bind(kind.as_str())
+        .execute(&mut *tx)
+        .await?;
+    let mut closed_from = None;
+    if kind == LinkKind::DuplicateOf {
+        sqlx::query("UPDATE bug_links SET target_id = ? WHERE kind = 'duplicate_of' AND target_id = ?")
+            .bind(target)
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+
+        let status: String = sqlx::query_scalar("SELECT status FROM bugs WHERE id = ?")
+            .bind(source)
+            .fetch_one(&mut *tx)
+            .await?;
+        if status != "Closed" {
+            if !allowed_transitions(&status).contains(&"Closed") {
+                return Ok(Err(LinkError::CannotClose(status)));
+            }
+            sqlx::query("UPDATE bugs SET status = 'Closed' WHERE id = ?")
+                .bind(source)
+                .execute(&mut *tx)
+                .await?;
+            closed_from = Some(status);
+        }
+    }
+    tx.commit().await?;
+
+    Ok(Ok(closed_from))
+}
+
+pub async fn get_link_edges(pool: &SqlitePool, kind: LinkKind) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    link_edges(&mut conn, kind.as_str()).await
+}
+
+pub async fn get_bugs_by_ids(pool: &SqlitePool, ids: &[i64]) -> Result<Vec<Bug>, sqlx::Error> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM bugs WHERE deleted_at IS NULL AND id IN (");
+    let mut separated = query.separated(", ");
+    for id in ids {
+        separated.push_bind(*id);
+    }
+    query.push(") ORDER BY id");
+    query.build_query_as::<Bug>().fetch_all(pool).await
+}
+
+// Only removes the link if it touches `bug_id`.
+pub async fn delete_bug_link(pool: &SqlitePool, bug_id: i64, link_id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM bug_links WHERE id = ? AND (source_id = ? OR target_id = ?)")
+        .bind(link_id)
+        .bind(bug_id)
+        .bind(bug_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn create_attachments(
+    pool: &SqlitePool,
+    bug_id: i64,
+    files: &[(String, String, i64, String)],
+) -> Result<Vec<Attachment>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut ids = Vec::new();
+    for (filename, content_type, size, sha256) in files {
+        let result = sqlx::query(
+            "INSERT INTO attachments (bug_id, filename, content_type, size, sha256) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(bug_id)
+        .bind(filename)
+        .bind(content_type)
+        .bind(size)
+        .bind(sha256)
+        .execute(&mut *tx)
+        .await?;
+        ids.push(result.last_insert_rowid());
+    }
+    tx.commit().await?;
+
+    let mut attachments = Vec::new();
+    for id in ids {
+        attachments.extend(get_attachment_by_id(pool, id).await?);
+    }
+    Ok(attachments)
+}
+
+pub async fn get_attachments_for_bug(pool: &SqlitePool, bug_id: i64) -> Result<Vec<Attachment>, sqlx::Error> {
+    sqlx::query_as::<_, Attachment>("SELECT * FROM attachments WHERE bug_id = ? ORDER BY id")
+        .bind(bug_id)
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_attachment_by_id(pool: &SqlitePool, attachment_id: i64) -> Result<Option<Attachment>, sqlx::Error> {
+    sqlx::query_as::<_, Attachment>("SELECT * FROM attachments WHERE id = ?")
+        .bind(attachment_id)
+        .fetch_optional(pool)
+        .await
+}
+
+// Returns the deleted attachment's hash if its file is now unreferenced.
+pub async fn delete_attachment(pool: &SqlitePool, attachment_id: i64) -> Result<Option<Vec<String>>, sqlx::Error> {
+    let Some(attachment) = get_attachment_by_id(pool, attachment_id).await? else {
+        return Ok(None);
+    };
+    sqlx::query("DELETE FROM attachments WHERE id = ?")
+        .bind(attachment_id)
+        .execute(pool)
+        .await?;
+    Ok(Some(unreferenced_hashes(pool, &[attachment.sha256]).await?))
+}
+
+// The subset of `hashes` that no attachment row points at.
+pub async fn unreferenced_hashes(pool: &SqlitePool, hashes: &[String]) -> Result<Vec<String>, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    unreferenced_hashes_on(&mut conn, hashes).await
+}
+
+async fn unreferenced_hashes_on(
+    conn: &mut sqlx::SqliteConnection,
+    hashes: &[String],
+) -> Result<Vec<String>, sqlx::Error> {
+    let mut unreferenced = Vec::new();
+    for sha256 in hashes {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM attachments WHERE sha256 = ?")
+            .bind(sha256)
+            .fetch_one(&mut *conn)
+            .await?;
+        if count == 0 && !unreferenced.contains(sha256) {
+            unreferenced.push(sha256.clone());
+        }
+    }
+    Ok(unreferenced)
+}
+
+pub async fn get_all_labels(pool: &SqlitePool) -> Result<Vec<Label>, sqlx::Error> {
+    sqlx::query_as::<_, Label>("SELECT * FROM labels ORDER BY name")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_label_by_id(pool: &SqlitePool, label_id: i64) -> Result<Option<Label>, sqlx::Error> {
+    sqlx::query_as::<_, Label>("SELECT * FROM labels WHERE id = ?")
+        .bind(label_id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn create_label(pool: &SqlitePool, label: &NewLabel) -> Result<Label, sqlx::Error> {
+    let result = sqlx::query("INSERT INTO labels (name, color, description) VALUES (?, ?, ?)")
+        .bind(label.name.trim())
+        .bind(label.color.as_deref().unwrap_or(DEFAULT_LABEL_COLOR))
+        .bind(&label.description)
+        .execute(pool)
+        .await?;
+
+    get_label_by_id(pool, result.last_insert_rowid()).await?
+        .ok_or(sqlx::Error::RowNotFound)
+}
+
+pub async fn update_label(
+    pool: &SqlitePool,
+    label_id: i64,
+    changes: &UpdateLabel,
+) -> Result<Option<Label>, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE labels SET name = COALESCE(?, name), color = COALESCE(?, color),
+         description = CASE WHEN ? THEN ? ELSE description END WHERE id = ?"
+    )
+    .bind(changes.name.as_deref().map(str::trim))
+    .bind(&changes.color)
+    .bind(changes.description.is_some())
+    .bind(changes.description.clone().flatten())
+    .bind(label_id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Ok(None);
+    }
+    get_label_by_id(pool, label_id).await
+}
+
+// Also removes the label from every bug (ON DELETE CASCADE).
+pub async fn delete_label(pool: &SqlitePool, label_id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM labels WHERE id = ?")
+        .bind(label_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// Looks labels up by name (case-insensitively). Returns the names that
+// don't exist as the error.
+pub async fn find_labels(
+    pool: &SqlitePool,
+    names: &[String],
+) -> Result<Result<Vec<Label>, Vec<String>>, sqlx::Error> {
+    let all = get_all_labels(pool).await?;
+    let mut found = Vec::new();
+    let mut missing = Vec::new();
+    for name in names {
+        match all.iter().find(|l| l.name.eq_ignore_ascii_case(name.trim())) {
+            Some(label) => found.push(label.clone()),
+            None => missing.push(name.trim().to_string()),
+        }
+    }
+    if missing.is_empty() { Ok(Ok(found)) } else { Ok(Err(missing)) }
+}
+
+pub async fn add_bug_labels(pool: &SqlitePool, bug_id: i64, labels: &[Label]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for label in labels {
+        sqlx::query("INSERT OR IGNORE INTO bug_labels (bug_id, label_id) VALUES (?, ?)")
+            .bind(bug_id)
+            .bind(label.id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+pub async fn remove_bug_labels(pool: &SqlitePool, bug_id: i64, labels: &[Label]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for label in labels {
+        sqlx::query("DELETE FROM bug_labels WHERE bug_id = ? AND label_id = ?")
+            .bind(bug_id)
+            .bind(label.id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+// Applies one operation to each bug in a single transaction. A bug that
+// fails a check is reported and skipped without touching the database, so
+// the rest can still commit; with `all_or_nothing` any failure rolls the
+// whole batch back. `severity` and `status` must already be canonical and
+// `labels` resolved.
+pub async fn bulk_update_bugs(
+    pool: &SqlitePool,
+    bug_ids: &[i64],
+    operation: &BulkOperation,
+    labels: &[Label],
+    policy: CapacityPolicy,
+    actor: &str,
+    all_or_nothing: bool,
+) -> Result<BulkResult, sqlx::Error> {
+    let mut tx = begin_write(pool).await?;
+    let mut results = Vec::with_capacity(bug_ids.len());
+
+    for &bug_id in bug_ids {
+        let previous_status = match operation {
+            BulkOperation::Transition { .. } => fetch_bug(&mut tx, bug_id).await?.map(|bug| bug.status),
+            _ => None,
+        };
+        let outcome = bulk_apply(&mut tx, bug_id, operation, labels, policy, actor).await?;
+        results.push(match outcome {
+            Ok(warning) => BulkItemResult { bug_id, ok: true, error: None, warning, previous_status },
+            Err(error) => BulkItemResult { bug_id, ok: false, error: Some(error), warning: None, previous_status: None },
+        });
+    }
+
+    let failed = results.iter().filter(|r| !r.ok).count();
+    let committed = !(all_or_nothing && failed > 0);
+    if committed {
+        tx.commit().await?;
+    } else {
+        tx.rollback().await?;
+    }
+
+    Ok(BulkResult {
+        operation: operation.name(),
+        all_or_nothing,
+        committed,
+        succeeded: results.len() - failed,
+        failed,
+        results,
+    })
+}
+
+// Checks and writes one bug. Returns the assignment warning, if any, or
+// why the bug was skipped.
+async fn bulk_apply(
+    conn: &mut sqlx::SqliteConnection,
+    bug_id: i64,
+    operation: &BulkOperation,
+    labels: &[Label],
+    policy: CapacityPolicy,
+    actor: &str,
+) -> Result<Result<Option<String>, String>, sqlx::Error> {
+    let Some(bug) = fetch_bug(conn, bug_id).await? else {
+        return Ok(Err(format!("Bug #{} not found", bug_id)));
+    };
+
+    match operation {
+        BulkOperation::Assign { developer_id } => {
+            let warning = match check_assignment_on(conn, bug_id, *developer_id, policy).await? {
+                Ok(warning) => warning,
+                Err(e) => return Ok(Err(e.message())),
+            };
+            sqlx::query("UPDATE bugs SET developer_id = ? WHERE id = ?")
+                .bind(developer_id)
+                .bind(bug_id)
+                .execute(&mut *conn)
+                .await?;
+            Ok(Ok(warning))
+        }
+        BulkOperation::SetSeverity { severity } => {
+            sqlx::query("UPDATE bugs SET severity = ? WHERE id = ?")
+                .bind(severity)
+                .bind(bug_id)
+                .execute(&mut *conn)
+                .await?;
+            Ok(Ok(None))
+        }
+        BulkOperation::AddLabels { .. } => {
+            for label in labels {
+                sqlx::query("INSERT OR IGNORE INTO bug_labels (bug_id, label_id) VALUES (?, ?)")
+                    .bind(bug_id)
+                    .bind(label.id)
+                    .execute(&mut *conn)
+                    .await?;
+            }
+            Ok(Ok(None))
+        }
+        BulkOperation::Transition { status } => {
+            if !allowed_transitions(&bug.status).contains(&status.as_str()) {
+                return Ok(Err(format!("Cannot move bug #{} from {} to {}", bug_id, bug.status, status)));
+            }
+            let warning = match check_reopen_on(conn, &bug, status, policy).await? {
+                Ok(warning) => warning,
+                Err(e) => return Ok(Err(e.message())),
+            };
+            sqlx::query("UPDATE bugs SET status = ? WHERE id = ?")
+                .bind(status)
+                .bind(bug_id)
+                .execute(&mut *conn)
+                .await?;
+            Ok(Ok(warning))
+        }
+        BulkOperation::Delete => {
+            sqlx::query("UPDATE bugs SET deleted_at = CURRENT_TIMESTAMP, deleted_by = ? WHERE id = ?")
+                .bind(actor)
+                .bind(bug_id)
+                .execute(&mut *conn)
+                .await?;
+            Ok(Ok(None))
+        }
+    }
+}
+
+pub async fn get_user_by_username(pool: &SqlitePool, username: &str) -> Result<Option<User>, sqlx::Error> {
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
         .bind(username)
         .fetch_optional(pool)
         .await?;
     Ok(user)
-}
\ No newline at end of file
+}
+
+pub async fn get_users(pool: &SqlitePool) -> Result<Vec<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>("SELECT * FROM users ORDER BY username")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_usernames(pool: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT username FROM users ORDER BY username")
+        .fetch_all(pool)
+        .await
+}
+
+// Webhooks. `events` is stored as a comma-separated list.
+type WebhookRow = (i64, String, String, String, bool, String);
+
+fn webhook_from_row((id, url, _secret, events, active, created_at): WebhookRow) -> Webhook {
+    Webhook {
+        id,
+        url,
+        events: events.split(',').filter(|e| !e.is_empty()).map(str::to_string).collect(),
+        active,
+        created_at,
+        secret: None,
+    }
+}
+
+pub async fn get_webhooks(pool: &SqlitePool) -> Result<Vec<Webhook>, sqlx::Error> {
+    let rows: Vec<WebhookRow> = sqlx::query_as(
+        "SELECT id, url, secret, events, active, created_at FROM webhooks ORDER BY id"
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(webhook_from_row).collect())
+}
+
+pub async fn get_webhook(pool: &SqlitePool, webhook_id: i64) -> Result<Option<Webhook>, sqlx::Error> {
+    let row: Option<WebhookRow> = sqlx::query_as(
+        "SELECT id, url, secret, events, active, created_at FROM webhooks WHERE id = ?"
+    )
+    .bind(webhook_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(webhook_from_row))
+}
+
+// Returns the webhook with its secret, which is only ever shown here.
+pub async fn create_webhook(
+    pool: &SqlitePool,
+    url: &str,
+    secret: &str,
+    events: &[String],
+    active: bool,
+) -> Result<Webhook, sqlx::Error> {
+    let result = sqlx::query("INSERT INTO webhooks (url, secret, events, active) VALUES (?, ?, ?, ?)")
+        .bind(url)
+        .bind(secret)
+        .bind(events.join(","))
+        .bind(active)
+        .execute(pool)
+        .await?;
+    let mut webhook = get_webhook(pool, result.last_insert_rowid()).await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+    webhook.secret = Some(secret.to_string());
+    Ok(webhook)
+}
+
+pub async fn update_webhook(
+    pool: &SqlitePool,
+    webhook_id: i64,
+    changes: &UpdateWebhook,
+) -> Result<Option<Webhook>, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE webhooks SET url = COALESCE(?, url), secret = COALESCE(?, secret),
+         events = COALESCE(?, events), active = COALESCE(?, active) WHERE id = ?"
+    )
+    .bind(changes.url.as_deref().map(str::trim))
+    .bind(&changes.secret)
+    .bind(changes.events.as_ref().map(|events| events.join(",")))
+    .bind(changes.active)
+    .bind(webhook_id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Ok(None);
+    }
+    get_webhook(pool, webhook_id).await
+}
+
+// Also drops the webhook's delivery log (ON DELETE CASCADE).
+pub async fn delete_webhook(pool: &SqlitePool, webhook_id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM webhooks WHERE id = ?")
+        .bind(webhook_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// Queues the event for every active webhook subscribed to it. Returns how
+// many deliveries were queued.
+pub async fn enqueue_webhook_deliveries(pool: &SqlitePool, event: &str, payload: &str) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO webhook_deliveries (webhook_id, event, payload)
+         SELECT id, ?, ? FROM webhooks
+         WHERE active = 1 AND ',' || events || ',' LIKE '%,' || ? || ',%'"
+    )
+    .bind(event)
+    .bind(payload)
+    .bind(event)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+// Newest first.
+pub async fn get_webhook_deliveries(
+    pool: &SqlitePool,
+    webhook_id: i64,
+    status: Option<&str>,
+    limit: i64,
+) -> Result<Vec<WebhookDelivery>, sqlx::Error> {
+    sqlx::query_as::<_, WebhookDelivery>(
+        "SELECT * FROM webhook_deliveries WHERE webhook_id = ? AND (? IS NULL OR status = ?)
+         ORDER BY id DESC LIMIT ?"
+    )
+    .bind(webhook_id)
+    .bind(status)
+    .bind(status)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_webhook_delivery(
+    pool: &SqlitePool,
+    webhook_id: i64,
+    delivery_id: i64,
+) -> Result<Option<WebhookDelivery>, sqlx::Error> {
+    sqlx::query_as::<_, WebhookDelivery>("SELECT * FROM webhook_deliveries WHERE id = ? AND webhook_id = ?")
+        .bind(delivery_id)
+        .bind(webhook_id)
+        .fetch_optional(pool)
+        .await
+}
+
+// Queues a fresh copy of a delivery, whatever became of the original.
+pub async fn redeliver_webhook(
+    pool: &SqlitePool,
+    webhook_id: i64,
+    delivery_id: i64,
+) -> Result<Option<WebhookDelivery>, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO webhook_deliveries (webhook_id, event, payload, redelivery_of)
+         SELECT webhook_id, event, payload, id FROM webhook_deliveries WHERE id = ? AND webhook_id = ?"
+    )
+    .bind(delivery_id)
+    .bind(webhook_id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Ok(None);
+    }
+    get_webhook_delivery(pool, webhook_id, result.last_insert_rowid()).await
+}
+
+// Pending deliveries whose next attempt is due, oldest first. Deliveries
+// for inactive webhooks wait until the webhook is switched back on.
+pub async fn due_webhook_deliveries(pool: &SqlitePool, limit: i64) -> Result<Vec<DueWebhookDelivery>, sqlx::Error> {
+    sqlx::query_as::<_, DueWebhookDelivery>(
+        "SELECT d.id, d.event, d.payload, d.attempts, w.url, w.secret
+         FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id
+         WHERE d.status = 'pending' AND d.next_attempt_at <= CURRENT_TIMESTAMP AND w.active = 1
+         ORDER BY d.next_attempt_at, d.id LIMIT ?"
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn record_webhook_attempt(
+    pool: &SqlitePool,
+    delivery_id: i64,
+    attempt: &WebhookAttempt,
+) -> Result<(), sqlx::Error> {
+    let status = match (attempt.delivered, attempt.retry_in_seconds) {
+        (true, _) => "delivered",
+        (false, Some(_)) => "pending",
+        (false, None) => "failed",
+    };
+    sqlx::query(
+        "UPDATE webhook_deliveries SET status = ?, attempts = attempts + 1,
+         last_attempt_at = CURRENT_TIMESTAMP,
+         next_attempt_at = CASE WHEN ? IS NULL THEN NULL ELSE datetime('now', '+' || ? || ' seconds') END,
+         response_status = ?, response_body = ?, error = ?
+         WHERE id = ?"
+    )
+    .bind(status)
+    .bind(attempt.retry_in_seconds)
+    .bind(attempt.retry_in_seconds)
+    .bind(attempt.response_status)
+    .bind(&attempt.response_body)
+    .bind(&attempt.error)
+    .bind(delivery_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// `None` when the developer has never saved any settings.
+pub async fn get_notification_settings(
+    pool: &SqlitePool,
+    developer_id: i64,
+) -> Result<Option<NotificationSettings>, sqlx::Error> {
+    sqlx::query_as::<_, NotificationSettings>("SELECT * FROM notification_settings WHERE developer_id = ?")
+        .bind(developer_id)
+        .fetch_optional(pool)
+        .await
+}
+
+// Stores the settings whole; the caller merges changes into the old ones.
+pub async fn save_notification_settings(pool: &SqlitePool, settings: &NotificationSettings) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO notification_settings (developer_id, email, delivery, on_assigned, on_status_change, on_comment)
+         VALUES (?, ?, ?, ?, ?, ?)
+         ON CONFLICT(developer_id) DO UPDATE SET email = excluded.email, delivery = excluded.delivery,
+         on_assigned = excluded.on_assigned, on_status_change = excluded.on_status_change,
+         on_comment = excluded.on_comment"
+    )
+    .bind(settings.developer_id)
+    .bind(&settings.email)
+    .bind(&settings.delivery)
+    .bind(settings.on_assigned)
+    .bind(settings.on_status_change)
+    .bind(settings.on_comment)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// Whether a developer receives notifications at this address.
+pub async fn is_developer_email(pool: &SqlitePool, email: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM notification_settings WHERE email = ? COLLATE NOCASE)")
+        .bind(email)
+        .fetch_one(pool)
+        .await
+}
+
+// Queues a notification if the developer is active, has an address and
+// wants this `kind` (`assigned`, `status_changed` or `comment`). Returns
+// whether one was queued.
+pub async fn queue_notification(
+    pool: &SqlitePool,
+    developer_id: i64,
+    bug_id: i64,
+    kind: &str,
+    summary: &str,
+    detail: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO notifications (developer_id, bug_id, kind, summary, detail)
+         SELECT s.developer_id, ?, ?, ?, ? FROM notification_settings s
+         JOIN developers d ON d.id = s.developer_id
+         WHERE s.developer_id = ? AND d.active = 1 AND s.delivery != 'off'
+         AND CASE ? WHEN 'assigned' THEN s.on_assigned
+                    WHEN 'status_changed' THEN s.on_status_change
+                    ELSE s.on_comment END = 1"
+    )
+    .bind(bug_id)
+    .bind(kind)
+    .bind(summary)
+    .bind(detail)
+    .bind(developer_id)
+    .bind(kind)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// Unsent notifications with attempts left and not waiting for a retry,
+// grouped by developer and oldest first. A developer's digest is due once
+// their oldest notification has waited `digest_minutes`; the worker sends
+// the whole group then.
+pub async fn pending_notifications(
+    pool: &SqlitePool,
+    max_attempts: i64,
+    digest_minutes: i64,
+) -> Result<Vec<PendingNotification>, sqlx::Error> {
+    sqlx::query_as::<_, PendingNotification>(
+        "SELECT n.id, n.developer_id, d.name AS developer_name, s.email, s.delivery, n.bug_id,
+                b.title AS bug_title, n.summary, n.detail, n.attempts,
+                (s.delivery = 'immediate' OR n.created_at <= datetime('now', '-' || ? || ' minutes')) AS due
+         FROM notifications n
+         JOIN notification_settings s ON s.developer_id = n.developer_id
+         JOIN developers d ON d.id = n.developer_id
+         JOIN bugs b ON b.id = n.bug_id
+         WHERE n.sent_at IS NULL AND n.attempts < ? AND s.delivery != 'off'
+         AND (n.next_attempt_at IS NULL OR n.next_attempt_at <= CURRENT_TIMESTAMP)
+         ORDER BY n.developer_id, n.id"
+    )
+    .bind(digest_minutes)
+    .bind(max_attempts)
+    .fetch_all(pool)
+    .await
+}
+
+// Marks notifications sent (`error` is `None`) or counts a failed attempt,
+// to be retried after `retry_in_seconds`.
+pub async fn record_notification_attempt(
+    pool: &SqlitePool,
+    notification_ids: &[i64],
+    error: Option<&str>,
+    retry_in_seconds: i64,
+) -> Result<(), sqlx::Error> {
+    if notification_ids.is_empty() {
+        return Ok(());
+    }
+    let mut query = QueryBuilder::<Sqlite>::new("UPDATE notifications SET attempts = attempts + 1, error = ");
+    query.push_bind(error)
+        .push(", sent_at = CASE WHEN ")
+        .push_bind(error)
+        .push(" IS NULL THEN CURRENT_TIMESTAMP END, next_attempt_at = datetime('now', '+' || ")
+        .push_bind(retry_in_seconds)
+        .push(" || ' seconds') WHERE id IN (");
+    let mut separated = query.separated(", ");
+    for id in notification_ids {
+        separated.push_bind(id);
+    }
+    query.push(")");
+    query.build().execute(pool).await?;
+    Ok(())
+}
+
+// Records that `commit_id` fixed the bug, returning false if that was
+// already recorded.
+pub async fn record_git_fix(
+    pool: &SqlitePool,
+    repository: &str,
+    commit_id: &str,
+    bug_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("INSERT OR IGNORE INTO git_fixes (repository, commit_id, bug_id) VALUES (?, ?, ?)")
+        .bind(repository)
+        .bind(commit_id)
+        .bind(bug_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// The project of a bug, trashed or not; events about deleted bugs and
+// their comments still belong to it.
+pub async fn get_bug_project_id(pool: &SqlitePool, bug_id: i64) -> Result<Option<i64>, sqlx::Error> {
+    let project_id: Option<Option<i64>> = sqlx::query_scalar("SELECT project_id FROM bugs WHERE id = ?")
+        .bind(bug_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(project_id.flatten())
+}
+
+// Appends an event to the log and returns its id, which is the stream's
+// event id.
+pub async fn log_event(
+    pool: &SqlitePool,
+    event: &str,
+    project_id: Option<i64>,
+    payload: &str,
+) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query("INSERT INTO event_log (event, project_id, payload) VALUES (?, ?, ?)")
+        .bind(event)
+        .bind(project_id)
+        .bind(payload)
+        .execute(pool)
+        .await?;
+    Ok(result.last_insert_rowid())
+}
+
+// Logged events after `after_id`, oldest first, optionally only those of
+// one project.
+pub async fn events_since(
+    pool: &SqlitePool,
+    after_id: i64,
+    project_id: Option<i64>,
+    limit: i64,
+) -> Result<Vec<LoggedEvent>, sqlx::Error> {
+    sqlx::query_as::<_, LoggedEvent>(
+        "SELECT id, event, payload FROM event_log
+         WHERE id > ? AND (? IS NULL OR project_id = ?)
+         ORDER BY id LIMIT ?"
+    )
+    .bind(after_id)
+    .bind(project_id)
+    .bind(project_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+// Keeps only the newest `keep` events.
+pub async fn prune_event_log(pool: &SqlitePool, keep: i64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM event_log WHERE id <= (SELECT MAX(id) FROM event_log) - ?")
+        .bind(keep)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+// 0 while the log is empty.
+pub async fn latest_event_id(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM event_log")
+        .fetch_one(pool)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn new_bug(pool: &SqlitePool, title: &str) -> i64 {
+        let bug = NewBug {
+            title: title.to_string(),
+            description: String::new(),
+            reported_by: "tester".to_string(),
+            severity: String::new(),
+            project_id: None,
+            component: None,
+        };
+        create_bug(pool, &bug).await.unwrap().id.unwrap()
+    }
+
+    async fn new_label(pool: &SqlitePool, name: &str) -> Label {
+        let label = NewLabel { name: name.to_string(), color: None, description: None };
+        create_label(pool, &label).await.unwrap()
+    }
+
+    async fn titles(pool: &SqlitePool, filter: &BugFilter) -> Vec<String> {
+        let mut titles: Vec<String> = get_bugs(pool, filter).await.unwrap().into_iter().map(|b| b.title).collect();
+        titles.sort();
+        titles
+    }
+
+    async fn new_developer(pool: &SqlitePool, name: &str, max_open_bugs: Option<i64>) -> i64 {
+        let developer = NewDeveloper { name: name.to_string(), skills: vec![], components: vec![] };
+        let id = create_developer(pool, &developer).await.unwrap().id;
+        let changes = UpdateDeveloper { name: None, active: None, max_open_bugs: Some(max_open_bugs), skills: None, components: None };
+        update_developer(pool, id, &changes).await.unwrap();
+        id
+    }
+
+    async fn developer_of(pool: &SqlitePool, bug_id: i64) -> Option<i64> {
+        get_bug_by_id(pool, bug_id).await.unwrap().unwrap().developer_id
+    }
+
+    #[tokio::test]
+    async fn reassigning_a_deleted_developers_bugs_respects_capacity() {
+        let pool = test_pool().await;
+        let leaving = new_developer(&pool, "leaving", None).await;
+        let target = new_developer(&pool, "target", Some(2)).await;
+        let (a, b, c) = (new_bug(&pool, "a").await, new_bug(&pool, "b").await, new_bug(&pool, "c").await);
+        assign_bug(&pool, a, target, CapacityPolicy::Refuse).await.unwrap().unwrap();
+        for bug in [b, c] {
+            assign_bug(&pool, bug, leaving, CapacityPolicy::Refuse).await.unwrap().unwrap();
+        }
+
+        let outcome = delete_developer(&pool, leaving, DeletePolicy::Reassign, Some(target), CapacityPolicy::Refuse).await.unwrap();
+        assert!(matches!(
+            outcome,
+            DeleteDeveloperOutcome::Refused { moving: 2, error: AssignmentError::OverCapacity { open_bugs: 1, max_open_bugs: 2, .. } }
+        ));
+        assert_eq!(developer_of(&pool, b).await, Some(leaving));
+
+        let outcome = delete_developer(&pool, leaving, DeletePolicy::Reassign, Some(target), CapacityPolicy::Warn).await.unwrap();
+        assert!(matches!(outcome, DeleteDeveloperOutcome::Deleted { ref bugs_moved, warning: Some(_) } if *bugs_moved == vec![b, c]));
+        assert_eq!(developer_of(&pool, c).await, Some(target));
+    }
+
+    #[tokio::test]
+    async fn reassigning_closed_bugs_needs_no_capacity() {
+        let pool = test_pool().await;
+        let leaving = new_developer(&pool, "leaving", None).await;
+        let target = new_developer(&pool, "target", Some(0)).await;
+        let bug = new_bug(&pool, "closed").await;
+        assign_bug(&pool, bug, leaving, CapacityPolicy::Refuse).await.unwrap().unwrap();
+        sqlx::query("UPDATE bugs SET status = 'Closed' WHERE id = ?").bind(bug).execute(&pool).await.unwrap();
+
+        let outcome = delete_developer(&pool, leaving, DeletePolicy::Reassign, Some(target), CapacityPolicy::Refuse).await.unwrap();
+        assert!(matches!(outcome, DeleteDeveloperOutcome::Deleted { warning: None, .. }));
+        assert_eq!(developer_of(&pool, bug).await, Some(target));
+    }
+
+    #[tokio::test]
+    async fn purging_reports_only_files_nothing_else_uses() {
+        let pool = test_pool().await;
+        let (a, b, live) = (new_bug(&pool, "a").await, new_bug(&pool, "b").await, new_bug(&pool, "live").await);
+        let file = |sha256: &str| ("log.txt".to_string(), "text/plain".to_string(), 3, sha256.to_string());
+        create_attachments(&pool, a, &[file("shared"), file("only-a")]).await.unwrap();
+        create_attachments(&pool, b, &[file("shared")]).await.unwrap();
+        create_attachments(&pool, live, &[file("kept")]).await.unwrap();
+        for bug in [a, b] {
+            delete_bug(&pool, bug, "admin").await.unwrap();
+        }
+
+        // Recently deleted bugs stay until they are old enough.
+        assert_eq!(purge_trash(&pool, None, 30).await.unwrap(), (0, vec![]));
+        assert_eq!(purge_trash(&pool, Some(a), 0).await.unwrap(), (1, vec!["only-a".to_string()]));
+        assert_eq!(purge_trash(&pool, None, 0).await.unwrap(), (1, vec!["shared".to_string()]));
+        assert!(get_bug_by_id(&pool, live).await.unwrap().is_some());
+    }
+
+    fn outcomes(result: &BulkResult) -> Vec<bool> {
+        result.results.iter().map(|r| r.ok).collect()
+    }
+
+    #[tokio::test]
+    async fn all_or_nothing_rolls_the_batch_back_on_any_failure() {
+        let pool = test_pool().await;
+        let bug = new_bug(&pool, "a").await;
+        let operation = BulkOperation::SetSeverity { severity: "High".to_string() };
+
+        let result = bulk_update_bugs(&pool, &[bug, 999], &operation, &[], CapacityPolicy::Refuse, "admin", true).await.unwrap();
+        assert_eq!((outcomes(&result), result.committed), (vec![true, false], false));
+        assert_eq!(result.results[1].error.as_deref(), Some("Bug #999 not found"));
+        assert_eq!(get_bug_by_id(&pool, bug).await.unwrap().unwrap().severity, None);
+
+        let result = bulk_update_bugs(&pool, &[bug, 999], &operation, &[], CapacityPolicy::Refuse, "admin", false).await.unwrap();
+        assert_eq!((outcomes(&result), result.committed), (vec![true, false], true));
+        assert_eq!(get_bug_by_id(&pool, bug).await.unwrap().unwrap().severity.as_deref(), Some("High"));
+    }
+
+    #[tokio::test]
+    async fn bulk_assignments_count_earlier_items_against_capacity() {
+        let pool = test_pool().await;
+        let developer = new_developer(&pool, "dana", Some(2)).await;
+        let bugs = [new_bug(&pool, "a").await, new_bug(&pool, "b").await, new_bug(&pool, "c").await, new_bug(&pool, "d").await];
+        assign_bug(&pool, bugs[0], developer, CapacityPolicy::Refuse).await.unwrap().unwrap();
+        let operation = BulkOperation::Assign { developer_id: developer };
+
+        let result = bulk_update_bugs(&pool, &bugs[1..3], &operation, &[], CapacityPolicy::Refuse, "admin", false).await.unwrap();
+        assert_eq!((outcomes(&result), result.committed), (vec![true, false], true));
+        assert_eq!(result.results[1].error, Some(capacity_message(developer, 2, 2)));
+        assert_eq!(developer_of(&pool, bugs[2]).await, None);
+
+        let result = bulk_update_bugs(&pool, &bugs[2..], &operation, &[], CapacityPolicy::Warn, "admin", false).await.unwrap();
+        assert_eq!(outcomes(&result), vec![true, true]);
+        assert_eq!(result.results[1].warning, Some(capacity_message(developer, 3, 2)));
+    }
+
+    #[tokio::test]
+    async fn bulk_transitions_follow_the_workflow_per_bug() {
+        let pool = test_pool().await;
+        let developer = new_developer(&pool, "dana", Some(1)).await;
+        let (open, closed, full) = (new_bug(&pool, "open").await, new_bug(&pool, "closed").await, new_bug(&pool, "full").await);
+        assign_bug(&pool, full, developer, CapacityPolicy::Refuse).await.unwrap().unwrap();
+        assign_bug(&pool, open, developer, CapacityPolicy::Warn).await.unwrap().unwrap();
+        for bug in [closed, full] {
+            sqlx::query("UPDATE bugs SET status = 'Closed' WHERE id = ?").bind(bug).execute(&pool).await.unwrap();
+        }
+
+        let operation = BulkOperation::Transition { status: "Resolved".to_string() };
+        let result = bulk_update_bugs(&pool, &[open, closed], &operation, &[], CapacityPolicy::Refuse, "admin", false).await.unwrap();
+        assert_eq!(outcomes(&result), vec![true, false]);
+        assert_eq!(result.results[0].previous_status.as_deref(), Some("Open"));
+        assert_eq!(result.results[1].error.as_deref(), Some(format!("Cannot move bug #{} from Closed to Resolved", closed).as_str()));
+
+        // Reopening `full` would give its developer a second open bug.
+        sqlx::query("UPDATE bugs SET status = 'Open' WHERE id = ?").bind(open).execute(&pool).await.unwrap();
+        let operation = BulkOperation::Transition { status: "Open".to_string() };
+        let result = bulk_update_bugs(&pool, &[closed, full], &operation, &[], CapacityPolicy::Refuse, "admin", false).await.unwrap();
+        assert_eq!(outcomes(&result), vec![true, false]);
+        assert_eq!(status(&pool, full).await, "Closed");
+        assert_eq!(status(&pool, closed).await, "Open");
+    }
+
+    async fn mark_duplicate(pool: &SqlitePool, bug_id: i64, of: i64) -> Result<Option<String>, LinkError> {
+        let link = NewBugLink { kind: LinkKind::DuplicateOf, target_id: of };
+        create_bug_link(pool, bug_id, &link).await.unwrap()
+    }
+
+    async fn status(pool: &SqlitePool, bug_id: i64) -> String {
+        get_bug_by_id(pool, bug_id).await.unwrap().unwrap().status
+    }
+
+    #[tokio::test]
+    async fn duplicates_point_at_the_root_of_the_chain() {
+        let pool = test_pool().await;
+        let (a, b, c, d) = (new_bug(&pool, "a").await, new_bug(&pool, "b").await, new_bug(&pool, "c").await, new_bug(&pool, "d").await);
+
+        assert_eq!(mark_duplicate(&pool, a, b).await.unwrap().as_deref(), Some("Open"));
+        // B's own duplicates move along with it.
+        assert_eq!(mark_duplicate(&pool, b, c).await.unwrap().as_deref(), Some("Open"));
+        // Marking a bug as a duplicate of a duplicate links it to the root.
+        mark_duplicate(&pool, d, a).await.unwrap();
+
+        let mut edges = get_link_edges(&pool, LinkKind::DuplicateOf).await.unwrap();
+        edges.sort();
+        assert_eq!(edges, vec![(a, c), (b, c), (d, c)]);
+        for bug in [a, b, d] {
+            assert_eq!(status(&pool, bug).await, "Closed");
+        }
+        assert_eq!(status(&pool, c).await, "Open");
+    }
+
+    #[tokio::test]
+    async fn a_closed_bug_is_not_closed_again() {
+        let pool = test_pool().await;
+        let (a, b) = (new_bug(&pool, "a").await, new_bug(&pool, "b").await);
+        sqlx::query("UPDATE bugs SET status = 'Closed' WHERE id = ?").bind(a).execute(&pool).await.unwrap();
+
+        assert_eq!(mark_duplicate(&pool, a, b).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn duplicate_links_refuse_repeats_and_cycles() {
+        let pool = test_pool().await;
+        let (a, b, c) = (new_bug(&pool, "a").await, new_bug(&pool, "b").await, new_bug(&pool, "c").await);
+        mark_duplicate(&pool, a, b).await.unwrap();
+
+        assert!(matches!(mark_duplicate(&pool, a, c).await, Err(LinkError::AlreadyDuplicate { of }) if of == b));
+        assert!(matches!(mark_duplicate(&pool, b, a).await, Err(LinkError::Cycle(path)) if path == vec![b, a, b]));
+        assert!(matches!(mark_duplicate(&pool, c, c).await, Err(LinkError::SelfLink)));
+        assert_eq!(status(&pool, b).await, "Open");
+    }
+
+    #[tokio::test]
+    async fn label_filters_match_names_in_any_case() {
+        let pool = test_pool().await;
+        let (ui, backend) = (new_label(&pool, "UI").await, new_label(&pool, "Backend").await);
+        let both = new_bug(&pool, "both").await;
+        let only_ui = new_bug(&pool, "only ui").await;
+        new_bug(&pool, "none").await;
+        add_bug_labels(&pool, both, &[ui.clone(), backend]).await.unwrap();
+        add_bug_labels(&pool, only_ui, &[ui]).await.unwrap();
+
+        let any = BugFilter { labels_any: Some("ui,missing".to_string()), ..Default::default() };
+        assert_eq!(titles(&pool, &any).await, vec!["both", "only ui"]);
+
+        let all = BugFilter { labels_all: Some("ui,BACKEND".to_string()), ..Default::default() };
+        assert_eq!(titles(&pool, &all).await, vec!["both"]);
+
+        let none = BugFilter { labels_none: Some("backend".to_string()), ..Default::default() };
+        assert_eq!(titles(&pool, &none).await, vec!["none", "only ui"]);
+    }
+
+    #[tokio::test]
+    async fn labels_all_counts_a_repeated_name_once() {
+        let pool = test_pool().await;
+        let ui = new_label(&pool, "UI").await;
+        let bug = new_bug(&pool, "labelled").await;
+        add_bug_labels(&pool, bug, &[ui]).await.unwrap();
+
+        let all = BugFilter { labels_all: Some("UI,ui".to_string()), ..Default::default() };
+        assert_eq!(titles(&pool, &all).await, vec!["labelled"]);
+    }
+}
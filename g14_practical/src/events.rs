@@ -0,0 +1,151 @@
+use serde_json::{json, Value};
+use crate::database;
+use crate::models::{Bug, Comment, LoggedEvent};
+use crate::notifications;
+use crate::state::AppState;
+
+// Events describing changes to bugs, published by the handlers once a
+// change is saved. Each one is appended to the event log and sent to the
+// live `GET /events` streams (see `sse.rs`), queued for the webhooks
+// subscribed to it (see `webhooks.rs`), and assignments, status changes
+// and comments also notify the developer by email (see
+// `notifications.rs`). Publishing never fails the request that caused it;
+// problems are only logged.
+
+// The event log keeps roughly this many of the newest events, which is how
+// far back a reconnecting stream can resume.
+const EVENT_LOG_RETENTION: i64 = 10_000;
+
+// The log is pruned on every this-many-th event rather than on each one.
+const PRUNE_EVERY: i64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    BugCreated,
+    BugUpdated,
+    BugAssigned,
+    BugDeleted,
+    CommentCreated,
+}
+
+pub const EVENT_TYPES: [&str; 5] = ["bug.created", "bug.updated", "bug.assigned", "bug.deleted", "comment.created"];
+
+impl EventKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            EventKind::BugCreated => "bug.created",
+            EventKind::BugUpdated => "bug.updated",
+            EventKind::BugAssigned => "bug.assigned",
+            EventKind::BugDeleted => "bug.deleted",
+            EventKind::CommentCreated => "comment.created",
+        }
+    }
+}
+
+// Every event carries a unique `id`, so receivers can ignore repeats
+// (deliveries are retried, and can be redelivered by hand). `project_id`
+// is the bug's project, for streams that follow a single project.
+pub async fn publish(app_state: &AppState, kind: EventKind, project_id: Option<i64>, data: Value) {
+    let payload = json!({
+        "id": uuid::Uuid::new_v4().to_string(),
+        "event": kind.name(),
+        "occurred_at": chrono::Utc::now().to_rfc3339(),
+        "data": data,
+    })
+    .to_string();
+
+    stream(app_state, kind, project_id, &payload).await;
+    match database::enqueue_webhook_deliveries(&app_state.db, kind.name(), &payload).await {
+        Ok(0) => {}
+        Ok(queued) => {
+            println!("📣 Queued {} webhook delivery(ies) for {}", queued, kind.name());
+            app_state.webhook_wakeup.notify_one();
+        }
+        Err(e) => eprintln!("❌ Failed to queue {} webhooks: {}", kind.name(), e),
+    }
+}
+
+// Logs the event and hands it to the live streams. Sending only fails
+// when nobody is listening, which is fine.
+async fn stream(app_state: &AppState, kind: EventKind, project_id: Option<i64>, payload: &str) {
+    let id = match database::log_event(&app_state.db, kind.name(), project_id, payload).await {
+        Ok(id) => id,
+        Err(e) => {
+            eprintln!("❌ Failed to log {} event: {}", kind.name(), e);
+            return;
+        }
+    };
+    let _ = app_state.event_stream.send(LoggedEvent {
+        id,
+        event: kind.name().to_string(),
+        payload: payload.to_string(),
+    });
+
+    if id % PRUNE_EVERY == 0 {
+        match database::prune_event_log(&app_state.db, EVENT_LOG_RETENTION).await {
+            Ok(0) => {}
+            Ok(pruned) => println!("🧹 Pruned {} old event(s) from the event log", pruned),
+            Err(e) => eprintln!("❌ Failed to prune the event log: {}", e),
+        }
+    }
+}
+
+pub async fn bug_event(app_state: &AppState, kind: EventKind, bug: &Bug) {
+    publish(app_state, kind, bug.project_id, json!({ "bug": bug })).await;
+    if kind == EventKind::BugAssigned {
+        notifications::bug_assigned(app_state, bug).await;
+    }
+}
+
+// For callers that only have the id; the bug is loaded as saved.
+pub async fn bug_event_by_id(app_state: &AppState, kind: EventKind, bug_id: i64) {
+    match database::get_bug_by_id(&app_state.db, bug_id).await {
+        Ok(Some(bug)) => bug_event(app_state, kind, &bug).await,
+        Ok(None) => {}
+        Err(e) => eprintln!("❌ Failed to load bug #{} for {}: {}", bug_id, kind.name(), e),
+    }
+}
+
+// A `bug.updated` that moved the bug out of `previous_status`.
+pub async fn bug_status_changed(app_state: &AppState, bug: &Bug, previous_status: &str) {
+    publish(
+        app_state,
+        EventKind::BugUpdated,
+        bug.project_id,
+        json!({ "bug": bug, "previous_status": previous_status }),
+    )
+    .await;
+    notifications::status_changed(app_state, bug, previous_status).await;
+}
+
+pub async fn bug_status_changed_by_id(app_state: &AppState, bug_id: i64, previous_status: &str) {
+    match database::get_bug_by_id(&app_state.db, bug_id).await {
+        Ok(Some(bug)) => bug_status_changed(app_state, &bug, previous_status).await,
+        Ok(None) => {}
+        Err(e) => eprintln!("❌ Failed to load bug #{} for bug.updated: {}", bug_id, e),
+    }
+}
+
+pub async fn bug_deleted(app_state: &AppState, bug_id: i64, deleted_by: &str) {
+    let project_id = project_of(app_state, bug_id).await;
+    publish(app_state, EventKind::BugDeleted, project_id, json!({ "bug_id": bug_id, "deleted_by": deleted_by })).await;
+}
+
+pub async fn comment_created(app_state: &AppState, comment: &Comment) {
+    let project_id = project_of(app_state, comment.bug_id).await;
+    publish(
+        app_state,
+        EventKind::CommentCreated,
+        project_id,
+        json!({ "bug_id": comment.bug_id, "comment": comment }),
+    )
+    .await;
+    notifications::comment_added(app_state, comment).await;
+}
+
+async fn project_of(app_state: &AppState, bug_id: i64) -> Option<i64> {
+    database::get_bug_project_id(&app_state.db, bug_id).await.unwrap_or_else(|e| {
+        eprintln!("❌ Failed to load the project of bug #{}: {}", bug_id, e);
+        None
+    })
+}
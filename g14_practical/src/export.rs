@@ -0,0 +1,286 @@
+use actix_web::web::Bytes;
+use futures_util::Stream;
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use crate::database;
+use crate::models::{BugFilter, ExportRow};
+
+// Bug exports for spreadsheets and scripts. Rows are streamed from SQLite
+// through a small channel and encoded one at a time, so an export never
+// holds more than a handful of bugs in memory.
+
+pub const COLUMNS: [&str; 12] = [
+    "id", "title", "description", "reported_by", "severity", "status", "component",
+    "developer_id", "developer_name", "project_id", "project_name", "labels",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn parse(format: Option<&str>) -> Option<Self> {
+        match format.map(str::trim).map(str::to_ascii_lowercase).as_deref() {
+            None | Some("") | Some("csv") => Some(ExportFormat::Csv),
+            Some("json") => Some(ExportFormat::Json),
+            Some("ndjson") => Some(ExportFormat::Ndjson),
+            Some(_) => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+// Columns in the order given; all of them when none are asked for. Returns
+// the unknown names as the error.
+pub fn parse_columns(spec: Option<&str>) -> Result<Vec<&'static str>, Vec<String>> {
+    let requested: Vec<&str> = spec.unwrap_or("")
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .collect();
+    if requested.is_empty() {
+        return Ok(COLUMNS.to_vec());
+    }
+
+    let mut columns = Vec::new();
+    let mut unknown = Vec::new();
+    for name in requested {
+        match COLUMNS.iter().find(|c| c.eq_ignore_ascii_case(name)) {
+            Some(column) if !columns.contains(column) => columns.push(*column),
+            Some(_) => {}
+            None => unknown.push(name.to_string()),
+        }
+    }
+    if unknown.is_empty() { Ok(columns) } else { Err(unknown) }
+}
+
+// Starts the query and returns the encoded body. A database error part
+// way through ends the stream with an error, so the client sees a
+// truncated download rather than a silently short one.
+pub fn stream(
+    pool: sqlx::SqlitePool,
+    filter: BugFilter,
+    format: ExportFormat,
+    columns: Vec<&'static str>,
+    project_names: HashMap<i64, String>,
+) -> impl Stream<Item = Result<Bytes, actix_web::Error>> + 'static {
+    let (sender, receiver) = mpsc::channel(64);
+    tokio::spawn(async move {
+        if let Err(e) = database::export_bugs(&pool, &filter, &sender).await {
+            eprintln!("❌ Bug export failed: {}", e);
+            let _ = sender.send(Err(e)).await;
+        }
+    });
+
+    let encoder = Encoder { format, columns, project_names, rows: 0 };
+    futures_util::stream::unfold(
+        Some((receiver, encoder, false)),
+        |state| async move {
+            let (mut receiver, mut encoder, started) = state?;
+            if !started {
+                let header = encoder.header();
+                return Some((header, Some((receiver, encoder, true))));
+            }
+            match receiver.recv().await {
+                Some(Ok(row)) => {
+                    let chunk = encoder.row(row);
+                    let next = chunk.is_ok().then_some((receiver, encoder, true));
+                    Some((chunk, next))
+                }
+                Some(Err(_)) => Some((Err(actix_web::error::ErrorInternalServerError("Export failed")), None)),
+                None => Some((Ok(encoder.footer()), None)),
+            }
+        },
+    )
+}
+
+struct Encoder {
+    format: ExportFormat,
+    columns: Vec<&'static str>,
+    project_names: HashMap<i64, String>,
+    rows: usize,
+}
+
+impl Encoder {
+    fn header(&self) -> Result<Bytes, actix_web::Error> {
+        match self.format {
+            ExportFormat::Csv => csv_record(self.columns.iter().copied()),
+            ExportFormat::Json => Ok(Bytes::from_static(b"[")),
+            ExportFormat::Ndjson => Ok(Bytes::new()),
+        }
+    }
+
+    fn row(&mut self, mut row: ExportRow) -> Result<Bytes, actix_web::Error> {
+        row.project_name = row.project_id.and_then(|id| self.project_names.get(&id).cloned());
+        let values: Vec<Value> = self.columns.iter().map(|column| value(&row, column)).collect();
+        self.rows += 1;
+
+        match self.format {
+            ExportFormat::Csv => csv_record(values.iter().map(|v| match v {
+                Value::Null => String::new(),
+                Value::String(s) => escape_formula(s),
+                other => other.to_string(),
+            })),
+            ExportFormat::Json | ExportFormat::Ndjson => {
+                // Written by hand so keys keep the requested column order.
+                let fields: Vec<String> = self.columns.iter()
+                    .zip(&values)
+                    .map(|(column, value)| format!("{}:{}", Value::from(*column), value))
+                    .collect();
+                let object = format!("{{{}}}", fields.join(","));
+                let line = match self.format {
+                    ExportFormat::Json if self.rows > 1 => format!(",\n{}", object),
+                    ExportFormat::Json => format!("\n{}", object),
+                    _ => format!("{}\n", object),
+                };
+                Ok(Bytes::from(line))
+            }
+        }
+    }
+
+    fn footer(&self) -> Bytes {
+        match self.format {
+            ExportFormat::Json if self.rows > 0 => Bytes::from_static(b"\n]\n"),
+            ExportFormat::Json => Bytes::from_static(b"]\n"),
+            _ => Bytes::new(),
+        }
+    }
+}
+
+fn value(row: &ExportRow, column: &str) -> Value {
+    match column {
+        "id" => row.id.into(),
+        "title" => row.title.clone().into(),
+        "description" => row.description.clone().into(),
+        "reported_by" => row.reported_by.clone().into(),
+        "severity" => row.severity.clone().into(),
+        "status" => row.status.clone().into(),
+        "component" => row.component.clone().into(),
+        "developer_id" => row.developer_id.into(),
+        "developer_name" => row.developer_name.clone().into(),
+        "project_id" => row.project_id.into(),
+        "project_name" => row.project_name.clone().into(),
+        "labels" => row.labels.clone().into(),
+        _ => Value::Null,
+    }
+}
+
+// Spreadsheets run a cell starting with one of these as a formula, so
+// such text (a title like "=HYPERLINK(...)") is exported with a leading
+// apostrophe and shows up as typed.
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+fn escape_formula(text: &str) -> String {
+    if text.starts_with(FORMULA_PREFIXES) {
+        format!("'{}", text)
+    } else {
+        text.to_string()
+    }
+}
+
+fn csv_record<I, T>(fields: I) -> Result<Bytes, actix_web::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(fields).map_err(actix_web::error::ErrorInternalServerError)?;
+    let bytes = writer.into_inner().map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
+    Ok(Bytes::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: i64, title: &str) -> ExportRow {
+        ExportRow {
+            id,
+            title: title.to_string(),
+            description: None,
+            reported_by: Some("dana@example.org".to_string()),
+            severity: Some("High".to_string()),
+            status: "Open".to_string(),
+            component: None,
+            developer_id: None,
+            developer_name: None,
+            project_id: Some(2),
+            project_name: None,
+            labels: Some("ui,regression".to_string()),
+        }
+    }
+
+    fn encode(format: ExportFormat, columns: &[&'static str], rows: Vec<ExportRow>) -> String {
+        let project_names = HashMap::from([(2, "Backend API".to_string())]);
+        let mut encoder = Encoder { format, columns: columns.to_vec(), project_names, rows: 0 };
+        let mut out = encoder.header().unwrap().to_vec();
+        for row in rows {
+            out.extend_from_slice(&encoder.row(row).unwrap());
+        }
+        out.extend_from_slice(&encoder.footer());
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn parse_columns_keeps_order_and_reports_unknown_names() {
+        assert_eq!(parse_columns(Some("Title, id,title")).unwrap(), ["title", "id"]);
+        assert_eq!(parse_columns(Some(" , ")).unwrap(), COLUMNS);
+        assert_eq!(parse_columns(Some("id,owner,due")).unwrap_err(), ["owner", "due"]);
+    }
+
+    #[test]
+    fn csv_quotes_fields_and_fills_in_project_names() {
+        let csv = encode(ExportFormat::Csv, &["id", "title", "project_name", "labels", "developer_id"], vec![
+            row(1, "Crash, then \"hang\""),
+        ]);
+        assert_eq!(csv, "id,title,project_name,labels,developer_id\n1,\"Crash, then \"\"hang\"\"\",Backend API,\"ui,regression\",\n");
+    }
+
+    #[test]
+    fn csv_defuses_formulas() {
+        let csv = encode(ExportFormat::Csv, &["title"], vec![
+            row(1, "=HYPERLINK(\"http://evil\")"),
+            row(2, "+1"),
+            row(3, "-"),
+            row(4, "@SUM(A1)"),
+            row(5, "a = b"),
+        ]);
+        assert_eq!(csv, "title\n\"'=HYPERLINK(\"\"http://evil\"\")\"\n'+1\n'-\n'@SUM(A1)\na = b\n");
+        assert_eq!(escape_formula("\tcmd"), "'\tcmd");
+    }
+
+    #[test]
+    fn json_keeps_the_requested_column_order() {
+        let json = encode(ExportFormat::Json, &["title", "id", "description"], vec![row(1, "A"), row(2, "B")]);
+        assert_eq!(json, "[\n{\"title\":\"A\",\"id\":1,\"description\":null},\n{\"title\":\"B\",\"id\":2,\"description\":null}\n]\n");
+        let parsed: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.as_array().unwrap().len(), 2);
+
+        assert_eq!(encode(ExportFormat::Json, &["id"], Vec::new()), "[]\n");
+    }
+
+    #[test]
+    fn ndjson_writes_one_object_per_line() {
+        let ndjson = encode(ExportFormat::Ndjson, &["id", "severity"], vec![row(1, "A"), row(2, "B")]);
+        assert_eq!(ndjson, "{\"id\":1,\"severity\":\"High\"}\n{\"id\":2,\"severity\":\"High\"}\n");
+    }
+}
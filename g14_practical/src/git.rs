@@ -0,0 +1,381 @@
+use actix_web::http::header::HeaderMap;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use crate::auth;
+use crate::bugs::{self, TransitionOutcome};
+use crate::database;
+use crate::events;
+use crate::models::OPEN_STATUSES;
+use crate::state::AppState;
+
+// Bug references in commit messages. `refs #12` (also `references`, `ref`,
+// `see`) adds a comment linking the commit to bug #12; `fixes #12` (also
+// `fix`, `fixed`, `closes`, `resolves`, ...) does the same and, for commits
+// on the repository's default branch, moves the bug to `GIT_FIX_STATUS`.
+// One keyword can name several bugs: `fixes #12, #13 and #14`.
+//
+// Commits arrive as push webhooks from GitHub, Gitea or GitLab, signed
+// with `GIT_WEBHOOK_SECRET`, or in a generic JSON form. Each commit is
+// recorded on each bug once, so re-sent pushes, and a fix pushed to a
+// branch and later merged, comment only once. Fixes are recorded the same
+// way: a commit moves a bug at most once, even if the bug is reopened and
+// the commit pushed again.
+
+// Push payloads for large pushes can be big.
+pub const MAX_PAYLOAD_BYTES: usize = 5 * 1024 * 1024;
+
+const FIX_KEYWORDS: [&str; 9] = ["fix", "fixes", "fixed", "close", "closes", "closed", "resolve", "resolves", "resolved"];
+const REF_KEYWORDS: [&str; 4] = ["ref", "refs", "references", "see"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Provider {
+    GitHub,
+    Gitea,
+    GitLab,
+}
+
+impl Provider {
+    // Gitea also sends the GitHub headers, so it is checked first.
+    pub fn detect(headers: &HeaderMap) -> Option<Self> {
+        if headers.contains_key("x-gitea-event") {
+            Some(Provider::Gitea)
+        } else if headers.contains_key("x-github-event") {
+            Some(Provider::GitHub)
+        } else if headers.contains_key("x-gitlab-event") {
+            Some(Provider::GitLab)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Provider::GitHub => "GitHub",
+            Provider::Gitea => "Gitea",
+            Provider::GitLab => "GitLab",
+        }
+    }
+
+    // Whether the delivery is a push; others (pings, tag pushes, issue
+    // events) are acknowledged and ignored.
+    pub fn is_push(self, headers: &HeaderMap) -> bool {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).unwrap_or("");
+        match self {
+            Provider::GitHub => header("x-github-event") == "push",
+            Provider::Gitea => header("x-gitea-event") == "push",
+            Provider::GitLab => header("x-gitlab-event") == "Push Hook",
+        }
+    }
+
+    // GitHub and Gitea sign the body with HMAC-SHA256; GitLab sends the
+    // secret itself as a token.
+    pub fn verify(self, headers: &HeaderMap, body: &[u8], secret: &str) -> bool {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        match self {
+            Provider::GitHub => header("x-hub-signature-256")
+                .and_then(|signature| signature.strip_prefix("sha256="))
+                .is_some_and(|signature| valid_signature(secret, body, signature)),
+            Provider::Gitea => header("x-gitea-signature")
+                .is_some_and(|signature| valid_signature(secret, body, signature)),
+            Provider::GitLab => header("x-gitlab-token")
+                .is_some_and(|token| auth::constant_time_eq(token.as_bytes(), secret.as_bytes())),
+        }
+    }
+}
+
+fn valid_signature(secret: &str, body: &[u8], hex_signature: &str) -> bool {
+    let Ok(signature) = hex::decode(hex_signature.trim()) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+pub struct GitCommit {
+    pub id: String,
+    pub message: String,
+    pub url: Option<String>,
+    pub author: String,
+}
+
+pub struct GitPush {
+    pub repository: String,
+    pub branch: Option<String>,
+    pub default_branch: Option<String>,
+    // False for pushes to tags and other refs that aren't branches.
+    pub to_branch: bool,
+    pub commits: Vec<GitCommit>,
+}
+
+impl GitPush {
+    // Fixes only count once they reach the default branch, and never from
+    // a tag. When either branch is unknown, every branch push counts.
+    fn applies_fixes(&self) -> bool {
+        if !self.to_branch {
+            return false;
+        }
+        match (&self.branch, &self.default_branch) {
+            (Some(branch), Some(default_branch)) => branch == default_branch,
+            _ => true,
+        }
+    }
+}
+
+// The parts of a GitHub, Gitea or GitLab push payload that are used.
+// GitHub and Gitea describe the repository in `repository`; GitLab has a
+// sparser `repository` too, but names the project and its default branch
+// in `project`.
+#[derive(Deserialize)]
+struct PushPayload {
+    #[serde(rename = "ref", default)]
+    git_ref: String,
+    repository: Option<PushRepository>,
+    project: Option<PushRepository>,
+    #[serde(default)]
+    commits: Vec<PushCommit>,
+}
+
+#[derive(Deserialize)]
+struct PushRepository {
+    #[serde(alias = "path_with_namespace")]
+    full_name: Option<String>,
+    default_branch: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PushCommit {
+    id: String,
+    #[serde(default)]
+    message: String,
+    url: Option<String>,
+    author: Option<PushAuthor>,
+}
+
+#[derive(Deserialize)]
+struct PushAuthor {
+    name: Option<String>,
+    username: Option<String>,
+}
+
+pub fn parse_push(body: &[u8]) -> Result<GitPush, String> {
+    let payload: PushPayload = serde_json::from_slice(body).map_err(|e| format!("Invalid push payload: {}", e))?;
+    let repository = payload.project.or(payload.repository)
+        .ok_or_else(|| "Invalid push payload: no repository".to_string())?;
+    Ok(GitPush {
+        repository: repository.full_name.unwrap_or_else(|| "unknown".to_string()),
+        branch: payload.git_ref.strip_prefix("refs/heads/").map(str::to_string),
+        default_branch: repository.default_branch,
+        to_branch: payload.git_ref.is_empty() || payload.git_ref.starts_with("refs/heads/"),
+        commits: payload.commits.into_iter()
+            .map(|commit| GitCommit {
+                author: commit.author
+                    .and_then(|author| author.name.or(author.username))
+                    .unwrap_or_else(|| "unknown".to_string()),
+                id: commit.id,
+                message: commit.message,
+                url: commit.url,
+            })
+            .collect(),
+    })
+}
+
+// Body of `POST /integrations/git/commits`, for hosts and scripts without
+// a supported webhook format.
+#[derive(Debug, Deserialize)]
+pub struct GenericPush {
+    pub repository: String,
+    #[serde(default)]
+    pub branch: Option<String>,
+    #[serde(default)]
+    pub default_branch: Option<String>,
+    pub commits: Vec<GenericCommit>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GenericCommit {
+    pub id: String,
+    pub message: String,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+}
+
+impl From<GenericPush> for GitPush {
+    fn from(push: GenericPush) -> Self {
+        GitPush {
+            repository: push.repository,
+            branch: push.branch,
+            default_branch: push.default_branch,
+            to_branch: true,
+            commits: push.commits.into_iter()
+                .map(|commit| GitCommit {
+                    id: commit.id,
+                    message: commit.message,
+                    url: commit.url,
+                    author: commit.author.unwrap_or_else(|| "unknown".to_string()),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GitReport {
+    pub repository: String,
+    pub commits: usize,
+    pub references: Vec<ReferenceResult>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReferenceResult {
+    pub commit: String,
+    pub bug_id: i64,
+    // `commented`, `fixed`, `already_recorded` or `bug_not_found`.
+    pub action: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+// Bugs referenced in a commit message, each once, with whether any
+// reference to it was a fix.
+pub fn references(message: &str) -> Vec<(i64, bool)> {
+    let words: Vec<&str> = message.split_whitespace().collect();
+    let mut found: Vec<(i64, bool)> = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let keyword = words[i].trim_start_matches('(').trim_end_matches(':').to_ascii_lowercase();
+        let fixes = FIX_KEYWORDS.contains(&keyword.as_str());
+        if !fixes && !REF_KEYWORDS.contains(&keyword.as_str()) {
+            i += 1;
+            continue;
+        }
+        i += 1;
+        while i < words.len() {
+            if words[i].eq_ignore_ascii_case("and") {
+                i += 1;
+                continue;
+            }
+            let Some(bug_id) = bug_number(words[i]) else {
+                break;
+            };
+            match found.iter_mut().find(|(id, _)| *id == bug_id) {
+                Some(existing) => existing.1 |= fixes,
+                None => found.push((bug_id, fixes)),
+            }
+            i += 1;
+        }
+    }
+    found
+}
+
+// `#12`, allowing trailing punctuation.
+fn bug_number(word: &str) -> Option<i64> {
+    word.trim_end_matches([',', '.', ';', ')'])
+        .strip_prefix('#')?
+        .parse()
+        .ok()
+}
+
+// Comments on (and, for fixes, transitions) every bug the commits
+// reference.
+pub async fn apply(app_state: &AppState, push: &GitPush) -> Result<GitReport, sqlx::Error> {
+    let fix_status = app_state.config.git_fix_status;
+    let applies_fixes = push.applies_fixes();
+    let mut report = GitReport { repository: push.repository.clone(), commits: push.commits.len(), references: Vec::new() };
+
+    for commit in &push.commits {
+        let short_id: String = commit.id.chars().take(7).collect();
+        for (bug_id, fixes) in references(&commit.message) {
+            let fixing = fixes && applies_fixes;
+            let mut result = ReferenceResult { commit: commit.id.clone(), bug_id, action: "already_recorded", warning: None };
+            let Some(bug) = database::get_bug_by_id(&app_state.db, bug_id).await? else {
+                result.action = "bug_not_found";
+                report.references.push(result);
+                continue;
+            };
+
+            let external_ref = format!("git:{}@{}#{}", push.repository, commit.id, bug_id);
+            if database::get_comment_by_external_ref(&app_state.db, &external_ref).await?.is_none() {
+                let verb = if fixes { "Fixed" } else { "Referenced" };
+                let summary = commit.message.lines().next().unwrap_or("").trim();
+                let mut body = format!("{} in commit {} ({}): {}", verb, short_id, push.repository, summary);
+                if let Some(url) = &commit.url {
+                    body.push_str(&format!("\n\n{}", url));
+                }
+                if let Some(comment) = database::create_comment(
+                    &app_state.db, bug_id, &commit.author, &body, Some(&external_ref),
+                ).await? {
+                    events::comment_created(app_state, &comment).await;
+                    result.action = "commented";
+                }
+            }
+
+            // Bugs already resolved or closed are left alone, and so is a
+            // bug this commit has fixed before.
+            if fixing
+                && OPEN_STATUSES.contains(&bug.status.as_str())
+                && database::record_git_fix(&app_state.db, &push.repository, &commit.id, bug_id).await?
+            {
+                match bugs::transition(app_state, bug_id, fix_status).await? {
+                    TransitionOutcome::Moved(bug, previous_status) => {
+                        println!("🔧 Commit {} moved bug #{} from {} to {}", short_id, bug_id, previous_status, bug.status);
+                        events::bug_status_changed(app_state, &bug, &previous_status).await;
+                        result.action = "fixed";
+                    }
+                    TransitionOutcome::NotFound => result.action = "bug_not_found",
+                    TransitionOutcome::Rejected(message) => result.warning = Some(message),
+                    TransitionOutcome::Refused(error) => result.warning = Some(error.message()),
+                }
+            }
+            report.references.push(result);
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_finds_fixes_and_mentions() {
+        assert_eq!(references("Fixes #12 and #13, refs #14."), vec![(12, true), (13, true), (14, false)]);
+        assert_eq!(references("(closes: #7)"), vec![(7, true)]);
+        assert_eq!(references("See #3; later resolved #3"), vec![(3, true)]);
+    }
+
+    #[test]
+    fn references_ignores_bare_numbers_and_other_words() {
+        assert!(references("Bump version to 1.2 #4").is_empty());
+        assert!(references("fix typo in #readme").is_empty());
+        assert_eq!(references("fix #5 #x #6"), vec![(5, true)]);
+    }
+
+    #[test]
+    fn fixes_only_apply_from_the_default_branch() {
+        let push = |git_ref: &str| {
+            parse_push(format!(
+                r#"{{"ref": "{}", "repository": {{"full_name": "acme/app", "default_branch": "main"}}, "commits": []}}"#,
+                git_ref
+            ).as_bytes()).unwrap()
+        };
+        assert!(push("refs/heads/main").applies_fixes());
+        assert!(!push("refs/heads/feature").applies_fixes());
+        assert!(!push("refs/tags/main").applies_fixes());
+    }
+
+    #[test]
+    fn parse_push_reads_gitlab_projects() {
+        let push = parse_push(br#"{
+            "ref": "refs/heads/main",
+            "project": {"path_with_namespace": "group/app", "default_branch": "main"},
+            "commits": [{"id": "abc", "message": "Fix #1", "author": {"name": "Dana"}}]
+        }"#).unwrap();
+        assert_eq!(push.repository, "group/app");
+        assert_eq!(push.commits[0].author, "Dana");
+        assert!(push.applies_fixes());
+    }
+}
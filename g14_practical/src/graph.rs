@@ -0,0 +1,335 @@
+use crate::models::{severity_weight, Bug, DependencyGraph, GraphEdge, GraphNode, OPEN_STATUSES};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+// Graph helpers over bug links, stored as (from, to) edges.
+
+// Shortest path from `from` to `to` following edge direction, including
+// both ends. Used to reject blocking links that would close a cycle.
+pub fn find_path(edges: &[(i64, i64)], from: i64, to: i64) -> Option<Vec<i64>> {
+    let mut next: HashMap<i64, Vec<i64>> = HashMap::new();
+    for (a, b) in edges {
+        next.entry(*a).or_default().push(*b);
+    }
+
+    let mut came_from: HashMap<i64, i64> = HashMap::new();
+    let mut seen: HashSet<i64> = HashSet::from([from]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(node) = queue.pop_front() {
+        if node == to {
+            let mut path = vec![to];
+            let mut current = to;
+            while let Some(previous) = came_from.get(&current) {
+                path.push(*previous);
+                current = *previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for neighbour in next.get(&node).into_iter().flatten() {
+            if seen.insert(*neighbour) {
+                came_from.insert(*neighbour, node);
+                queue.push_back(*neighbour);
+            }
+        }
+    }
+    None
+}
+
+// Kahn's algorithm. Among nodes that are ready at the same time the one
+// with the lowest `priority` key goes first. Nodes stuck behind a cycle
+// are left out of the order.
+pub fn topological_order<K: Ord>(
+    nodes: &[i64],
+    edges: &[(i64, i64)],
+    priority: impl Fn(i64) -> K,
+) -> Vec<i64> {
+    let mut incoming: HashMap<i64, usize> = nodes.iter().map(|n| (*n, 0)).collect();
+    let mut next: HashMap<i64, Vec<i64>> = HashMap::new();
+    for (a, b) in edges {
+        if incoming.contains_key(a) && let Some(count) = incoming.get_mut(b) {
+            *count += 1;
+            next.entry(*a).or_default().push(*b);
+        }
+    }
+
+    let mut ready: Vec<i64> = nodes.iter().copied().filter(|n| incoming[n] == 0).collect();
+    let mut order = Vec::new();
+    while !ready.is_empty() {
+        ready.sort_by_key(|n| std::cmp::Reverse(priority(*n)));
+        let node = ready.pop().unwrap();
+        order.push(node);
+        for neighbour in next.get(&node).into_iter().flatten() {
+            let count = incoming.get_mut(neighbour).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                ready.push(*neighbour);
+            }
+        }
+    }
+    order
+}
+
+// Strongly connected components with more than one node (or a self loop),
+// via Tarjan's algorithm. Each cycle is sorted, and cycles by first id.
+// The depth-first search keeps its own stack, so a long blocking chain
+// can't overflow the thread's.
+pub fn find_cycles(nodes: &[i64], edges: &[(i64, i64)]) -> Vec<Vec<i64>> {
+    struct Tarjan<'a> {
+        next: &'a HashMap<i64, Vec<i64>>,
+        index: HashMap<i64, usize>,
+        low: HashMap<i64, usize>,
+        stack: Vec<i64>,
+        on_stack: HashSet<i64>,
+        components: Vec<Vec<i64>>,
+    }
+
+    impl Tarjan<'_> {
+        fn enter(&mut self, node: i64) {
+            let index = self.index.len();
+            self.index.insert(node, index);
+            self.low.insert(node, index);
+            self.stack.push(node);
+            self.on_stack.insert(node);
+        }
+
+        fn lower(&mut self, node: i64, to: usize) {
+            let low = self.low[&node].min(to);
+            self.low.insert(node, low);
+        }
+
+        fn visit(&mut self, root: i64) {
+            // Each node being visited, with how many of its neighbours
+            // have been looked at.
+            let mut visiting = vec![(root, 0)];
+            self.enter(root);
+
+            while let Some((node, position)) = visiting.last_mut() {
+                let node = *node;
+                if let Some(&neighbour) = self.next.get(&node).and_then(|n| n.get(*position)) {
+                    *position += 1;
+                    if !self.index.contains_key(&neighbour) {
+                        self.enter(neighbour);
+                        visiting.push((neighbour, 0));
+                    } else if self.on_stack.contains(&neighbour) {
+                        self.lower(node, self.index[&neighbour]);
+                    }
+                    continue;
+                }
+
+                visiting.pop();
+                if let Some((parent, _)) = visiting.last() {
+                    self.lower(*parent, self.low[&node]);
+                }
+                if self.low[&node] == self.index[&node] {
+                    let mut component = Vec::new();
+                    while let Some(member) = self.stack.pop() {
+                        self.on_stack.remove(&member);
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    self.components.push(component);
+                }
+            }
+        }
+    }
+
+    let mut next: HashMap<i64, Vec<i64>> = HashMap::new();
+    for (a, b) in edges {
+        next.entry(*a).or_default().push(*b);
+    }
+    let mut tarjan = Tarjan {
+        next: &next,
+        index: HashMap::new(),
+        low: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: HashSet::new(),
+        components: Vec::new(),
+    };
+    for node in nodes {
+        if !tarjan.index.contains_key(node) {
+            tarjan.visit(*node);
+        }
+    }
+
+    let mut cycles: Vec<Vec<i64>> = tarjan.components.into_iter()
+        .filter(|c| c.len() > 1 || next.get(&c[0]).is_some_and(|n| n.contains(&c[0])))
+        .map(|mut c| {
+            c.sort_unstable();
+            c
+        })
+        .collect();
+    cycles.sort();
+    cycles
+}
+
+// Builds the blocking graph around a project from its bugs, the bugs on
+// the other end of any cross-project links, and every `blocks` edge.
+pub fn build_dependency_graph(
+    project_id: i64,
+    project_bugs: Vec<Bug>,
+    external_bugs: Vec<Bug>,
+    blocks: &[(i64, i64)],
+) -> DependencyGraph {
+    let in_project: HashSet<i64> = project_bugs.iter().filter_map(|b| b.id).collect();
+    let loaded: HashSet<i64> = external_bugs.iter().filter_map(|b| b.id).chain(in_project.iter().copied()).collect();
+    // Links to bugs in the trash are kept but left out of the graph.
+    let edges: Vec<(i64, i64)> = blocks.iter()
+        .copied()
+        .filter(|(a, b)| in_project.contains(a) || in_project.contains(b))
+        .filter(|(a, b)| loaded.contains(a) && loaded.contains(b))
+        .collect();
+
+    let nodes: Vec<GraphNode> = project_bugs.into_iter()
+        .chain(external_bugs)
+        .filter_map(|bug| {
+            let id = bug.id?;
+            Some(GraphNode {
+                id,
+                open: OPEN_STATUSES.contains(&bug.status.as_str()),
+                external: !in_project.contains(&id),
+                title: bug.title,
+                status: bug.status,
+                severity: bug.severity,
+                project_id: bug.project_id,
+            })
+        })
+        .collect();
+    let node_ids: Vec<i64> = nodes.iter().map(|n| n.id).collect();
+
+    // Closed and resolved bugs no longer hold anything up.
+    let open_ids: Vec<i64> = nodes.iter().filter(|n| n.open).map(|n| n.id).collect();
+    let open_edges: Vec<(i64, i64)> = edges.iter()
+        .copied()
+        .filter(|(a, b)| open_ids.contains(a) && open_ids.contains(b))
+        .collect();
+    let weight = |id: i64| {
+        let severity = nodes.iter().find(|n| n.id == id).and_then(|n| n.severity.as_deref());
+        (std::cmp::Reverse(severity_weight(severity)), id)
+    };
+    let fix_order = topological_order(&open_ids, &open_edges, weight);
+
+    DependencyGraph {
+        project_id,
+        cycles: find_cycles(&node_ids, &edges),
+        edges: edges.into_iter().map(|(from, to)| GraphEdge { from, to }).collect(),
+        nodes,
+        fix_order,
+    }
+}
+
+// Graphviz rendering of a dependency graph. Edges point from the blocking
+// bug to the bug it blocks; closed bugs are greyed out and edges inside a
+// cycle drawn in red.
+pub fn to_dot(graph: &DependencyGraph) -> String {
+    let same_cycle = |a: i64, b: i64| graph.cycles.iter().any(|c| c.contains(&a) && c.contains(&b));
+
+    let mut dot = format!("digraph \"project_{}\" {{\n", graph.project_id);
+    dot.push_str("    rankdir=LR;\n    node [shape=box, style=\"rounded,filled\", fillcolor=white];\n");
+    for node in &graph.nodes {
+        let mut attributes = format!(
+            "label=\"#{} {}\\n{} / {}\"",
+            node.id,
+            dot_escape(&node.title),
+            dot_escape(&node.status),
+            dot_escape(node.severity.as_deref().unwrap_or("-")),
+        );
+        if !node.open {
+            attributes.push_str(", fillcolor=\"#e9ecef\", fontcolor=\"#6c757d\"");
+        }
+        if node.external {
+            attributes.push_str(", style=\"rounded,dashed\"");
+        }
+        dot.push_str(&format!("    \"{}\" [{}];\n", node.id, attributes));
+    }
+    for edge in &graph.edges {
+        let colour = if same_cycle(edge.from, edge.to) { " [color=red]" } else { "" };
+        dot.push_str(&format!("    \"{}\" -> \"{}\"{};\n", edge.from, edge.to, colour));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn dot_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bug(id: i64, severity: &str, status: &str, project_id: i64) -> Bug {
+        Bug {
+            id: Some(id),
+            title: format!("Bug {}", id),
+            description: None,
+            reported_by: None,
+            severity: Some(severity.to_string()),
+            developer_id: None,
+            status: status.to_string(),
+            project_id: Some(project_id),
+            component: None,
+            labels: vec![],
+            deleted_at: None,
+            deleted_by: None,
+            external_ref: None,
+        }
+    }
+
+    #[test]
+    fn a_link_closing_a_cycle_has_a_path_back() {
+        let blocks = [(1, 2), (2, 3), (4, 3)];
+        // 3 blocks 1 would close 1 -> 2 -> 3 -> 1.
+        assert_eq!(find_path(&blocks, 1, 3), Some(vec![1, 2, 3]));
+        assert_eq!(find_path(&blocks, 3, 1), None);
+        assert_eq!(find_path(&blocks, 4, 1), None);
+    }
+
+    #[test]
+    fn fix_order_follows_links_then_severity() {
+        let bugs = vec![
+            bug(1, "Low", "Open", 1),
+            bug(2, "Critical", "Open", 1),
+            bug(3, "High", "In Progress", 1),
+            bug(4, "Critical", "Closed", 1),
+        ];
+        // 1 blocks 2; the closed 4 no longer holds up 3.
+        let graph = build_dependency_graph(1, bugs, vec![], &[(1, 2), (4, 3)]);
+        assert_eq!(graph.fix_order, vec![3, 1, 2]);
+        assert!(graph.cycles.is_empty());
+    }
+
+    #[test]
+    fn bugs_behind_a_cycle_are_left_out_of_the_fix_order() {
+        let bugs = (1..=4).map(|id| bug(id, "Medium", "Open", 1)).collect();
+        let graph = build_dependency_graph(1, bugs, vec![], &[(1, 2), (2, 1), (2, 3)]);
+        assert_eq!(graph.fix_order, vec![4]);
+        assert_eq!(graph.cycles, vec![vec![1, 2]]);
+    }
+
+    #[test]
+    fn cycles_include_self_loops_and_are_sorted() {
+        let edges = [(5, 4), (4, 5), (3, 3), (1, 2)];
+        assert_eq!(find_cycles(&[1, 2, 3, 4, 5], &edges), vec![vec![3], vec![4, 5]]);
+    }
+
+    #[test]
+    fn long_chains_do_not_overflow_the_stack() {
+        let nodes: Vec<i64> = (0..50_000).collect();
+        let mut edges: Vec<(i64, i64)> = nodes.windows(2).map(|w| (w[0], w[1])).collect();
+        edges.push((49_999, 49_998));
+        assert_eq!(find_cycles(&nodes, &edges), vec![vec![49_998, 49_999]]);
+    }
+
+    #[test]
+    fn dot_labels_are_escaped() {
+        let mut quoted = bug(1, "High", "Open", 1);
+        quoted.title = "Say \"hi\" \\ twice\nplease".to_string();
+        let graph = build_dependency_graph(1, vec![quoted], vec![bug(2, "Low", "Closed", 2)], &[(2, 1)]);
+        let dot = to_dot(&graph);
+        assert!(dot.contains(r##"label="#1 Say \"hi\" \\ twice please\nOpen / High""##), "{}", dot);
+        assert!(dot.contains(r##""2" [label="#2 Bug 2\nClosed / Low", fillcolor="#e9ecef", fontcolor="#6c757d", style="rounded,dashed"];"##));
+        assert!(dot.contains("\"2\" -> \"1\";"));
+    }
+}
@@ -0,0 +1,757 @@
+use async_graphql::dataloader::{DataLoader, HashMapCache, Loader};
+use async_graphql::{
+    Context, EmptySubscription, Error, ErrorExtensions, InputObject, MaybeUndefined, Object, Schema, SimpleObject,
+};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::bugs::{self, BugError};
+use crate::database;
+use crate::events::{self, EventKind};
+use crate::models::{
+    AssignmentError, Bug, BugFilter, Claims, Comment, Developer, Label, NewBug, Project, User, OPEN_STATUSES,
+};
+use crate::state::AppState;
+
+// `POST /graphql`: bugs, developers, projects and users in one round trip,
+// with bugs resolving their developer, project and comments and
+// developers and projects their bugs. Nested lookups go through
+// per-request DataLoaders, so a page of 50 bugs costs one developer query,
+// not 50.
+//
+// Access is as in REST: reading and the bug mutations are open, `me`
+// needs a token and `users` the admin role. Errors carry a `code`
+// extension: UNAUTHENTICATED, FORBIDDEN, BAD_USER_INPUT, NOT_FOUND,
+// CONFLICT or INTERNAL.
+
+pub type BugSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+// Bounds on what one query may ask for.
+const MAX_DEPTH: usize = 10;
+const MAX_COMPLEXITY: usize = 5000;
+
+pub fn schema(app_state: AppState) -> BugSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .data(app_state)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+// The caller: the token's claims, or why there are none (the same messages
+// as a REST 401).
+pub struct Viewer(pub Result<Claims, &'static str>);
+
+// Adds the caller and fresh loaders to a request, so batches and cached
+// rows never outlive it.
+pub fn prepare(request: async_graphql::Request, app_state: &AppState, viewer: Viewer) -> async_graphql::Request {
+    let db = &app_state.db;
+    request
+        .data(viewer)
+        .data(DataLoader::with_cache(DeveloperLoader(db.clone()), tokio::spawn, HashMapCache::default()))
+        .data(DataLoader::with_cache(AssignedBugsLoader(db.clone()), tokio::spawn, HashMapCache::default()))
+        .data(DataLoader::with_cache(ProjectBugsLoader(db.clone()), tokio::spawn, HashMapCache::default()))
+        .data(DataLoader::with_cache(CommentsLoader(db.clone()), tokio::spawn, HashMapCache::default()))
+}
+
+fn error(code: &'static str, message: impl Into<String>) -> Error {
+    Error::new(message).extend_with(|_, extensions| extensions.set("code", code))
+}
+
+// Logs the database error and hands the client a generic one, as the REST
+// handlers do.
+fn database_error(action: &str, e: impl std::fmt::Display) -> Error {
+    eprintln!("❌ GraphQL failed to {}: {}", action, e);
+    error("INTERNAL", format!("Failed to {}", action))
+}
+
+fn require_user<'a>(ctx: &'a Context<'_>) -> async_graphql::Result<&'a Claims> {
+    match &ctx.data_unchecked::<Viewer>().0 {
+        Ok(claims) => Ok(claims),
+        Err(message) => Err(error("UNAUTHENTICATED", *message)),
+    }
+}
+
+fn require_admin<'a>(ctx: &'a Context<'_>) -> async_graphql::Result<&'a Claims> {
+    let claims = require_user(ctx)?;
+    if claims.role != "admin" {
+        return Err(error("FORBIDDEN", "Admin role required"));
+    }
+    Ok(claims)
+}
+
+// Loaders. Errors are shared between the callers waiting on a batch, so
+// they are reference counted.
+
+pub struct DeveloperLoader(SqlitePool);
+
+impl Loader<i64> for DeveloperLoader {
+    type Value = Developer;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, ids: &[i64]) -> Result<HashMap<i64, Developer>, Self::Error> {
+        let developers = database::get_developers_by_ids(&self.0, ids).await?;
+        Ok(developers.into_iter().map(|d| (d.id, d)).collect())
+    }
+}
+
+// Bugs by assigned developer.
+pub struct AssignedBugsLoader(SqlitePool);
+
+impl Loader<i64> for AssignedBugsLoader {
+    type Value = Vec<Bug>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, developer_ids: &[i64]) -> Result<HashMap<i64, Vec<Bug>>, Self::Error> {
+        let bugs = database::get_bugs_for_developers(&self.0, developer_ids).await?;
+        Ok(group_by(bugs, |bug| bug.developer_id))
+    }
+}
+
+// Bugs by project.
+pub struct ProjectBugsLoader(SqlitePool);
+
+impl Loader<i64> for ProjectBugsLoader {
+    type Value = Vec<Bug>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, project_ids: &[i64]) -> Result<HashMap<i64, Vec<Bug>>, Self::Error> {
+        let bugs = database::get_bugs_for_projects(&self.0, project_ids).await?;
+        Ok(group_by(bugs, |bug| bug.project_id))
+    }
+}
+
+// Comments by bug.
+pub struct CommentsLoader(SqlitePool);
+
+impl Loader<i64> for CommentsLoader {
+    type Value = Vec<Comment>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, bug_ids: &[i64]) -> Result<HashMap<i64, Vec<Comment>>, Self::Error> {
+        let comments = database::get_comments_for_bugs(&self.0, bug_ids).await?;
+        Ok(group_by(comments, |comment| Some(comment.bug_id)))
+    }
+}
+
+// Keeps the rows' order within each group.
+fn group_by<T>(rows: Vec<T>, key: impl Fn(&T) -> Option<i64>) -> HashMap<i64, Vec<T>> {
+    let mut groups: HashMap<i64, Vec<T>> = HashMap::new();
+    for row in rows {
+        if let Some(key) = key(&row) {
+            groups.entry(key).or_default().push(row);
+        }
+    }
+    groups
+}
+
+// Output types.
+
+pub struct BugNode(Bug);
+
+#[Object(name = "Bug")]
+impl BugNode {
+    async fn id(&self) -> i64 {
+        self.0.id.unwrap_or_default()
+    }
+
+    async fn title(&self) -> &str {
+        &self.0.title
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.0.description.as_deref()
+    }
+
+    async fn reported_by(&self) -> Option<&str> {
+        self.0.reported_by.as_deref()
+    }
+
+    async fn severity(&self) -> Option<&str> {
+        self.0.severity.as_deref()
+    }
+
+    async fn status(&self) -> &str {
+        &self.0.status
+    }
+
+    async fn component(&self) -> Option<&str> {
+        self.0.component.as_deref()
+    }
+
+    async fn labels(&self) -> Vec<LabelNode> {
+        self.0.labels.iter().cloned().map(LabelNode::from).collect()
+    }
+
+    async fn developer_id(&self) -> Option<i64> {
+        self.0.developer_id
+    }
+
+    async fn developer(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<DeveloperNode>> {
+        let Some(developer_id) = self.0.developer_id else {
+            return Ok(None);
+        };
+        let developer = ctx.data_unchecked::<DataLoader<DeveloperLoader, HashMapCache>>()
+            .load_one(developer_id)
+            .await
+            .map_err(|e| database_error("load developers", e))?;
+        Ok(developer.map(DeveloperNode))
+    }
+
+    async fn project_id(&self) -> Option<i64> {
+        self.0.project_id
+    }
+
+    async fn project(&self, ctx: &Context<'_>) -> Option<ProjectNode> {
+        let project_id = self.0.project_id?;
+        ctx.data_unchecked::<AppState>().get_project(project_id).await.map(ProjectNode)
+    }
+
+    // Oldest first.
+    async fn comments(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<CommentNode>> {
+        let Some(bug_id) = self.0.id else {
+            return Ok(Vec::new());
+        };
+        let comments = ctx.data_unchecked::<DataLoader<CommentsLoader, HashMapCache>>()
+            .load_one(bug_id)
+            .await
+            .map_err(|e| database_error("load comments", e))?;
+        Ok(comments.unwrap_or_default().into_iter().map(CommentNode::from).collect())
+    }
+}
+
+pub struct DeveloperNode(Developer);
+
+#[Object(name = "Developer")]
+impl DeveloperNode {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn active(&self) -> bool {
+        self.0.active
+    }
+
+    async fn max_open_bugs(&self) -> Option<i64> {
+        self.0.max_open_bugs
+    }
+
+    async fn skills(&self) -> Vec<String> {
+        self.0.skills.clone()
+    }
+
+    async fn components(&self) -> Vec<String> {
+        self.0.components.clone()
+    }
+
+    // Bugs assigned to the developer, newest first and paged as the root
+    // `bugs`; `openOnly` leaves out resolved and closed ones.
+    #[graphql(complexity = "first.clamp(0, MAX_PAGE_SIZE) as usize * child_complexity")]
+    async fn bugs(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = false)] open_only: bool,
+        #[graphql(default_with = "DEFAULT_PAGE_SIZE")] first: i64,
+        #[graphql(default = 0)] offset: i64,
+    ) -> async_graphql::Result<BugPage> {
+        let bugs = ctx.data_unchecked::<DataLoader<AssignedBugsLoader, HashMapCache>>()
+            .load_one(self.0.id)
+            .await
+            .map_err(|e| database_error("load bugs", e))?;
+        let bugs = bugs.unwrap_or_default()
+            .into_iter()
+            .filter(|bug| !open_only || OPEN_STATUSES.contains(&bug.status.as_str()))
+            .collect();
+        Ok(BugPage::slice(bugs, first, offset))
+    }
+}
+
+pub struct ProjectNode(Project);
+
+#[Object(name = "Project")]
+impl ProjectNode {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn description(&self) -> &str {
+        &self.0.description
+    }
+
+    async fn active(&self) -> bool {
+        self.0.active
+    }
+
+    async fn components(&self) -> Vec<String> {
+        self.0.components.clone()
+    }
+
+    // Newest first, paged as the root `bugs`.
+    #[graphql(complexity = "first.clamp(0, MAX_PAGE_SIZE) as usize * child_complexity")]
+    async fn bugs(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "DEFAULT_PAGE_SIZE")] first: i64,
+        #[graphql(default = 0)] offset: i64,
+    ) -> async_graphql::Result<BugPage> {
+        let bugs = ctx.data_unchecked::<DataLoader<ProjectBugsLoader, HashMapCache>>()
+            .load_one(self.0.id)
+            .await
+            .map_err(|e| database_error("load bugs", e))?;
+        Ok(BugPage::slice(bugs.unwrap_or_default(), first, offset))
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Label")]
+pub struct LabelNode {
+    name: String,
+    color: String,
+    description: Option<String>,
+}
+
+impl From<Label> for LabelNode {
+    fn from(label: Label) -> Self {
+        LabelNode { name: label.name, color: label.color, description: label.description }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Comment")]
+pub struct CommentNode {
+    id: i64,
+    author: String,
+    body: String,
+    created_at: String,
+}
+
+impl From<Comment> for CommentNode {
+    fn from(comment: Comment) -> Self {
+        CommentNode { id: comment.id, author: comment.author, body: comment.body, created_at: comment.created_at }
+    }
+}
+
+// A user account, without its password hash.
+#[derive(SimpleObject)]
+#[graphql(name = "User")]
+pub struct UserNode {
+    id: i64,
+    username: String,
+    role: String,
+}
+
+impl From<User> for UserNode {
+    fn from(user: User) -> Self {
+        UserNode { id: user.id, username: user.username, role: user.role }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct BugPage {
+    nodes: Vec<BugNode>,
+    // Bugs matching the filter across all pages.
+    total_count: i64,
+    has_next_page: bool,
+}
+
+impl BugPage {
+    // One page of an already loaded list, for the nested `bugs` fields.
+    fn slice(bugs: Vec<Bug>, first: i64, offset: i64) -> Self {
+        let total_count = bugs.len() as i64;
+        let limit = first.clamp(0, MAX_PAGE_SIZE) as usize;
+        let offset = offset.max(0);
+        let nodes: Vec<BugNode> = bugs.into_iter().skip(offset as usize).take(limit).map(BugNode).collect();
+        BugPage { has_next_page: offset + (nodes.len() as i64) < total_count, nodes, total_count }
+    }
+}
+
+// A saved bug, with the capacity warning the REST API sends as the
+// X-Capacity-Warning header.
+#[derive(SimpleObject)]
+pub struct BugPayload {
+    bug: BugNode,
+    warning: Option<String>,
+}
+
+// The `GET /bugs` filters; label lists are names.
+#[derive(InputObject, Default)]
+pub struct BugFilterInput {
+    severity: Option<String>,
+    developer_id: Option<i64>,
+    unassigned: Option<bool>,
+    reported_by: Option<String>,
+    // Text in the title or description.
+    q: Option<String>,
+    status: Option<String>,
+    project_id: Option<i64>,
+    component: Option<String>,
+    labels_any: Option<Vec<String>>,
+    labels_all: Option<Vec<String>>,
+    labels_none: Option<Vec<String>>,
+}
+
+impl From<BugFilterInput> for BugFilter {
+    fn from(input: BugFilterInput) -> Self {
+        let names = |labels: Option<Vec<String>>| labels.map(|labels| labels.join(","));
+        BugFilter {
+            severity: input.severity,
+            developer_id: input.developer_id,
+            unassigned: input.unassigned,
+            reported_by: input.reported_by,
+            q: input.q,
+            status: input.status,
+            project_id: input.project_id,
+            component: input.component,
+            labels_any: names(input.labels_any),
+            labels_all: names(input.labels_all),
+            labels_none: names(input.labels_none),
+        }
+    }
+}
+
+#[derive(InputObject)]
+pub struct CreateBugInput {
+    title: String,
+    description: String,
+    reported_by: String,
+    severity: String,
+    project_id: Option<i64>,
+    component: Option<String>,
+}
+
+// Fields left out are kept; `null` clears the optional ones. Assignment
+// goes through `assignBug` and status through the REST workflow.
+#[derive(InputObject)]
+pub struct UpdateBugInput {
+    title: Option<String>,
+    description: MaybeUndefined<String>,
+    reported_by: MaybeUndefined<String>,
+    severity: Option<String>,
+    project_id: MaybeUndefined<i64>,
+    component: MaybeUndefined<String>,
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    // Newest first: `first` bugs (at most 200) after skipping `offset`.
+    #[graphql(complexity = "first.clamp(0, MAX_PAGE_SIZE) as usize * child_complexity")]
+    async fn bugs(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<BugFilterInput>,
+        #[graphql(default_with = "DEFAULT_PAGE_SIZE")] first: i64,
+        #[graphql(default = 0)] offset: i64,
+    ) -> async_graphql::Result<BugPage> {
+        let app_state = ctx.data_unchecked::<AppState>();
+        let filter = BugFilter::from(filter.unwrap_or_default());
+        let limit = first.clamp(0, MAX_PAGE_SIZE);
+        let offset = offset.max(0);
+        let (bugs, total_count) = database::get_bugs_page(&app_state.db, &filter, limit, offset)
+            .await
+            .map_err(|e| database_error("fetch bugs", e))?;
+        Ok(BugPage {
+            has_next_page: offset + (bugs.len() as i64) < total_count,
+            nodes: bugs.into_iter().map(BugNode).collect(),
+            total_count,
+        })
+    }
+
+    async fn bug(&self, ctx: &Context<'_>, id: i64) -> async_graphql::Result<Option<BugNode>> {
+        let app_state = ctx.data_unchecked::<AppState>();
+        let bug = database::get_bug_by_id(&app_state.db, id)
+            .await
+            .map_err(|e| database_error("fetch bug", e))?;
+        Ok(bug.map(BugNode))
+    }
+
+    // By name; `active` picks only active or only inactive developers.
+    async fn developers(&self, ctx: &Context<'_>, active: Option<bool>) -> async_graphql::Result<Vec<DeveloperNode>> {
+        let app_state = ctx.data_unchecked::<AppState>();
+        let developers = database::get_all_developers(&app_state.db)
+            .await
+            .map_err(|e| database_error("fetch developers", e))?;
+        Ok(developers.into_iter()
+            .filter(|d| active.is_none_or(|active| d.active == active))
+            .map(DeveloperNode)
+            .collect())
+    }
+
+    async fn developer(&self, ctx: &Context<'_>, id: i64) -> async_graphql::Result<Option<DeveloperNode>> {
+        let developer = ctx.data_unchecked::<DataLoader<DeveloperLoader, HashMapCache>>()
+            .load_one(id)
+            .await
+            .map_err(|e| database_error("load developers", e))?;
+        Ok(developer.map(DeveloperNode))
+    }
+
+    async fn projects(&self, ctx: &Context<'_>) -> Vec<ProjectNode> {
+        let app_state = ctx.data_unchecked::<AppState>();
+        app_state.projects.read().await.iter().cloned().map(ProjectNode).collect()
+    }
+
+    async fn project(&self, ctx: &Context<'_>, id: i64) -> Option<ProjectNode> {
+        ctx.data_unchecked::<AppState>().get_project(id).await.map(ProjectNode)
+    }
+
+    // Admin only.
+    async fn users(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<UserNode>> {
+        require_admin(ctx)?;
+        let app_state = ctx.data_unchecked::<AppState>();
+        let users = database::get_users(&app_state.db)
+            .await
+            .map_err(|e| database_error("fetch users", e))?;
+        Ok(users.into_iter().map(UserNode::from).collect())
+    }
+
+    // The token's user.
+    async fn me(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<UserNode>> {
+        let claims = require_user(ctx)?;
+        let app_state = ctx.data_unchecked::<AppState>();
+        let user = database::get_user_by_username(&app_state.db, &claims.sub)
+            .await
+            .map_err(|e| database_error("fetch user", e))?;
+        Ok(user.map(UserNode::from))
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    // As `POST /bugs/new`, including auto-assignment.
+    async fn create_bug(&self, ctx: &Context<'_>, input: CreateBugInput) -> async_graphql::Result<BugPayload> {
+        let app_state = ctx.data_unchecked::<AppState>();
+        let new_bug = NewBug {
+            title: input.title,
+            description: input.description,
+            reported_by: input.reported_by,
+            severity: input.severity,
+            project_id: input.project_id,
+            component: input.component,
+        };
+        let created = bugs::create(app_state, &new_bug).await.map_err(|e| bug_error("create bug", e))?;
+        let warning = created.assigned.and_then(|assigned| assigned.warning);
+        Ok(BugPayload { bug: BugNode(created.bug), warning })
+    }
+
+    // As `PATCH /bugs/{id}`, for the fields given.
+    async fn update_bug(&self, ctx: &Context<'_>, id: i64, input: UpdateBugInput) -> async_graphql::Result<BugPayload> {
+        let app_state = ctx.data_unchecked::<AppState>();
+        let Some(mut bug) = database::get_bug_by_id(&app_state.db, id)
+            .await
+            .map_err(|e| database_error("fetch bug", e))?
+        else {
+            return Err(error("NOT_FOUND", "Bug not found"));
+        };
+
+        if let Some(title) = input.title {
+            bug.title = title;
+        }
+        if let Some(severity) = input.severity {
+            bug.severity = Some(severity);
+        }
+        input.description.update_to(&mut bug.description);
+        input.reported_by.update_to(&mut bug.reported_by);
+        input.project_id.update_to(&mut bug.project_id);
+        input.component.update_to(&mut bug.component);
+
+        let updated = bugs::update(app_state, id, &bug).await.map_err(|e| bug_error("update bug", e))?;
+        Ok(BugPayload { bug: BugNode(updated.bug), warning: updated.warning })
+    }
+
+    // As `POST /bugs/assign`, with the same capacity policy.
+    async fn assign_bug(&self, ctx: &Context<'_>, bug_id: i64, developer_id: i64) -> async_graphql::Result<BugPayload> {
+        let app_state = ctx.data_unchecked::<AppState>();
+        let policy = app_state.config.capacity_policy;
+        let warning = match database::assign_bug(&app_state.db, bug_id, developer_id, policy).await {
+            Ok(Ok(warning)) => warning,
+            Ok(Err(assignment_error)) => {
+                return Err(assignment_failed(&assignment_error));
+            }
+            Err(e) => return Err(database_error("assign bug", e)),
+        };
+        println!("✅ Bug #{} assigned to developer #{} via GraphQL", bug_id, developer_id);
+
+        let bug = database::get_bug_by_id(&app_state.db, bug_id)
+            .await
+            .map_err(|e| database_error("fetch bug", e))?
+            .ok_or_else(|| error("NOT_FOUND", "Bug not found"))?;
+        events::bug_event(app_state, EventKind::BugAssigned, &bug).await;
+        Ok(BugPayload { bug: BugNode(bug), warning })
+    }
+}
+
+fn assignment_failed(assignment_error: &AssignmentError) -> Error {
+    let code = match assignment_error {
+        AssignmentError::BugNotFound(_) => "NOT_FOUND",
+        AssignmentError::DeveloperNotFound(_) | AssignmentError::DeveloperInactive(_) => "BAD_USER_INPUT",
+        AssignmentError::OverCapacity { .. } => "CONFLICT",
+    };
+    error(code, assignment_error.message())
+}
+
+fn bug_error(action: &str, e: BugError) -> Error {
+    match e {
+        BugError::Invalid(field_error) => invalid(field_error.field, &field_error.message),
+        BugError::NotFound => error("NOT_FOUND", "Bug not found"),
+        BugError::Assignment(assignment_error) => assignment_failed(&assignment_error),
+        BugError::Database(e) => database_error(action, e),
+    }
+}
+
+fn invalid(field: &'static str, message: &str) -> Error {
+    Error::new(message).extend_with(|_, extensions| {
+        extensions.set("code", "BAD_USER_INPUT");
+        extensions.set("field", field);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use serde_json::{json, Value};
+
+    async fn app_state() -> AppState {
+        let db = database::test_pool().await;
+        for (username, role) in [("admin", "admin"), ("dana", "user")] {
+            sqlx::query("INSERT INTO users (username, password_hash, role) VALUES (?, '-', ?)")
+                .bind(username)
+                .bind(role)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+        AppState::new(db, Config::from_env())
+    }
+
+    fn as_user(username: &str, role: &str) -> Viewer {
+        Viewer(Ok(Claims { sub: username.to_string(), role: role.to_string(), exp: usize::MAX }))
+    }
+
+    // The response data, or the `code` of the first error.
+    async fn run(app_state: &AppState, viewer: Viewer, query: &str) -> Result<Value, String> {
+        let request = prepare(async_graphql::Request::new(query), app_state, viewer);
+        let response = schema(app_state.clone()).execute(request).await;
+        match response.errors.first() {
+            Some(error) => match error.extensions.as_ref().and_then(|extensions| extensions.get("code")) {
+                Some(async_graphql::Value::String(code)) => Err(code.clone()),
+                _ => Err(error.message.clone()),
+            },
+            None => Ok(response.data.into_json().unwrap()),
+        }
+    }
+
+    fn bug(id: i64) -> Bug {
+        Bug {
+            id: Some(id),
+            title: format!("Bug {}", id),
+            description: None,
+            reported_by: None,
+            severity: None,
+            developer_id: None,
+            status: "Open".to_string(),
+            project_id: None,
+            component: None,
+            labels: vec![],
+            deleted_at: None,
+            deleted_by: None,
+            external_ref: None,
+        }
+    }
+
+    fn page_ids(page: &BugPage) -> Vec<i64> {
+        page.nodes.iter().filter_map(|node| node.0.id).collect()
+    }
+
+    #[tokio::test]
+    async fn users_needs_the_admin_role() {
+        let app_state = app_state().await;
+        let query = "{ users { username role } }";
+
+        assert_eq!(run(&app_state, Viewer(Err("Missing Authorization header")), query).await, Err("UNAUTHENTICATED".to_string()));
+        assert_eq!(run(&app_state, as_user("dana", "user"), query).await, Err("FORBIDDEN".to_string()));
+        let data = run(&app_state, as_user("admin", "admin"), query).await.unwrap();
+        assert_eq!(data["users"], json!([{ "username": "admin", "role": "admin" }, { "username": "dana", "role": "user" }]));
+    }
+
+    #[tokio::test]
+    async fn me_needs_a_token() {
+        let app_state = app_state().await;
+        let query = "{ me { username role } }";
+
+        assert_eq!(run(&app_state, Viewer(Err("Token has expired")), query).await, Err("UNAUTHENTICATED".to_string()));
+        let data = run(&app_state, as_user("dana", "user"), query).await.unwrap();
+        assert_eq!(data["me"], json!({ "username": "dana", "role": "user" }));
+    }
+
+    #[test]
+    fn pages_are_sliced_with_clamped_bounds() {
+        let bugs = || (1..=5).map(bug).collect::<Vec<_>>();
+
+        let page = BugPage::slice(bugs(), 2, 2);
+        assert_eq!((page_ids(&page), page.total_count, page.has_next_page), (vec![3, 4], 5, true));
+        let page = BugPage::slice(bugs(), 2, 4);
+        assert_eq!((page_ids(&page), page.has_next_page), (vec![5], false));
+        let page = BugPage::slice(bugs(), -1, -3);
+        assert_eq!((page_ids(&page), page.has_next_page), (vec![], true));
+        let page = BugPage::slice(bugs(), MAX_PAGE_SIZE + 1, 0);
+        assert_eq!((page_ids(&page), page.has_next_page), (vec![1, 2, 3, 4, 5], false));
+    }
+
+    #[tokio::test]
+    async fn bugs_are_paged_newest_first() {
+        let app_state = app_state().await;
+        for n in 1..=3 {
+            let mutation = format!(
+                r#"mutation {{ createBug(input: {{title: "Bug {}", description: "-", reportedBy: "dana", severity: "Low"}}) {{ bug {{ id }} }} }}"#,
+                n
+            );
+            run(&app_state, as_user("dana", "user"), &mutation).await.unwrap();
+        }
+
+        let data = run(&app_state, as_user("dana", "user"), "{ bugs(first: 2, offset: 1) { totalCount hasNextPage nodes { id } } }")
+            .await
+            .unwrap();
+        assert_eq!(data["bugs"], json!({ "totalCount": 3, "hasNextPage": false, "nodes": [{ "id": 2 }, { "id": 1 }] }));
+    }
+
+    #[tokio::test]
+    async fn update_bug_keeps_left_out_fields_and_clears_nulls() {
+        let app_state = app_state().await;
+        let viewer = || as_user("dana", "user");
+        run(&app_state, viewer(), r#"mutation { createBug(input: {title: "Crash", description: "On save", reportedBy: "dana", severity: "High", projectId: 1, component: "auth"}) { bug { id } } }"#)
+            .await
+            .unwrap();
+        let fields = "bug { title description reportedBy severity projectId component }";
+
+        let data = run(&app_state, viewer(), &format!(r#"mutation {{ updateBug(id: 1, input: {{title: "Crash on save"}}) {{ {} }} }}"#, fields))
+            .await
+            .unwrap();
+        assert_eq!(data["updateBug"]["bug"], json!({
+            "title": "Crash on save", "description": "On save", "reportedBy": "dana",
+            "severity": "High", "projectId": 1, "component": "auth",
+        }));
+
+        let data = run(&app_state, viewer(), &format!(r#"mutation {{ updateBug(id: 1, input: {{description: null, component: null}}) {{ {} }} }}"#, fields))
+            .await
+            .unwrap();
+        assert_eq!(data["updateBug"]["bug"], json!({
+            "title": "Crash on save", "description": null, "reportedBy": "dana",
+            "severity": "High", "projectId": 1, "component": null,
+        }));
+    }
+}
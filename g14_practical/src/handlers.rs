@@ -1,173 +1,35 @@
-use actix_web::{web, HttpResponse, Result, HttpRequest};
+use actix_web::{web, http::StatusCode, HttpResponse, Result, HttpRequest};
+use actix_web::http::header::{
+    Charset, ContentDisposition, DispositionParam, DispositionType, ExtendedValue, HeaderName,
+    HeaderValue,
+};
+use actix_multipart::Multipart;
 use crate::models::*;
-use sqlx::Row;  
 use crate::state::AppState;
 use crate::database;
 use crate::auth;
+use crate::board;
+use crate::bugs::{self, BugError, CreatedBug, TransitionOutcome, UpdatedBug};
+use crate::attachments::{self, UploadError};
+use crate::graph;
+use crate::export::{self, ExportFormat};
+use crate::import::{self, ImportFormat};
+use crate::inbound::{self, InboundError, InboundOutcome};
+use crate::git;
+use crate::graphql::{self, BugSchema};
+use crate::assignment;
+use crate::events::{self, EventKind};
+use crate::notifications;
+use crate::presence;
+use crate::sse;
+use crate::templates::{
+    self, AssignResultTemplate, BoardTemplate, BugAssignFormTemplate, BugDetailTemplate,
+    BugFormTemplate, BugListTemplate, HomepageTemplate,
+};
 
 // Homepage
 pub async fn homepage() -> Result<HttpResponse> {
-    let html = r#"
-<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>Bug Tracker System</title>
-    <style>
-        body { 
-            font-family: Arial, sans-serif; 
-            max-width: 800px; 
-            margin: 50px auto; 
-            padding: 20px; 
-            background-color: #f8f9fa;
-        }
-        .header { text-align: center; margin-bottom: 40px; }
-        .section { background: white; padding: 20px; margin: 20px 0; border-radius: 8px; box-shadow: 0 2px 4px rgba(0,0,0,0.1); }
-        .endpoint { margin: 10px 0; padding: 8px; background: #e9ecef; border-radius: 4px; }
-        .method { font-weight: bold; color: #007bff; }
-        a { color: #007bff; text-decoration: none; }
-        a:hover { text-decoration: underline; }
-        .nav-links { text-align: center; margin: 20px 0; }
-        .nav-links a { 
-            display: inline-block; 
-            margin: 10px; 
-            padding: 10px 20px; 
-            background: #007bff; 
-            color: white; 
-            border-radius: 4px; 
-            text-decoration: none;
-        }
-        .nav-links a:hover { background: #0056b3; }
-    </style>
-</head>
-<body>
-    <div class="header">
-        <h1>🐛 Bug Tracker System</h1>
-        <p>CSC1106 Web Programming - Group 14</p>
-    </div>
-
-    <div class="nav-links">
-        <a href="/bugs/assign">🔧 Assign Bugs</a>
-        <a href="/health">❤️ Health Check</a>
-    </div>
-
-    <div class="section">
-        <h2>📚 API Endpoints</h2>
-        
-        <h3>🐛 Bug Management</h3>
-        <div class="endpoint"><span class="method">POST</span> /bugs/new - Create new bug</div>
-        <div class="endpoint"><span class="method">GET</span> /bugs - List all bugs</div>
-        <div class="endpoint"><span class="method">GET</span> /bugs/:id - Get specific bug</div>
-        <div class="endpoint"><span class="method">PATCH</span> /bugs/:id - Update bug</div>
-        <div class="endpoint"><span class="method">DELETE</span> /bugs/:id - Delete bug</div>
-        
-        <h3>👥 Developer Management</h3>
-        <div class="endpoint"><span class="method">GET</span> /developers - List developers</div>
-        <div class="endpoint"><span class="method">POST</span> /developers - Add developer</div>
-        
-        <h3>📁 Project Management</h3>
-        <div class="endpoint"><span class="method">GET</span> /projects - List projects</div>
-        <div class="endpoint"><span class="method">POST</span> /projects - Add project</div>
-        
-        <h3>🔐 Authentication</h3>
-        <div class="endpoint"><span class="method">POST</span> /login - User login</div>
-    </div>
-
-    <div class="section">
-        <h2>🧪 Quick Test Commands</h2>
-        <h3>Create a Bug:</h3>
-        <pre style="background: #f1f3f4; padding: 10px; border-radius: 4px; overflow-x: auto;">
-curl -X POST http://localhost:8080/bugs/new \
-  -H "Content-Type: application/json" \
-  -d '{
-    "title": "Sample Bug",
-    "description": "This is a test bug",
-    "reported_by": "test@example.com",
-    "severity": "Medium"
-  }'</pre>
-
-        <h3>Login:</h3>
-        <pre style="background: #f1f3f4; padding: 10px; border-radius: 4px; overflow-x: auto;">
-curl -X POST http://localhost:8080/login \
-  -H "Content-Type: application/json" \
-  -d '{"username": "admin", "password": "admin123"}'</pre>
-
-        <h3>Bugs Testing:</h3>
-</pre>
-        <h4>Get All Bugs</h4>
-        <pre style="background: #f1f3f4; padding: 10px; border-radius: 4px; overflow-x: auto;">
-curl http://localhost:8080/bugs</pre>
-
-        <h4>Update Bug</h4>
-        <pre style="background: #f1f3f4; padding: 10px; border-radius: 4px; overflow-x: auto;">
-curl -X PATCH http://localhost:8080/bugs/1 -H "Content-Type: application/json" -d "{\"id\":1,\"title\":\"Updated Bug\",\"description\":\"Updated description\",\"reported_by\":\"test1@test.com\",\"severity\":\"Critical\",\"developer_id\":1}"</pre>
-        
-        <h4>Delete Bugs</h4>
-        <pre style="background: #f1f3f4; padding: 10px; border-radius: 4px; overflow-x: auto;">
-curl -X DELETE http://localhost:8080/bugs/2</pre>
-
-        <h3>Validation Testing</h3>
-        <h4>Empty Project Name (should fail):</h4>
-        <pre style="background: #f1f3f4; padding: 10px; border-radius: 4px; overflow-x: auto;">
-curl -X POST http://localhost:8080/projects -H "Content-Type: application/json" -d "{\"name\":\"\",\"description\":\"Test project\"}" </pre>
-       
-        <h3>Get Project List:</h3>
-        <pre style="background: #f1f3f4; padding: 10px; border-radius: 4px; overflow-x: auto;"> 
-curl http://localhost:8080/projects</pre>
-
-        <h3>Add Project:</h3>
-        <pre style="background: #f1f3f4; padding: 10px; border-radius: 4px; overflow-x: auto;">
-curl -X POST http://localhost:8080/projects -H "Content-Type: application/json" -d "{\"name\":\"DevOps Pipeline\",\"description\":\"CI/CD and deployment automation\"}"</pre>
-
-    <h3>Thread-Safety Testing:</h3>
-        <pre style="background: #f1f3f4; padding: 10px; border-radius: 4px; overflow-x: auto;">
-curl -X POST http://localhost:8080/projects -H "Content-Type: application/json" -d "{\"name\":\"Project Alpha\",\"description\":\"Alpha testing project\"}" &
-curl -X POST http://localhost:8080/projects -H "Content-Type: application/json" -d "{\"name\":\"Project Beta\",\"description\":\"Beta testing project\"}" &
-curl -X POST http://localhost:8080/projects -H "Content-Type: application/json" -d "{\"name\":\"Project Gamma\",\"description\":\"Gamma testing project\"}" & </pre> 
-    
-     <h3>Fix Admin Password:</h3>
-        <pre style="background: #f1f3f4; padding: 10px; border-radius: 4px; overflow-x: auto;">
-curl -X POST http://localhost:8080/fix-admin</pre>
-
-    <h3>Failed Login Attempts:</h3>
-    <h4>Wrong Password</h4>
-        <pre style="background: #f1f3f4; padding: 10px; border-radius: 4px; overflow-x: auto;">
-curl -X POST http://localhost:8080/login -H "Content-Type: application/json" -d "{\"username\":\"admin\",\"password\":\"wrongpassword\"}"</pre>
-    <h4>Wrong Username</h4>
-        <pre style="background: #f1f3f4; padding: 10px; border-radius: 4px; overflow-x: auto;">
-curl -X POST http://localhost:8080/login -H "Content-Type: application/json" -d "{\"username\":\"wronguser\",\"password\":\"admin123\"}"</pre>
-    </div>
-    
-
-    <div class="section">
-        <h2>👤 Default Users</h2>
-        <ul>
-            <li><strong>Admin:</strong> username: admin, password: admin123</li>
-            <li><strong>Developer 1:</strong> ID: 1 - John Developer</li>
-            <li><strong>Developer 2:</strong> ID: 2 - Jane Smith</li>
-            <li><strong>Developer 3:</strong> ID: 3 - Bob Wilson</li>
-        </ul>
-    </div>
-
-    <div class="section">
-        <h2>🚀 Features Implemented</h2>
-        <ul>
-            <li>✅ Full CRUD operations for bugs</li>
-            <li>✅ Bug assignment with HTML forms</li>
-            <li>✅ Project state management</li>
-            <li>✅ User authentication with bcrypt + JWT</li>
-            <li>✅ Developer management system</li>
-            <li>✅ Dynamic HTML templates</li>
-            <li>✅ Comprehensive error handling</li>
-            <li>✅ SQLite database integration</li>
-        </ul>
-    </div>
-</body>
-</html>
-    "#;
-    
-    Ok(HttpResponse::Ok().content_type("text/html").body(html))
+    Ok(templates::render(StatusCode::OK, &HomepageTemplate))
 }
 
 // Health check
@@ -184,21 +46,25 @@ pub async fn create_bug(
     app_state: web::Data<AppState>,
     bug: web::Json<NewBug>,
 ) -> Result<HttpResponse> {
-    if bug.title.trim().is_empty() {
-        return Ok(HttpResponse::BadRequest().json(
-            ApiResponse::<()>::error("Title is required")
-        ));
-    }
-
-    match database::create_bug(&app_state.db, &bug).await {
-        Ok(created_bug) => {
-            println!("✅ Created bug #{:?}: {}", created_bug.id, created_bug.title);
-            Ok(HttpResponse::Created().json(
-                ApiResponse::success_with_id(created_bug.clone(), "Bug created successfully", created_bug.id.unwrap_or(0))
+    match bugs::create(&app_state, &bug).await {
+        Ok(CreatedBug { bug: created_bug, assigned }) => {
+            let mut message = "Bug created successfully".to_string();
+            let mut response = HttpResponse::Created();
+            if let Some(assigned) = assigned {
+                message = format!("Bug created and assigned to developer #{} ({})", assigned.developer_id, assigned.reason);
+                if let Some(warning) = assigned.warning {
+                    response.insert_header((CAPACITY_WARNING_HEADER, warning));
+                }
+            }
+            Ok(response.json(
+                ApiResponse::success_with_id(created_bug.clone(), &message, created_bug.id.unwrap_or(0))
             ))
         }
+        Err(BugError::Invalid(error)) => Ok(HttpResponse::BadRequest().json(
+            ApiResponse::<()>::error(&error.message)
+        )),
         Err(e) => {
-            eprintln!("❌ Failed to create bug: {}", e);
+            eprintln!("❌ Failed to create bug: {:?}", e);
             Ok(HttpResponse::InternalServerError().json(
                 ApiResponse::<()>::error("Failed to create bug")
             ))
@@ -206,8 +72,11 @@ pub async fn create_bug(
     }
 }
 
-pub async fn get_bugs(app_state: web::Data<AppState>) -> Result<HttpResponse> {
-    match database::get_all_bugs(&app_state.db).await {
+pub async fn get_bugs(
+    app_state: web::Data<AppState>,
+    filter: web::Query<BugFilter>,
+) -> Result<HttpResponse> {
+    match database::get_bugs(&app_state.db, &filter).await {
         Ok(bugs) => {
             println!("📋 Retrieved {} bugs", bugs.len());
             Ok(HttpResponse::Ok().json(bugs))
@@ -221,6 +90,358 @@ pub async fn get_bugs(app_state: web::Data<AppState>) -> Result<HttpResponse> {
     }
 }
 
+// Streams the filtered bugs as a download; `format` is csv (default), json
+// or ndjson, and `columns` picks and orders the fields.
+pub async fn export_bugs(
+    app_state: web::Data<AppState>,
+    query: web::Query<ExportQuery>,
+) -> Result<HttpResponse> {
+    let query = query.into_inner();
+    let Some(format) = ExportFormat::parse(query.format.as_deref()) else {
+        return Ok(HttpResponse::BadRequest().json("Unknown format (use csv, json or ndjson)"));
+    };
+    let columns = match export::parse_columns(query.columns.as_deref()) {
+        Ok(columns) => columns,
+        Err(unknown) => {
+            return Ok(HttpResponse::BadRequest().json(format!(
+                "Unknown column(s): {}; available: {}", unknown.join(", "), export::COLUMNS.join(", ")
+            )));
+        }
+    };
+    let project_names = app_state.projects.read().await
+        .iter()
+        .map(|p| (p.id, p.name.clone()))
+        .collect();
+
+    println!("📤 Exporting bugs as {}", format.extension());
+    let body = export::stream(app_state.db.clone(), query.filter, format, columns, project_names);
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!("bugs.{}", format.extension()))],
+        })
+        .streaming(body))
+}
+
+// Opens the bug's presence WebSocket (see `presence.rs`). Any signed-in
+// user may join, with a ticket or an Authorization header; the username is
+// what others see.
+pub async fn bug_presence(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    query: web::Query<PresenceQuery>,
+    req: HttpRequest,
+    body: web::Payload,
+) -> Result<HttpResponse> {
+    let bug_id = path.into_inner();
+    let username = match &query.ticket {
+        Some(ticket) => app_state.presence.redeem_ticket(bug_id, ticket).await.ok_or("Invalid or expired ticket"),
+        None => auth::claims_from_request(&req).map(|claims| claims.sub),
+    };
+    let username = match username {
+        Ok(username) => username,
+        Err(message) => return Ok(HttpResponse::Unauthorized().json(message)),
+    };
+    if let Err(response) = presence_bug_exists(&app_state, bug_id).await {
+        return Ok(response);
+    }
+
+    let (response, session, messages) = actix_ws::handle(&req, body)?;
+    presence::spawn_session(app_state.get_ref().clone(), bug_id, username, session, messages);
+    Ok(response)
+}
+
+// Trades the Authorization header for a short-lived, single-use ticket to
+// put in the presence WebSocket's URL.
+pub async fn bug_presence_ticket(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let bug_id = path.into_inner();
+    let claims = match auth::claims_from_request(&req) {
+        Ok(claims) => claims,
+        Err(message) => return Ok(HttpResponse::Unauthorized().json(message)),
+    };
+    if let Err(response) = presence_bug_exists(&app_state, bug_id).await {
+        return Ok(response);
+    }
+
+    let ticket = app_state.presence.issue_ticket(bug_id, &claims.sub).await;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "ticket": ticket,
+        "expires_in": presence::TICKET_TTL.as_secs(),
+    })))
+}
+
+async fn presence_bug_exists(app_state: &AppState, bug_id: i64) -> std::result::Result<(), HttpResponse> {
+    match database::get_bug_by_id(&app_state.db, bug_id).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(HttpResponse::NotFound().json("Bug not found")),
+        Err(e) => {
+            eprintln!("❌ Failed to load bug #{} for presence: {}", bug_id, e);
+            Err(HttpResponse::InternalServerError().json("Failed to open presence"))
+        }
+    }
+}
+
+// Runs a GraphQL query or mutation (see `graphql.rs`). A missing or bad
+// token only fails the fields that need one, so the response is always
+// 200 with any errors listed in it.
+pub async fn graphql(
+    app_state: web::Data<AppState>,
+    schema: web::Data<BugSchema>,
+    request: web::Json<async_graphql::Request>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let viewer = graphql::Viewer(auth::claims_from_request(&req));
+    let response = schema.execute(graphql::prepare(request.into_inner(), &app_state, viewer)).await;
+    if response.is_err() {
+        let messages: Vec<&str> = response.errors.iter().map(|e| e.message.as_str()).collect();
+        println!("⚠️  GraphQL request failed: {}", messages.join("; "));
+    }
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// GraphiQL, for exploring the schema in a browser.
+pub async fn graphiql() -> Result<HttpResponse> {
+    let page = async_graphql::http::GraphiQLSource::build().endpoint("/graphql").finish();
+    Ok(HttpResponse::Ok().content_type("text/html; charset=utf-8").body(page))
+}
+
+// Live bug changes as Server-Sent Events, optionally for one project.
+// A `Last-Event-ID` header resumes after that event.
+pub async fn event_stream(
+    app_state: web::Data<AppState>,
+    query: web::Query<EventStreamQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let project_id = query.project_id;
+    if let Some(project_id) = project_id
+        && !app_state.has_project(project_id).await
+    {
+        return Ok(HttpResponse::BadRequest().json(format!("Project #{} does not exist", project_id)));
+    }
+    let last_event_id = match req.headers().get("Last-Event-ID") {
+        None => None,
+        Some(value) => match value.to_str().ok().and_then(|v| v.trim().parse::<i64>().ok()) {
+            Some(id) => Some(id),
+            None => return Ok(HttpResponse::BadRequest().json("Last-Event-ID must be an event id")),
+        },
+    };
+
+    match sse::stream(app_state.get_ref().clone(), project_id, last_event_id).await {
+        Ok(body) => {
+            match last_event_id {
+                Some(id) => println!("📡 Event stream resumed after #{}", id),
+                None => println!("📡 Event stream opened"),
+            }
+            Ok(HttpResponse::Ok()
+                .content_type("text/event-stream")
+                .insert_header(("Cache-Control", "no-cache"))
+                // Stops nginx from buffering the stream.
+                .insert_header(("X-Accel-Buffering", "no"))
+                .streaming(body))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to open event stream: {}", e);
+            Ok(HttpResponse::InternalServerError().json("Failed to open event stream"))
+        }
+    }
+}
+
+// Validates every row against the `POST /bugs/new` rules and, unless
+// `dry_run` is set, inserts the valid ones in batches. Invalid rows are
+// reported and skipped; imported bugs aren't auto-assigned. A batch that
+// fails to save is reported as failed and the rest still go ahead; the
+// answer is then a 500 with the full report, since earlier batches stay
+// imported.
+pub async fn import_bugs(
+    app_state: web::Data<AppState>,
+    query: web::Query<ImportQuery>,
+    req: HttpRequest,
+    body: web::Bytes,
+) -> Result<HttpResponse> {
+    let content_type = req.headers()
+        .get(actix_web::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let Some(format) = ImportFormat::parse(query.format.as_deref(), content_type) else {
+        return Ok(HttpResponse::BadRequest().json("Unknown format (use csv or ndjson)"));
+    };
+    let mapping = match import::parse_mapping(query.mapping.as_deref()) {
+        Ok(mapping) => mapping,
+        Err(message) => return Ok(HttpResponse::BadRequest().json(message)),
+    };
+    let parsed = match import::parse_rows(format, &body, &mapping) {
+        Ok(parsed) => parsed,
+        Err(message) => return Ok(HttpResponse::BadRequest().json(message)),
+    };
+    let dry_run = query.dry_run.unwrap_or(false);
+
+    let projects = app_state.projects.read().await.clone();
+    let mut rows = Vec::with_capacity(parsed.len());
+    let mut valid = Vec::new();
+    for (i, parsed) in parsed.into_iter().enumerate() {
+        let errors = match &parsed {
+            Ok(bug) => bug.validate(&projects).into_iter().map(|e| e.message).collect(),
+            Err(errors) => errors.clone(),
+        };
+        let status = if errors.is_empty() { "valid" } else { "invalid" };
+        if let (Ok(bug), true) = (parsed, errors.is_empty()) {
+            valid.push((i, bug));
+        }
+        rows.push(ImportRowReport { row: i + 1, status, bug_id: None, errors });
+    }
+
+    let mut imported = 0;
+    let mut failed = 0;
+    if !dry_run {
+        for batch in valid.chunks(import::BATCH_SIZE) {
+            let bugs: Vec<NewBug> = batch.iter().map(|(_, bug)| bug.clone()).collect();
+            match database::import_bugs(&app_state.db, &bugs).await {
+                Ok(ids) => {
+                    for ((i, _), id) in batch.iter().zip(ids) {
+                        rows[*i].status = "imported";
+                        rows[*i].bug_id = Some(id);
+                        events::bug_event_by_id(&app_state, EventKind::BugCreated, id).await;
+                    }
+                    imported += batch.len();
+                }
+                Err(e) => {
+                    eprintln!("❌ Failed to import a batch of {} bug(s): {}", batch.len(), e);
+                    for (i, _) in batch {
+                        rows[*i].status = "failed";
+                        rows[*i].errors.push("Failed to save the bug".to_string());
+                    }
+                    failed += batch.len();
+                }
+            }
+        }
+    }
+
+    let report = ImportReport {
+        dry_run,
+        total: rows.len(),
+        valid: valid.len(),
+        invalid: rows.len() - valid.len(),
+        imported,
+        failed,
+        rows,
+    };
+    println!(
+        "📥 Import{}: {} row(s), {} valid, {} invalid, {} imported, {} failed",
+        if dry_run { " (dry run)" } else { "" }, report.total, report.valid, report.invalid, report.imported,
+        report.failed
+    );
+    if failed > 0 {
+        return Ok(HttpResponse::InternalServerError().json(report));
+    }
+    Ok(HttpResponse::Ok().json(report))
+}
+
+// Git integration
+pub async fn git_push(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Bytes,
+) -> Result<HttpResponse> {
+    let Some(secret) = &app_state.config.git_webhook_secret else {
+        return Ok(HttpResponse::ServiceUnavailable().json("Git integration is off: GIT_WEBHOOK_SECRET is not set"));
+    };
+    let Some(provider) = git::Provider::detect(req.headers()) else {
+        return Ok(HttpResponse::BadRequest().json("Not a GitHub, Gitea or GitLab webhook"));
+    };
+    if !provider.verify(req.headers(), &body, secret) {
+        println!("🚫 Rejected {} webhook with a bad signature", provider.name());
+        return Ok(HttpResponse::Unauthorized().json("Invalid webhook signature"));
+    }
+    if !provider.is_push(req.headers()) {
+        return Ok(HttpResponse::Ok().json("Ignored: not a push event"));
+    }
+
+    match git::parse_push(&body) {
+        Ok(push) => git_push_response(&app_state, &push).await,
+        Err(message) => Ok(HttpResponse::BadRequest().json(message)),
+    }
+}
+
+pub async fn git_commits(
+    app_state: web::Data<AppState>,
+    body: web::Json<git::GenericPush>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req) {
+        return Ok(response);
+    }
+    let push = git::GitPush::from(body.into_inner());
+    git_push_response(&app_state, &push).await
+}
+
+async fn git_push_response(app_state: &AppState, push: &git::GitPush) -> Result<HttpResponse> {
+    match git::apply(app_state, push).await {
+        Ok(report) => {
+            println!(
+                "🔧 {} commit(s) pushed to {}: {} bug reference(s)",
+                report.commits, report.repository, report.references.len()
+            );
+            Ok(HttpResponse::Ok().json(report))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to apply commits from {}: {}", push.repository, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to apply commits"))
+        }
+    }
+}
+
+const INBOUND_TOKEN_HEADER: &str = "x-inbound-token";
+
+// A raw RFC 822 message, e.g. from an MTA pipe: opens a bug, or comments on
+// the bug named by a `[Bug #N]` token in the subject. A message seen before
+// answers 200 without saving anything.
+pub async fn receive_email(
+    app_state: web::Data<AppState>,
+    query: web::Query<InboundEmailQuery>,
+    req: HttpRequest,
+    body: web::Bytes,
+) -> Result<HttpResponse> {
+    let Some(token) = &app_state.config.inbound_email_token else {
+        return Ok(HttpResponse::ServiceUnavailable().json("Inbound email is off: INBOUND_EMAIL_TOKEN is not set"));
+    };
+    let given = req.headers().get(INBOUND_TOKEN_HEADER).map(|v| v.as_bytes()).unwrap_or_default();
+    if !auth::constant_time_eq(given, token.as_bytes()) {
+        return Ok(HttpResponse::Unauthorized().json("Missing or wrong X-Inbound-Token"));
+    }
+    if let Some(project_id) = query.project_id
+        && !app_state.has_project(project_id).await
+    {
+        return Ok(HttpResponse::BadRequest().json(format!("Project #{} does not exist", project_id)));
+    }
+
+    match inbound::receive(&app_state, &body, query.project_id).await {
+        Ok(outcome @ InboundOutcome::Duplicate { .. }) => Ok(HttpResponse::Ok().json(outcome)),
+        Ok(outcome) => Ok(HttpResponse::Created().json(outcome)),
+        Err(error) => {
+            let status = match &error {
+                InboundError::Unreadable(_) => StatusCode::BAD_REQUEST,
+                InboundError::UnknownSender(_) => StatusCode::FORBIDDEN,
+                InboundError::Empty => StatusCode::UNPROCESSABLE_ENTITY,
+                InboundError::Upload(UploadError::TooLarge { .. }) => StatusCode::PAYLOAD_TOO_LARGE,
+                InboundError::Upload(e) => {
+                    eprintln!("❌ Failed to store email attachments: {:?}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+                InboundError::Database(e) => {
+                    eprintln!("❌ Failed to save inbound email: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+            };
+            Ok(HttpResponse::build(status).json(error.message()))
+        }
+    }
+}
+
 pub async fn get_bug(
     app_state: web::Data<AppState>,
     path: web::Path<i64>,
@@ -229,8 +450,15 @@ pub async fn get_bug(
     
     match database::get_bug_by_id(&app_state.db, bug_id).await {
         Ok(Some(bug)) => {
+            let links = match database::get_bug_links(&app_state.db, bug_id).await {
+                Ok(links) => links,
+                Err(e) => {
+                    eprintln!("❌ Failed to fetch links for bug #{}: {}", bug_id, e);
+                    return Ok(HttpResponse::InternalServerError().json("Database error"));
+                }
+            };
             println!("🔍 Retrieved bug #{}", bug_id);
-            Ok(HttpResponse::Ok().json(bug))
+            Ok(HttpResponse::Ok().json(BugDetail { bug, links }))
         }
         Ok(None) => {
             println!("⚠️  Bug #{} not found", bug_id);
@@ -249,20 +477,21 @@ pub async fn update_bug(
     bug: web::Json<Bug>,
 ) -> Result<HttpResponse> {
     let bug_id = path.into_inner();
-    
-    if bug.title.trim().is_empty() {
-        return Ok(HttpResponse::BadRequest().json("Title is required"));
-    }
-    
-    match database::update_bug(&app_state.db, bug_id, &bug).await {
-        Ok(updated_bug) => {
-            println!("✏️  Updated bug #{}", bug_id);
-            Ok(HttpResponse::Ok().json(updated_bug))
+
+    match bugs::update(&app_state, bug_id, &bug).await {
+        Ok(UpdatedBug { bug: updated_bug, warning }) => {
+            let mut response = HttpResponse::Ok();
+            if let Some(warning) = warning {
+                response.insert_header((CAPACITY_WARNING_HEADER, warning));
+            }
+            Ok(response.json(updated_bug))
         }
-        Err(sqlx::Error::RowNotFound) => {
-            Ok(HttpResponse::NotFound().json("Bug not found"))
+        Err(BugError::Invalid(error)) => Ok(HttpResponse::BadRequest().json(&error.message)),
+        Err(BugError::NotFound) => Ok(HttpResponse::NotFound().json("Bug not found")),
+        Err(BugError::Assignment(error)) => {
+            Ok(HttpResponse::build(assignment_error_status(&error)).json(error.message()))
         }
-        Err(e) => {
+        Err(BugError::Database(e)) => {
             eprintln!("❌ Failed to update bug #{}: {}", bug_id, e);
             Ok(HttpResponse::InternalServerError().json("Failed to update bug"))
         }
@@ -272,13 +501,16 @@ pub async fn update_bug(
 pub async fn delete_bug(
     app_state: web::Data<AppState>,
     path: web::Path<i64>,
+    req: HttpRequest,
 ) -> Result<HttpResponse> {
     let bug_id = path.into_inner();
-    
-    match database::delete_bug(&app_state.db, bug_id).await {
+    let deleted_by = deleted_by(&req);
+
+    match database::delete_bug(&app_state.db, bug_id, &deleted_by).await {
         Ok(true) => {
-            println!("🗑️  Deleted bug #{}", bug_id);
-            Ok(HttpResponse::Ok().json("Bug deleted successfully"))
+            println!("🗑️  Moved bug #{} to the trash ({})", bug_id, deleted_by);
+            events::bug_deleted(&app_state, bug_id, &deleted_by).await;
+            Ok(HttpResponse::Ok().json("Bug moved to the trash"))
         }
         Ok(false) => {
             Ok(HttpResponse::NotFound().json("Bug not found"))
@@ -290,198 +522,1641 @@ pub async fn delete_bug(
     }
 }
 
-// Developer Management
-pub async fn get_developers(app_state: web::Data<AppState>) -> Result<HttpResponse> {
-    match database::get_all_developers(&app_state.db).await {
-        Ok(developers) => {
-            println!("👥 Retrieved {} developers", developers.len());
-            Ok(HttpResponse::Ok().json(developers))
-        }
+// Deletions are attributed to the token's user when one is sent.
+fn deleted_by(req: &HttpRequest) -> String {
+    auth::claims_from_request(req)
+        .map(|claims| claims.sub)
+        .unwrap_or_else(|_| "anonymous".to_string())
+}
+
+// Trash
+pub async fn get_trash(app_state: web::Data<AppState>) -> Result<HttpResponse> {
+    match database::get_trash(&app_state.db).await {
+        Ok(bugs) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "retention_days": app_state.config.trash_retention_days,
+            "bugs": bugs,
+        }))),
         Err(e) => {
-            eprintln!("❌ Failed to fetch developers: {}", e);
-            Ok(HttpResponse::InternalServerError().json("Failed to fetch developers"))
+            eprintln!("❌ Failed to load the trash: {}", e);
+            Ok(HttpResponse::InternalServerError().json("Failed to load the trash"))
         }
     }
 }
 
-pub async fn create_developer(
+pub async fn restore_bug(
     app_state: web::Data<AppState>,
-    developer: web::Json<NewDeveloper>,
+    path: web::Path<i64>,
 ) -> Result<HttpResponse> {
-    if developer.name.trim().is_empty() {
-        return Ok(HttpResponse::BadRequest().json("Developer name is required"));
-    }
+    let bug_id = path.into_inner();
 
-    match database::create_developer(&app_state.db, &developer).await {
-        Ok(created_developer) => {
-            println!("✅ Created developer #{}: {}", created_developer.id, created_developer.name);
-            Ok(HttpResponse::Created().json(created_developer))
+    match database::restore_bug(&app_state.db, bug_id).await {
+        Ok(true) => {
+            println!("♻️  Restored bug #{} from the trash", bug_id);
+            events::bug_event_by_id(&app_state, EventKind::BugUpdated, bug_id).await;
+            bug_detail_response(&app_state, bug_id, StatusCode::OK).await
         }
+        Ok(false) => Ok(HttpResponse::NotFound().json("Bug not found in the trash")),
         Err(e) => {
-            eprintln!("❌ Failed to create developer: {}", e);
-            Ok(HttpResponse::InternalServerError().json("Failed to create developer"))
+            eprintln!("❌ Failed to restore bug #{}: {}", bug_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to restore bug"))
         }
     }
 }
 
-// Project Management
-pub async fn get_projects(app_state: web::Data<AppState>) -> Result<HttpResponse> {
-    let projects = app_state.projects.read().await;
-    println!("📁 Retrieved {} projects", projects.len());
-    Ok(HttpResponse::Ok().json(projects.clone()))
+// Permanently deletes bugs that have been in the trash for longer than
+// the retention period. Admin only.
+pub async fn purge_trash(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let claims = match require_admin(&req) {
+        Ok(claims) => claims,
+        Err(response) => return Ok(response),
+    };
+    let retention_days = app_state.config.trash_retention_days;
+
+    match database::purge_trash(&app_state.db, None, retention_days).await {
+        Ok((purged, orphaned)) => {
+            attachments::remove_blobs(&app_state, &orphaned).await;
+            println!("🔥 {} purged {} bug(s) older than {} day(s) from the trash", claims.sub, purged, retention_days);
+            Ok(HttpResponse::Ok().json(serde_json::json!({
+                "purged": purged,
+                "retention_days": retention_days,
+            })))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to purge the trash: {}", e);
+            Ok(HttpResponse::InternalServerError().json("Failed to purge the trash"))
+        }
+    }
 }
 
-pub async fn add_project(
+// Permanently deletes one trashed bug straight away. Admin only.
+pub async fn purge_bug(
     app_state: web::Data<AppState>,
-    project: web::Json<NewProject>,
+    path: web::Path<i64>,
+    req: HttpRequest,
 ) -> Result<HttpResponse> {
-    if project.name.trim().is_empty() {
-        return Ok(HttpResponse::BadRequest().json("Project name is required"));
-    }
-    
-    let new_id = app_state.get_next_project_id().await;
-    let mut projects = app_state.projects.write().await;
-    
-    let new_project = Project {
-        id: new_id,
-        name: project.name.clone(),
-        description: project.description.clone(),
-        active: true,
+    let claims = match require_admin(&req) {
+        Ok(claims) => claims,
+        Err(response) => return Ok(response),
     };
-    
-    projects.push(new_project.clone());
-    drop(projects);
-    println!("📁 Created project #{}: {}", new_id, project.name);
-    
-    Ok(HttpResponse::Created().json(new_project))
+    let bug_id = path.into_inner();
+
+    match database::purge_trash(&app_state.db, Some(bug_id), 0).await {
+        Ok((0, _)) => Ok(HttpResponse::NotFound().json("Bug not found in the trash")),
+        Ok((_, orphaned)) => {
+            attachments::remove_blobs(&app_state, &orphaned).await;
+            println!("🔥 {} purged bug #{} from the trash", claims.sub, bug_id);
+            Ok(HttpResponse::Ok().json("Bug permanently deleted"))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to purge bug #{}: {}", bug_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to purge bug"))
+        }
+    }
 }
 
-// Authentication
-pub async fn login(
+// Bulk operations
+const MAX_BULK_BUGS: usize = 500;
+
+pub async fn bulk_update_bugs(
     app_state: web::Data<AppState>,
-    credentials: web::Json<LoginRequest>,
+    body: web::Json<BulkRequest>,
+    req: HttpRequest,
 ) -> Result<HttpResponse> {
-    if credentials.username.trim().is_empty() || credentials.password.trim().is_empty() {
-        return Ok(HttpResponse::BadRequest().json(LoginResponse {
-            status: "failure".to_string(),
-            token: None,
-            message: "Username and password are required".to_string(),
-        }));
-    }
+    let request = body.into_inner();
 
-    match auth::authenticate_user(&app_state.db, &credentials.username, &credentials.password).await {
-        Ok(Some(user)) => {
-            match auth::generate_jwt(&user) {
-                Ok(token) => {
-                    println!("🔐 User '{}' logged in successfully", credentials.username);
-                    Ok(HttpResponse::Ok().json(LoginResponse {
-                        status: "success".to_string(),
-                        token: Some(token),
-                        message: "Login successful".to_string(),
-                    }))
+    let bug_ids = match (request.ids, &request.filter) {
+        (Some(ids), None) if !ids.is_empty() => {
+            let mut unique = Vec::with_capacity(ids.len());
+            for id in ids {
+                if !unique.contains(&id) {
+                    unique.push(id);
                 }
+            }
+            unique
+        }
+        (None, Some(filter)) if !filter.is_empty() => {
+            match database::get_bugs(&app_state.db, filter).await {
+                Ok(bugs) => bugs.iter().filter_map(|b| b.id).collect(),
                 Err(e) => {
-                    eprintln!("❌ JWT generation error: {}", e);
-                    Ok(HttpResponse::InternalServerError().json(LoginResponse {
-                        status: "failure".to_string(),
-                        token: None,
-                        message: "Token generation failed".to_string(),
-                    }))
+                    eprintln!("❌ Failed to select bugs for bulk update: {}", e);
+                    return Ok(HttpResponse::InternalServerError().json("Database error"));
                 }
             }
         }
-        Ok(None) => {
-            println!("🚫 Failed login attempt for '{}'", credentials.username);
-            Ok(HttpResponse::Unauthorized().json(LoginResponse {
-                status: "failure".to_string(),
-                token: None,
-                message: "Invalid username or password".to_string(),
-            }))
+        (Some(_), Some(_)) => {
+            return Ok(HttpResponse::BadRequest().json("Give either ids or filter, not both"));
         }
-        Err(e) => {
-            eprintln!("❌ Authentication error: {}", e);
-            Ok(HttpResponse::InternalServerError().json(LoginResponse {
-                status: "failure".to_string(),
-                token: None,
-                message: "Authentication system error".to_string(),
-            }))
+        _ => {
+            return Ok(HttpResponse::BadRequest().json("Give a non-empty list of ids or a filter"));
         }
+    };
+    if bug_ids.len() > MAX_BULK_BUGS {
+        return Ok(HttpResponse::BadRequest().json(format!(
+            "{} bugs selected; at most {} can be changed at once", bug_ids.len(), MAX_BULK_BUGS
+        )));
     }
-}
-
-// Bug Assignment (HTML)
-pub async fn bug_assign_form(app_state: web::Data<AppState>) -> Result<HttpResponse> {
-    let bugs = database::get_all_bugs(&app_state.db).await.unwrap_or_default();
-    let developers = database::get_all_developers(&app_state.db).await.unwrap_or_default();
-    
-    let html = format!(r#"
-<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>Bug Assignment</title>
-    <style>
-        body {{ font-family: Arial, sans-serif; max-width: 800px; margin: 50px auto; padding: 20px; }}
-        .form-group {{ margin-bottom: 15px; }}
-        label {{ display: block; margin-bottom: 5px; font-weight: bold; }}
-        select, input {{ width: 100%; padding: 8px; border: 1px solid #ddd; border-radius: 4px; }}
-        button {{ background-color: #007bff; color: white; padding: 10px 20px; border: none; border-radius: 4px; cursor: pointer; }}
-        button:hover {{ background-color: #0056b3; }}
-        .info {{ background-color: #f8f9fa; padding: 15px; border-radius: 4px; margin-bottom: 20px; }}
-    </style>
-</head>
-<body>
-    <h1>🐛 Bug Assignment</h1>
-    
-    <div class="info">
-        <h3>Available Bugs:</h3>
-        <ul>
-            {}
-        </ul>
-        
-        <h3>Available Developers:</h3>
-        <ul>
-            {}
-        </ul>
-    </div>
-    
-    <form method="post" action="/bugs/assign">
-        <div class="form-group">
-            <label for="bug_id">Bug ID:</label>
-            <select id="bug_id" name="bug_id" required>
-                <option value="">Select a bug...</option>
-                {}
-            </select>
-        </div>
-        
-        <div class="form-group">
-            <label for="developer_id">Developer:</label>
-            <select id="developer_id" name="developer_id" required>
-                <option value="">Select a developer...</option>
-                {}
-            </select>
-        </div>
-        
-        <button type="submit">Assign Bug</button>
-    </form>
-    
-    <div style="margin-top: 20px;">
-        <a href="/" style="color: #007bff;">← Back to Homepage</a>
-    </div>
-</body>
-</html>
-    "#,
-        bugs.iter().map(|b| format!("<li>#{} - {}</li>", b.id.unwrap_or(0), b.title)).collect::<Vec<_>>().join(""),
-        developers.iter().map(|d| format!("<li>#{} - {}</li>", d.id, d.name)).collect::<Vec<_>>().join(""),
-        bugs.iter().map(|b| format!("<option value=\"{}\">{} - {}</option>", b.id.unwrap_or(0), b.id.unwrap_or(0), b.title)).collect::<Vec<_>>().join(""),
-        developers.iter().map(|d| format!("<option value=\"{}\">{} - {}</option>", d.id, d.id, d.name)).collect::<Vec<_>>().join("")
-    );
-    
-    Ok(HttpResponse::Ok().content_type("text/html").body(html))
-}
 
-// Add this simple function to src/handlers.rs
+    // Problems with the operation itself fail the whole request up front.
+    let mut operation = request.operation;
+    let mut labels = Vec::new();
+    match &mut operation {
+        BulkOperation::Assign { developer_id } => {
+            match database::get_developer_by_id(&app_state.db, *developer_id).await {
+                Ok(Some(_)) => {}
+                Ok(None) => {
+                    return Ok(HttpResponse::UnprocessableEntity().json(format!("Developer #{} does not exist", developer_id)));
+                }
+                Err(e) => {
+                    eprintln!("❌ Database error checking developer #{}: {}", developer_id, e);
+                    return Ok(HttpResponse::InternalServerError().json("Database error"));
+                }
+            }
+        }
+        BulkOperation::SetSeverity { severity } => match canonical_severity(severity) {
+            Some(canonical) => *severity = canonical.to_string(),
+            None => {
+                return Ok(HttpResponse::UnprocessableEntity().json(format!(
+                    "Severity must be one of: {}", SEVERITIES.join(", ")
+                )));
+            }
+        },
+        BulkOperation::Transition { status } => match canonical_status(status) {
+            Some(canonical) => *status = canonical.to_string(),
+            None => {
+                return Ok(HttpResponse::UnprocessableEntity().json(format!(
+                    "Unknown status '{}'; expected one of: {}", status, STATUSES.join(", ")
+                )));
+            }
+        },
+        BulkOperation::AddLabels { labels: names } => {
+            if names.is_empty() {
+                return Ok(HttpResponse::BadRequest().json("No labels given"));
+            }
+            match database::find_labels(&app_state.db, names).await {
+                Ok(Ok(found)) => labels = found,
+                Ok(Err(missing)) => {
+                    return Ok(HttpResponse::UnprocessableEntity().json(format!("Unknown label(s): {}", missing.join(", "))));
+                }
+                Err(e) => {
+                    eprintln!("❌ Failed to look up labels: {}", e);
+                    return Ok(HttpResponse::InternalServerError().json("Database error"));
+                }
+            }
+        }
+        BulkOperation::Delete => {}
+    }
+
+    let actor = deleted_by(&req);
+    let policy = app_state.config.capacity_policy;
+    match database::bulk_update_bugs(
+        &app_state.db, &bug_ids, &operation, &labels, policy, &actor, request.all_or_nothing,
+    ).await {
+        Ok(result) if result.committed => {
+            println!(
+                "📦 Bulk {}: {} succeeded, {} failed",
+                result.operation, result.succeeded, result.failed
+            );
+            for item in result.results.iter().filter(|item| item.ok) {
+                match &operation {
+                    BulkOperation::Delete => events::bug_deleted(&app_state, item.bug_id, &actor).await,
+                    BulkOperation::Assign { .. } => {
+                        events::bug_event_by_id(&app_state, EventKind::BugAssigned, item.bug_id).await
+                    }
+                    BulkOperation::Transition { .. } => match &item.previous_status {
+                        Some(previous_status) => {
+                            events::bug_status_changed_by_id(&app_state, item.bug_id, previous_status).await
+                        }
+                        None => events::bug_event_by_id(&app_state, EventKind::BugUpdated, item.bug_id).await,
+                    },
+                    _ => events::bug_event_by_id(&app_state, EventKind::BugUpdated, item.bug_id).await,
+                }
+            }
+            Ok(HttpResponse::Ok().json(result))
+        }
+        Ok(result) => {
+            println!("📦 Bulk {} rolled back: {} of {} failed", result.operation, result.failed, bug_ids.len());
+            Ok(HttpResponse::UnprocessableEntity().json(result))
+        }
+        Err(e) => {
+            eprintln!("❌ Bulk {} failed: {}", operation.name(), e);
+            Ok(HttpResponse::InternalServerError().json("Failed to apply bulk operation"))
+        }
+    }
+}
+
+const CAPACITY_WARNING_HEADER: &str = "x-capacity-warning";
+
+// A missing bug is a 404; a bad developer reference is a 422; a full
+// developer under the `refuse` capacity policy is a 409.
+fn assignment_error_status(error: &AssignmentError) -> StatusCode {
+    match error {
+        AssignmentError::BugNotFound(_) => StatusCode::NOT_FOUND,
+        AssignmentError::DeveloperNotFound(_) | AssignmentError::DeveloperInactive(_) => {
+            StatusCode::UNPROCESSABLE_ENTITY
+        }
+        AssignmentError::OverCapacity { .. } => StatusCode::CONFLICT,
+    }
+}
+
+// Workflow transitions
+pub async fn transition_bug(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    body: web::Json<BugTransition>,
+) -> Result<HttpResponse> {
+    let bug_id = path.into_inner();
+
+    match bugs::transition(&app_state, bug_id, &body.status).await {
+        Ok(TransitionOutcome::Moved(bug, previous_status)) => {
+            println!("🔀 Bug #{} moved to {}", bug_id, bug.status);
+            events::bug_status_changed(&app_state, &bug, &previous_status).await;
+            Ok(HttpResponse::Ok().json(bug))
+        }
+        Ok(TransitionOutcome::NotFound) => Ok(HttpResponse::NotFound().json("Bug not found")),
+        Ok(TransitionOutcome::Rejected(message)) => {
+            Ok(HttpResponse::UnprocessableEntity().json(message))
+        }
+        Ok(TransitionOutcome::Refused(error)) => {
+            Ok(HttpResponse::build(assignment_error_status(&error)).json(error.message()))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to transition bug #{}: {}", bug_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to transition bug"))
+        }
+    }
+}
+
+// Kanban board
+async fn load_board(
+    app_state: &AppState,
+    query: &BoardQuery,
+    group_by: &str,
+) -> Result<(Board, Vec<Developer>, Vec<Project>), sqlx::Error> {
+    let filter = BugFilter {
+        project_id: query.project_id,
+        ..BugFilter::default()
+    };
+    let bugs = database::get_bugs(&app_state.db, &filter).await?;
+    let developers = database::get_all_developers(&app_state.db).await?;
+    let projects = app_state.projects.read().await.clone();
+
+    let board = board::build_board(bugs, &developers, &projects, group_by);
+    Ok((board, developers, projects))
+}
+
+pub async fn board_page(
+    app_state: web::Data<AppState>,
+    query: web::Query<BoardQuery>,
+) -> Result<HttpResponse> {
+    let Some(group_by) = board::parse_group_by(query.group_by.as_deref()) else {
+        return Ok(HttpResponse::BadRequest().json("group_by must be 'status' or 'developer'"));
+    };
+
+    match load_board(&app_state, &query, group_by).await {
+        Ok((board, developers, projects)) => {
+            Ok(templates::render(StatusCode::OK, &BoardTemplate {
+                board: &board,
+                developers: &developers,
+                projects: &projects,
+                project_id: query.project_id,
+            }))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to build board: {}", e);
+            Ok(HttpResponse::InternalServerError().json("Failed to build board"))
+        }
+    }
+}
+
+pub async fn board_json(
+    app_state: web::Data<AppState>,
+    query: web::Query<BoardQuery>,
+) -> Result<HttpResponse> {
+    let Some(group_by) = board::parse_group_by(query.group_by.as_deref()) else {
+        return Ok(HttpResponse::BadRequest().json("group_by must be 'status' or 'developer'"));
+    };
+
+    match load_board(&app_state, &query, group_by).await {
+        Ok((board, _, _)) => Ok(HttpResponse::Ok().json(board)),
+        Err(e) => {
+            eprintln!("❌ Failed to build board: {}", e);
+            Ok(HttpResponse::InternalServerError().json("Failed to build board"))
+        }
+    }
+}
+
+// Fallback for browsers without JavaScript: each card posts here.
+pub async fn board_move(
+    app_state: web::Data<AppState>,
+    form: web::Form<BoardMove>,
+) -> Result<HttpResponse> {
+    let form = form.into_inner();
+    let Some(group_by) = board::parse_group_by(Some(&form.group_by)) else {
+        return Ok(HttpResponse::BadRequest().json("group_by must be 'status' or 'developer'"));
+    };
+
+    if group_by == board::GROUP_BY_DEVELOPER {
+        let Ok(developer_id) = form.target.parse::<i64>() else {
+            return Ok(HttpResponse::BadRequest().json("Target must be a developer id"));
+        };
+        match database::assign_bug(&app_state.db, form.bug_id, developer_id, app_state.config.capacity_policy).await {
+            Ok(Ok(warning)) => {
+                println!("✅ Bug #{} assigned to developer #{} from board", form.bug_id, developer_id);
+                events::bug_event_by_id(&app_state, EventKind::BugAssigned, form.bug_id).await;
+                if let Some(warning) = warning {
+                    println!("⚠️  {}", warning);
+                }
+            }
+            Ok(Err(error)) => {
+                return Ok(HttpResponse::build(assignment_error_status(&error)).json(error.message()));
+            }
+            Err(e) => {
+                eprintln!("❌ Assignment failed: {}", e);
+                return Ok(HttpResponse::InternalServerError().json("Assignment failed"));
+            }
+        }
+    } else {
+        match bugs::transition(&app_state, form.bug_id, &form.target).await {
+            Ok(TransitionOutcome::Moved(bug, previous_status)) => {
+                println!("🔀 Bug #{} moved to {} from board", form.bug_id, bug.status);
+                events::bug_status_changed(&app_state, &bug, &previous_status).await;
+            }
+            Ok(TransitionOutcome::NotFound) => return Ok(HttpResponse::NotFound().json("Bug not found")),
+            Ok(TransitionOutcome::Rejected(message)) => {
+                return Ok(HttpResponse::UnprocessableEntity().json(message));
+            }
+            Ok(TransitionOutcome::Refused(error)) => {
+                return Ok(HttpResponse::build(assignment_error_status(&error)).json(error.message()));
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to transition bug #{}: {}", form.bug_id, e);
+                return Ok(HttpResponse::InternalServerError().json("Failed to transition bug"));
+            }
+        }
+    }
+
+    let mut location = format!("/board?group_by={}", group_by);
+    if let Some(project_id) = form.project_id {
+        location.push_str(&format!("&project_id={}", project_id));
+    }
+    Ok(templates::redirect(&location))
+}
+
+// Developer Management
+pub async fn get_developers(app_state: web::Data<AppState>) -> Result<HttpResponse> {
+    match database::get_developer_summaries(&app_state.db).await {
+        Ok(developers) => {
+            println!("👥 Retrieved {} developers", developers.len());
+            Ok(HttpResponse::Ok().json(developers))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to fetch developers: {}", e);
+            Ok(HttpResponse::InternalServerError().json("Failed to fetch developers"))
+        }
+    }
+}
+
+pub async fn create_developer(
+    app_state: web::Data<AppState>,
+    developer: web::Json<NewDeveloper>,
+) -> Result<HttpResponse> {
+    if developer.name.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json("Developer name is required"));
+    }
+
+    match database::create_developer(&app_state.db, &developer).await {
+        Ok(created_developer) => {
+            println!("✅ Created developer #{}: {}", created_developer.id, created_developer.name);
+            Ok(HttpResponse::Created().json(created_developer))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to create developer: {}", e);
+            Ok(HttpResponse::InternalServerError().json("Failed to create developer"))
+        }
+    }
+}
+
+pub async fn get_developer(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse> {
+    let developer_id = path.into_inner();
+
+    match database::get_developer_by_id(&app_state.db, developer_id).await {
+        Ok(Some(developer)) => Ok(HttpResponse::Ok().json(developer)),
+        Ok(None) => Ok(HttpResponse::NotFound().json("Developer not found")),
+        Err(e) => {
+            eprintln!("❌ Database error retrieving developer #{}: {}", developer_id, e);
+            Ok(HttpResponse::InternalServerError().json("Database error"))
+        }
+    }
+}
+
+pub async fn update_developer(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    changes: web::Json<UpdateDeveloper>,
+) -> Result<HttpResponse> {
+    let developer_id = path.into_inner();
+
+    if changes.name.as_deref().is_some_and(|name| name.trim().is_empty()) {
+        return Ok(HttpResponse::BadRequest().json("Developer name cannot be empty"));
+    }
+    if changes.max_open_bugs.flatten().is_some_and(|max| max < 0) {
+        return Ok(HttpResponse::BadRequest().json("max_open_bugs cannot be negative"));
+    }
+
+    match database::update_developer(&app_state.db, developer_id, &changes).await {
+        Ok(Some(developer)) => {
+            println!("✏️  Updated developer #{}: {} (active: {})", developer.id, developer.name, developer.active);
+            Ok(HttpResponse::Ok().json(developer))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json("Developer not found")),
+        Err(e) => {
+            eprintln!("❌ Failed to update developer #{}: {}", developer_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to update developer"))
+        }
+    }
+}
+
+pub async fn get_developer_workload(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse> {
+    let developer_id = path.into_inner();
+
+    match database::get_developer_workload(&app_state.db, developer_id).await {
+        Ok(Some(workload)) => Ok(HttpResponse::Ok().json(workload)),
+        Ok(None) => Ok(HttpResponse::NotFound().json("Developer not found")),
+        Err(e) => {
+            eprintln!("❌ Failed to load workload for developer #{}: {}", developer_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to load workload"))
+        }
+    }
+}
+
+// Email notification settings. Developers who never saved any get the
+// defaults, which have no address and so send nothing.
+// Only the developer (a user with the developer's name) or an admin may
+// read or change them: the address receives the contents of their bugs.
+pub async fn get_notification_settings(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let developer_id = path.into_inner();
+    if let Err(response) = authorize_developer(&app_state, &req, developer_id).await {
+        return Ok(response);
+    }
+
+    match load_notification_settings(&app_state, developer_id).await {
+        Ok(settings) => Ok(HttpResponse::Ok().json(settings)),
+        Err(e) => {
+            eprintln!("❌ Failed to load notification settings for developer #{}: {}", developer_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to load notification settings"))
+        }
+    }
+}
+
+pub async fn update_notification_settings(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    changes: web::Json<UpdateNotificationSettings>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let developer_id = path.into_inner();
+    if let Err(response) = authorize_developer(&app_state, &req, developer_id).await {
+        return Ok(response);
+    }
+
+    let mut settings = match load_notification_settings(&app_state, developer_id).await {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("❌ Failed to load notification settings for developer #{}: {}", developer_id, e);
+            return Ok(HttpResponse::InternalServerError().json("Failed to update notification settings"));
+        }
+    };
+
+    let changes = changes.into_inner();
+    if let Some(email) = changes.email {
+        settings.email = Some(email.trim().to_string());
+    }
+    match settings.email.as_deref() {
+        None => return Ok(HttpResponse::BadRequest().json("An email address is required")),
+        Some(email) if !notifications::valid_address(email) => {
+            return Ok(HttpResponse::BadRequest().json(format!("'{}' is not a valid email address", email)));
+        }
+        Some(_) => {}
+    }
+    if let Some(delivery) = changes.delivery {
+        let Some(delivery) = NOTIFICATION_DELIVERIES.iter().find(|d| d.eq_ignore_ascii_case(delivery.trim())) else {
+            return Ok(HttpResponse::BadRequest().json(format!(
+                "Unknown delivery '{}'; expected one of: {}", delivery, NOTIFICATION_DELIVERIES.join(", ")
+            )));
+        };
+        settings.delivery = delivery.to_string();
+    }
+    settings.on_assigned = changes.on_assigned.unwrap_or(settings.on_assigned);
+    settings.on_status_change = changes.on_status_change.unwrap_or(settings.on_status_change);
+    settings.on_comment = changes.on_comment.unwrap_or(settings.on_comment);
+
+    match database::save_notification_settings(&app_state.db, &settings).await {
+        Ok(()) => {
+            println!("✉️  Developer #{} notifications: {} to {}", developer_id, settings.delivery,
+                settings.email.as_deref().unwrap_or_default());
+            Ok(HttpResponse::Ok().json(settings))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to save notification settings for developer #{}: {}", developer_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to update notification settings"))
+        }
+    }
+}
+
+async fn load_notification_settings(
+    app_state: &AppState,
+    developer_id: i64,
+) -> Result<NotificationSettings, sqlx::Error> {
+    let settings = database::get_notification_settings(&app_state.db, developer_id).await?;
+    Ok(settings.unwrap_or_else(|| NotificationSettings::defaults(developer_id)))
+}
+
+// 401 without a valid token, 404 for an unknown developer and 403 unless
+// the token is an admin's or the developer's own, i.e. its username is the
+// developer's name (ignoring case).
+async fn authorize_developer(
+    app_state: &AppState,
+    req: &HttpRequest,
+    developer_id: i64,
+) -> std::result::Result<Claims, HttpResponse> {
+    let claims = auth::claims_from_request(req).map_err(|message| HttpResponse::Unauthorized().json(message))?;
+    let developer = match database::get_developer_by_id(&app_state.db, developer_id).await {
+        Ok(Some(developer)) => developer,
+        Ok(None) => return Err(HttpResponse::NotFound().json("Developer not found")),
+        Err(e) => {
+            eprintln!("❌ Failed to load developer #{}: {}", developer_id, e);
+            return Err(HttpResponse::InternalServerError().json("Failed to load developer"));
+        }
+    };
+    if claims.role != "admin" && !claims.sub.eq_ignore_ascii_case(developer.name.trim()) {
+        return Err(HttpResponse::Forbidden().json("Only this developer or an admin may do that"));
+    }
+    Ok(claims)
+}
+
+pub async fn delete_developer(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    query: web::Query<DeveloperDeleteQuery>,
+) -> Result<HttpResponse> {
+    let developer_id = path.into_inner();
+
+    let capacity_policy = app_state.config.capacity_policy;
+    match database::delete_developer(&app_state.db, developer_id, query.policy, query.reassign_to, capacity_policy).await {
+        Ok(database::DeleteDeveloperOutcome::Deleted { bugs_moved, warning }) => {
+            println!("🗑️  Deleted developer #{} ({:?}, {} bugs moved)", developer_id, query.policy, bugs_moved.len());
+            let kind = match query.policy {
+                DeletePolicy::Reassign => EventKind::BugAssigned,
+                _ => EventKind::BugUpdated,
+            };
+            for bug_id in &bugs_moved {
+                events::bug_event_by_id(&app_state, kind, *bug_id).await;
+            }
+            let mut response = HttpResponse::Ok();
+            if let Some(warning) = warning {
+                println!("⚠️  {}", warning);
+                response.insert_header((CAPACITY_WARNING_HEADER, warning));
+            }
+            Ok(response.json(serde_json::json!({
+                "message": "Developer deleted successfully",
+                "policy": query.policy,
+                "bugs_moved": bugs_moved.len(),
+                "reassigned_to": query.reassign_to.filter(|_| query.policy == DeletePolicy::Reassign),
+            })))
+        }
+        Ok(database::DeleteDeveloperOutcome::NotFound) => {
+            Ok(HttpResponse::NotFound().json("Developer not found"))
+        }
+        Ok(database::DeleteDeveloperOutcome::HasAssignedBugs(count)) => {
+            Ok(HttpResponse::Conflict().json(format!(
+                "Developer #{} still has {} assigned bug(s); use policy=unassign or policy=reassign&reassign_to=ID",
+                developer_id, count
+            )))
+        }
+        Ok(database::DeleteDeveloperOutcome::InvalidReassignTarget(message)) => {
+            Ok(HttpResponse::UnprocessableEntity().json(message))
+        }
+        Ok(database::DeleteDeveloperOutcome::Refused { moving, error }) => {
+            Ok(HttpResponse::build(assignment_error_status(&error)).json(format!(
+                "Cannot move {} open bug(s) from developer #{}: {}", moving, developer_id, error.message()
+            )))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to delete developer #{}: {}", developer_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to delete developer"))
+        }
+    }
+}
+
+// Bug links
+pub async fn create_bug_link(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    link: web::Json<NewBugLink>,
+) -> Result<HttpResponse> {
+    let bug_id = path.into_inner();
+
+    match database::create_bug_link(&app_state.db, bug_id, &link).await {
+        // Marking a duplicate closes the bug.
+        Ok(Ok(closed_from)) => {
+            println!("🔗 Linked bug #{} {} #{}", bug_id, link.kind.as_str(), link.target_id);
+            if let Some(previous_status) = closed_from {
+                events::bug_status_changed_by_id(&app_state, bug_id, &previous_status).await;
+            }
+            bug_detail_response(&app_state, bug_id, StatusCode::CREATED).await
+        }
+        Ok(Err(error)) => {
+            let status = match error {
+                LinkError::SelfLink => StatusCode::BAD_REQUEST,
+                LinkError::BugNotFound(_) => StatusCode::NOT_FOUND,
+                LinkError::TargetNotFound(_) => StatusCode::UNPROCESSABLE_ENTITY,
+                LinkError::AlreadyLinked
+                | LinkError::AlreadyDuplicate { .. }
+                | LinkError::Cycle(_)
+                | LinkError::CannotClose(_) => StatusCode::CONFLICT,
+            };
+            Ok(HttpResponse::build(status).json(error.message()))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to link bug #{}: {}", bug_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to link bugs"))
+        }
+    }
+}
+
+pub async fn delete_bug_link(
+    app_state: web::Data<AppState>,
+    path: web::Path<(i64, i64)>,
+) -> Result<HttpResponse> {
+    let (bug_id, link_id) = path.into_inner();
+
+    match database::delete_bug_link(&app_state.db, bug_id, link_id).await {
+        Ok(true) => {
+            println!("🔗 Removed link #{} from bug #{}", link_id, bug_id);
+            bug_detail_response(&app_state, bug_id, StatusCode::OK).await
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json("Link not found")),
+        Err(e) => {
+            eprintln!("❌ Failed to remove link #{}: {}", link_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to remove link"))
+        }
+    }
+}
+
+async fn bug_detail_response(app_state: &AppState, bug_id: i64, status: StatusCode) -> Result<HttpResponse> {
+    let bug = database::get_bug_by_id(&app_state.db, bug_id).await;
+    let links = database::get_bug_links(&app_state.db, bug_id).await;
+    match (bug, links) {
+        (Ok(Some(bug)), Ok(links)) => Ok(HttpResponse::build(status).json(BugDetail { bug, links })),
+        (Ok(None), _) => Ok(HttpResponse::NotFound().json("Bug not found")),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("❌ Database error retrieving bug #{}: {}", bug_id, e);
+            Ok(HttpResponse::InternalServerError().json("Database error"))
+        }
+    }
+}
+
+// Comments
+pub async fn get_bug_comments(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse> {
+    let bug_id = path.into_inner();
+
+    match database::get_bug_by_id(&app_state.db, bug_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Ok(HttpResponse::NotFound().json("Bug not found")),
+        Err(e) => {
+            eprintln!("❌ Database error retrieving bug #{}: {}", bug_id, e);
+            return Ok(HttpResponse::InternalServerError().json("Database error"));
+        }
+    }
+    match database::get_comments(&app_state.db, bug_id).await {
+        Ok(comments) => Ok(HttpResponse::Ok().json(comments)),
+        Err(e) => {
+            eprintln!("❌ Failed to fetch comments for bug #{}: {}", bug_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to fetch comments"))
+        }
+    }
+}
+
+// Comments are signed by the token's user, so a token is required.
+pub async fn add_bug_comment(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    body: web::Json<NewComment>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let bug_id = path.into_inner();
+    let comment = body.into_inner();
+
+    let author = match auth::claims_from_request(&req) {
+        Ok(claims) => claims.sub,
+        Err(message) => return Ok(HttpResponse::Unauthorized().json(message)),
+    };
+    if comment.body.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json("Comment body is required"));
+    }
+
+    match database::create_comment(&app_state.db, bug_id, &author, comment.body.trim(), None).await {
+        Ok(Some(created)) => {
+            println!("💬 {} commented on bug #{}", author, bug_id);
+            events::comment_created(&app_state, &created).await;
+            Ok(HttpResponse::Created().json(created))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json("Bug not found")),
+        Err(e) => {
+            eprintln!("❌ Failed to add comment to bug #{}: {}", bug_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to add comment"))
+        }
+    }
+}
+
+// Attachments
+pub async fn upload_attachments(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    payload: Multipart,
+) -> Result<HttpResponse> {
+    let bug_id = path.into_inner();
+
+    match attachments::receive(&app_state, bug_id, payload).await {
+        Ok(stored) => {
+            println!("📎 Stored {} attachment(s) on bug #{}", stored.len(), bug_id);
+            Ok(HttpResponse::Created().json(stored))
+        }
+        Err(error) => Ok(upload_error_response(bug_id, &error)),
+    }
+}
+
+fn upload_error_response(bug_id: i64, error: &UploadError) -> HttpResponse {
+    let status = match error {
+        UploadError::BugNotFound(_) => StatusCode::NOT_FOUND,
+        UploadError::NoFiles | UploadError::Multipart(_) => StatusCode::BAD_REQUEST,
+        UploadError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        UploadError::Storage(e) => {
+            eprintln!("❌ Failed to store attachment for bug #{}: {}", bug_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+        UploadError::Database(e) => {
+            eprintln!("❌ Failed to save attachment for bug #{}: {}", bug_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    HttpResponse::build(status).json(error.message())
+}
+
+pub async fn get_bug_attachments(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse> {
+    let bug_id = path.into_inner();
+
+    match database::get_bug_by_id(&app_state.db, bug_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Ok(HttpResponse::NotFound().json("Bug not found")),
+        Err(e) => {
+            eprintln!("❌ Database error retrieving bug #{}: {}", bug_id, e);
+            return Ok(HttpResponse::InternalServerError().json("Database error"));
+        }
+    }
+    match database::get_attachments_for_bug(&app_state.db, bug_id).await {
+        Ok(attachments) => Ok(HttpResponse::Ok().json(attachments)),
+        Err(e) => {
+            eprintln!("❌ Failed to fetch attachments for bug #{}: {}", bug_id, e);
+            Ok(HttpResponse::InternalServerError().json("Database error"))
+        }
+    }
+}
+
+pub async fn download_attachment(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse> {
+    let attachment_id = path.into_inner();
+
+    let attachment = match database::get_attachment_by_id(&app_state.db, attachment_id).await {
+        Ok(Some(attachment)) => attachment,
+        Ok(None) => return Ok(HttpResponse::NotFound().json("Attachment not found")),
+        Err(e) => {
+            eprintln!("❌ Database error retrieving attachment #{}: {}", attachment_id, e);
+            return Ok(HttpResponse::InternalServerError().json("Database error"));
+        }
+    };
+    let bytes = match attachments::read_blob(&app_state.config.attachments_dir, &attachment.sha256).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("❌ Attachment #{} file {} is unreadable: {}", attachment_id, attachment.sha256, e);
+            return Ok(HttpResponse::InternalServerError().json("Attachment file is missing"));
+        }
+    };
+
+    let disposition = ContentDisposition {
+        disposition: if attachments::is_inline(&attachment.content_type) {
+            DispositionType::Inline
+        } else {
+            DispositionType::Attachment
+        },
+        parameters: vec![if attachment.filename.is_ascii() {
+            DispositionParam::Filename(attachment.filename.clone())
+        } else {
+            DispositionParam::FilenameExt(ExtendedValue {
+                charset: Charset::Ext("UTF-8".to_string()),
+                language_tag: None,
+                value: attachment.filename.clone().into_bytes(),
+            })
+        }],
+    };
+    println!("📎 Downloading attachment #{} ({})", attachment_id, attachment.filename);
+    Ok(HttpResponse::Ok()
+        .content_type(attachment.content_type)
+        .insert_header(disposition)
+        .insert_header(("X-Content-Type-Options", "nosniff"))
+        .body(bytes))
+}
+
+pub async fn delete_attachment(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse> {
+    let attachment_id = path.into_inner();
+
+    match database::delete_attachment(&app_state.db, attachment_id).await {
+        Ok(Some(orphaned)) => {
+            attachments::remove_blobs(&app_state, &orphaned).await;
+            println!("🗑️  Deleted attachment #{}", attachment_id);
+            Ok(HttpResponse::Ok().json("Attachment deleted successfully"))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json("Attachment not found")),
+        Err(e) => {
+            eprintln!("❌ Failed to delete attachment #{}: {}", attachment_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to delete attachment"))
+        }
+    }
+}
+
+// Labels
+pub async fn get_labels(app_state: web::Data<AppState>) -> Result<HttpResponse> {
+    match database::get_all_labels(&app_state.db).await {
+        Ok(labels) => {
+            println!("🏷️  Retrieved {} labels", labels.len());
+            Ok(HttpResponse::Ok().json(labels))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to fetch labels: {}", e);
+            Ok(HttpResponse::InternalServerError().json("Failed to fetch labels"))
+        }
+    }
+}
+
+pub async fn get_label(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse> {
+    let label_id = path.into_inner();
+
+    match database::get_label_by_id(&app_state.db, label_id).await {
+        Ok(Some(label)) => Ok(HttpResponse::Ok().json(label)),
+        Ok(None) => Ok(HttpResponse::NotFound().json("Label not found")),
+        Err(e) => {
+            eprintln!("❌ Database error retrieving label #{}: {}", label_id, e);
+            Ok(HttpResponse::InternalServerError().json("Database error"))
+        }
+    }
+}
+
+pub async fn create_label(
+    app_state: web::Data<AppState>,
+    label: web::Json<NewLabel>,
+) -> Result<HttpResponse> {
+    if label.name.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json("Label name is required"));
+    }
+    if label.name.contains(',') {
+        return Ok(HttpResponse::BadRequest().json("Label names cannot contain commas"));
+    }
+    if let Some(color) = &label.color
+        && !is_valid_color(color)
+    {
+        return Ok(HttpResponse::BadRequest().json("Color must be a hex colour like #d73a4a"));
+    }
+
+    match database::create_label(&app_state.db, &label).await {
+        Ok(created) => {
+            println!("🏷️  Created label #{}: {}", created.id, created.name);
+            Ok(HttpResponse::Created().json(created))
+        }
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            Ok(HttpResponse::Conflict().json(format!("Label '{}' already exists", label.name.trim())))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to create label: {}", e);
+            Ok(HttpResponse::InternalServerError().json("Failed to create label"))
+        }
+    }
+}
+
+pub async fn update_label(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    changes: web::Json<UpdateLabel>,
+) -> Result<HttpResponse> {
+    let label_id = path.into_inner();
+
+    if let Some(name) = &changes.name
+        && (name.trim().is_empty() || name.contains(','))
+    {
+        return Ok(HttpResponse::BadRequest().json("Label names cannot be empty or contain commas"));
+    }
+    if let Some(color) = &changes.color
+        && !is_valid_color(color)
+    {
+        return Ok(HttpResponse::BadRequest().json("Color must be a hex colour like #d73a4a"));
+    }
+
+    match database::update_label(&app_state.db, label_id, &changes).await {
+        Ok(Some(label)) => {
+            println!("✏️  Updated label #{}: {}", label.id, label.name);
+            Ok(HttpResponse::Ok().json(label))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json("Label not found")),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            Ok(HttpResponse::Conflict().json("Another label already has that name"))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to update label #{}: {}", label_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to update label"))
+        }
+    }
+}
+
+pub async fn delete_label(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse> {
+    let label_id = path.into_inner();
+
+    match database::delete_label(&app_state.db, label_id).await {
+        Ok(true) => {
+            println!("🗑️  Deleted label #{}", label_id);
+            Ok(HttpResponse::Ok().json("Label deleted successfully"))
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json("Label not found")),
+        Err(e) => {
+            eprintln!("❌ Failed to delete label #{}: {}", label_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to delete label"))
+        }
+    }
+}
+
+pub async fn add_bug_labels(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    body: web::Json<BugLabels>,
+) -> Result<HttpResponse> {
+    change_bug_labels(&app_state, path.into_inner(), &body.labels, true).await
+}
+
+pub async fn remove_bug_labels(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    body: web::Json<BugLabels>,
+) -> Result<HttpResponse> {
+    change_bug_labels(&app_state, path.into_inner(), &body.labels, false).await
+}
+
+// Adding a label twice or removing one the bug doesn't have is a no-op;
+// unknown label names reject the whole request. Returns the updated bug.
+async fn change_bug_labels(
+    app_state: &AppState,
+    bug_id: i64,
+    names: &[String],
+    add: bool,
+) -> Result<HttpResponse> {
+    match database::get_bug_by_id(&app_state.db, bug_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Ok(HttpResponse::NotFound().json("Bug not found")),
+        Err(e) => {
+            eprintln!("❌ Database error retrieving bug #{}: {}", bug_id, e);
+            return Ok(HttpResponse::InternalServerError().json("Database error"));
+        }
+    }
+
+    let labels = match database::find_labels(&app_state.db, names).await {
+        Ok(Ok(labels)) => labels,
+        Ok(Err(missing)) => {
+            return Ok(HttpResponse::UnprocessableEntity().json(
+                format!("Unknown label(s): {}", missing.join(", "))
+            ));
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to look up labels: {}", e);
+            return Ok(HttpResponse::InternalServerError().json("Database error"));
+        }
+    };
+
+    let result = if add {
+        database::add_bug_labels(&app_state.db, bug_id, &labels).await
+    } else {
+        database::remove_bug_labels(&app_state.db, bug_id, &labels).await
+    };
+    if let Err(e) = result {
+        eprintln!("❌ Failed to update labels on bug #{}: {}", bug_id, e);
+        return Ok(HttpResponse::InternalServerError().json("Failed to update labels"));
+    }
+
+    match database::get_bug_by_id(&app_state.db, bug_id).await {
+        Ok(Some(bug)) => {
+            println!("🏷️  Bug #{} labels: {}", bug_id,
+                bug.labels.iter().map(|l| l.name.as_str()).collect::<Vec<_>>().join(", "));
+            events::bug_event(app_state, EventKind::BugUpdated, &bug).await;
+            Ok(HttpResponse::Ok().json(bug))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json("Bug not found")),
+        Err(e) => {
+            eprintln!("❌ Database error retrieving bug #{}: {}", bug_id, e);
+            Ok(HttpResponse::InternalServerError().json("Database error"))
+        }
+    }
+}
+
+// Dry run of auto-assignment: ranks candidates for an existing bug without
+// assigning it or advancing the round-robin rotation. `?strategy=` overrides
+// the project's configured strategy; bugs without one are ranked by load.
+pub async fn suggest_assignee(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    query: web::Query<SuggestAssigneeQuery>,
+) -> Result<HttpResponse> {
+    let bug_id = path.into_inner();
+
+    let bug = match database::get_bug_by_id(&app_state.db, bug_id).await {
+        Ok(Some(bug)) => bug,
+        Ok(None) => return Ok(HttpResponse::NotFound().json("Bug not found")),
+        Err(e) => {
+            eprintln!("❌ Database error retrieving bug #{}: {}", bug_id, e);
+            return Ok(HttpResponse::InternalServerError().json("Database error"));
+        }
+    };
+    let project = match bug.project_id {
+        Some(project_id) => app_state.get_project(project_id).await,
+        None => None,
+    };
+    let config = project.map(|p| p.assignment).unwrap_or_default();
+
+    let kind = match query.strategy.unwrap_or(config.strategy) {
+        AssignmentStrategyKind::Manual => AssignmentStrategyKind::LeastLoaded,
+        kind => kind,
+    };
+    let Some(strategy) = assignment::strategy_for(kind) else {
+        return Ok(HttpResponse::BadRequest().json("Unknown assignment strategy"));
+    };
+
+    let developers = match database::get_developer_summaries(&app_state.db).await {
+        Ok(developers) => developers,
+        Err(e) => {
+            eprintln!("❌ Failed to fetch developers: {}", e);
+            return Ok(HttpResponse::InternalServerError().json("Database error"));
+        }
+    };
+    let last_assigned = match bug.project_id {
+        Some(project_id) => app_state.round_robin.lock().await.get(&project_id).copied(),
+        None => None,
+    };
+
+    let candidates = assignment::rank_candidates(strategy, &bug, &developers, &config, last_assigned);
+    println!("🤖 Suggested {} assignee(s) for bug #{}", candidates.len(), bug_id);
+    Ok(HttpResponse::Ok().json(AssigneeSuggestion { bug_id, strategy: kind, candidates }))
+}
+
+// Project Management
+pub async fn get_projects(app_state: web::Data<AppState>) -> Result<HttpResponse> {
+    let projects = app_state.projects.read().await;
+    println!("📁 Retrieved {} projects", projects.len());
+    Ok(HttpResponse::Ok().json(projects.clone()))
+}
+
+pub async fn add_project(
+    app_state: web::Data<AppState>,
+    project: web::Json<NewProject>,
+) -> Result<HttpResponse> {
+    if project.name.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json("Project name is required"));
+    }
+    
+    let new_id = app_state.get_next_project_id().await;
+    let mut projects = app_state.projects.write().await;
+
+    let new_project = Project {
+        id: new_id,
+        name: project.name.clone(),
+        description: project.description.clone(),
+        active: true,
+        assignment: AssignmentConfig::default(),
+        components: normalize_tags(&project.components),
+    };
+
+    // Replaces whatever a project with this id, lost on a restart, saved.
+    let saved = async {
+        database::save_project_assignment(&app_state.db, new_id, &new_project.assignment).await?;
+        database::save_project_components(&app_state.db, new_id, &new_project.components).await
+    };
+    if let Err(e) = saved.await {
+        eprintln!("❌ Failed to save the settings of project #{}: {}", new_id, e);
+        return Ok(HttpResponse::InternalServerError().json("Database error"));
+    }
+    
+    projects.push(new_project.clone());
+    drop(projects);
+    println!("📁 Created project #{}: {}", new_id, project.name);
+    
+    Ok(HttpResponse::Created().json(new_project))
+}
+
+pub async fn get_project_assignment(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse> {
+    match app_state.get_project(path.into_inner()).await {
+        Some(project) => Ok(HttpResponse::Ok().json(project.assignment)),
+        None => Ok(HttpResponse::NotFound().json("Project not found")),
+    }
+}
+
+pub async fn update_project_assignment(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    config: web::Json<AssignmentConfig>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req) {
+        return Ok(response);
+    }
+    let project_id = path.into_inner();
+    let config = config.into_inner();
+
+    for rule in &config.rules {
+        if rule.keywords.iter().all(|k| k.trim().is_empty()) {
+            return Ok(HttpResponse::BadRequest().json("Routing rules need at least one keyword"));
+        }
+        match database::get_developer_by_id(&app_state.db, rule.developer_id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return Ok(HttpResponse::UnprocessableEntity().json(
+                    format!("Developer #{} does not exist", rule.developer_id)
+                ));
+            }
+            Err(e) => {
+                eprintln!("❌ Database error retrieving developer #{}: {}", rule.developer_id, e);
+                return Ok(HttpResponse::InternalServerError().json("Database error"));
+            }
+        }
+    }
+
+    let mut projects = app_state.projects.write().await;
+    let Some(project) = projects.iter_mut().find(|p| p.id == project_id) else {
+        return Ok(HttpResponse::NotFound().json("Project not found"));
+    };
+    if let Err(e) = database::save_project_assignment(&app_state.db, project_id, &config).await {
+        eprintln!("❌ Failed to save the assignment settings of project #{}: {}", project_id, e);
+        return Ok(HttpResponse::InternalServerError().json("Database error"));
+    }
+    project.assignment = config;
+    println!("🤖 Project #{} now assigns bugs with {:?}", project_id, project.assignment.strategy);
+    Ok(HttpResponse::Ok().json(project.assignment.clone()))
+}
+
+// Blocking relationships around a project as JSON (nodes, edges, fix
+// order and cycles) or, with `?format=dot`, as a Graphviz digraph.
+pub async fn get_dependency_graph(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    query: web::Query<DependencyGraphQuery>,
+) -> Result<HttpResponse> {
+    let project_id = path.into_inner();
+
+    let as_dot = match query.format.as_deref() {
+        None | Some("json") => false,
+        Some("dot") => true,
+        Some(other) => {
+            return Ok(HttpResponse::BadRequest().json(format!("Unknown format '{}' (use json or dot)", other)));
+        }
+    };
+    if !app_state.has_project(project_id).await {
+        return Ok(HttpResponse::NotFound().json("Project not found"));
+    }
+
+    let filter = BugFilter { project_id: Some(project_id), ..BugFilter::default() };
+    let loaded = async {
+        let project_bugs = database::get_bugs(&app_state.db, &filter).await?;
+        let blocks = database::get_link_edges(&app_state.db, LinkKind::Blocks).await?;
+        let in_project: Vec<i64> = project_bugs.iter().filter_map(|b| b.id).collect();
+        let mut external: Vec<i64> = blocks.iter()
+            .filter(|(a, b)| in_project.contains(a) || in_project.contains(b))
+            .flat_map(|(a, b)| [*a, *b])
+            .filter(|id| !in_project.contains(id))
+            .collect();
+        external.sort_unstable();
+        external.dedup();
+        let external_bugs = database::get_bugs_by_ids(&app_state.db, &external).await?;
+        Ok::<_, sqlx::Error>((project_bugs, external_bugs, blocks))
+    };
+    let (project_bugs, external_bugs, blocks) = match loaded.await {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("❌ Failed to load dependency graph for project #{}: {}", project_id, e);
+            return Ok(HttpResponse::InternalServerError().json("Database error"));
+        }
+    };
+
+    let graph = graph::build_dependency_graph(project_id, project_bugs, external_bugs, &blocks);
+    println!("🕸️  Dependency graph for project #{}: {} nodes, {} edges, {} cycle(s)",
+        project_id, graph.nodes.len(), graph.edges.len(), graph.cycles.len());
+    if as_dot {
+        Ok(HttpResponse::Ok()
+            .content_type("text/vnd.graphviz; charset=utf-8")
+            .body(graph::to_dot(&graph)))
+    } else {
+        Ok(HttpResponse::Ok().json(graph))
+    }
+}
+
+pub async fn update_project_components(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    components: web::Json<Vec<String>>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req) {
+        return Ok(response);
+    }
+    let project_id = path.into_inner();
+    let components = normalize_tags(&components);
+
+    let mut projects = app_state.projects.write().await;
+    let Some(project) = projects.iter_mut().find(|p| p.id == project_id) else {
+        return Ok(HttpResponse::NotFound().json("Project not found"));
+    };
+    // An empty list accepts any component, so only a shorter, non-empty
+    // list can leave bugs with a component their project no longer has.
+    if !components.is_empty() {
+        let in_use = match database::get_project_bug_components(&app_state.db, project_id).await {
+            Ok(in_use) => in_use,
+            Err(e) => {
+                eprintln!("❌ Failed to load the components of project #{}'s bugs: {}", project_id, e);
+                return Ok(HttpResponse::InternalServerError().json("Database error"));
+            }
+        };
+        let removed: Vec<String> = in_use.into_iter().filter(|c| !components.contains(c)).collect();
+        if !removed.is_empty() {
+            return Ok(HttpResponse::Conflict().json(format!(
+                "Bugs in {} still use component(s): {}", project.name, removed.join(", ")
+            )));
+        }
+    }
+    if let Err(e) = database::save_project_components(&app_state.db, project_id, &components).await {
+        eprintln!("❌ Failed to save the components of project #{}: {}", project_id, e);
+        return Ok(HttpResponse::InternalServerError().json("Database error"));
+    }
+    project.components = components;
+    println!("🧩 Project #{} components: {}", project_id, project.components.join(", "));
+    Ok(HttpResponse::Ok().json(project.clone()))
+}
+
+// Who owns this area: component owners, then developers with a matching
+// skill, plus the projects that list the component.
+pub async fn get_component_owners(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let component = path.into_inner().trim().to_lowercase();
+    let projects = app_state.projects.read().await.iter()
+        .filter(|p| p.components.contains(&component))
+        .map(|p| p.id)
+        .collect();
+
+    match database::get_component_owners(&app_state.db, &component).await {
+        Ok((owners, skilled)) => {
+            println!("🧩 Component '{}' has {} owner(s)", component, owners.len());
+            Ok(HttpResponse::Ok().json(ComponentOwners { component, projects, owners, skilled }))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to look up owners of '{}': {}", component, e);
+            Ok(HttpResponse::InternalServerError().json("Database error"))
+        }
+    }
+}
+
+// Authentication
+pub async fn login(
+    app_state: web::Data<AppState>,
+    credentials: web::Json<LoginRequest>,
+) -> Result<HttpResponse> {
+    if credentials.username.trim().is_empty() || credentials.password.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json(LoginResponse {
+            status: "failure".to_string(),
+            token: None,
+            message: "Username and password are required".to_string(),
+        }));
+    }
+
+    match auth::authenticate_user(&app_state.db, &credentials.username, &credentials.password).await {
+        Ok(Some(user)) => {
+            match auth::generate_jwt(&user) {
+                Ok(token) => {
+                    println!("🔐 User '{}' logged in successfully", credentials.username);
+                    Ok(HttpResponse::Ok().json(LoginResponse {
+                        status: "success".to_string(),
+                        token: Some(token),
+                        message: "Login successful".to_string(),
+                    }))
+                }
+                Err(e) => {
+                    eprintln!("❌ JWT generation error: {}", e);
+                    Ok(HttpResponse::InternalServerError().json(LoginResponse {
+                        status: "failure".to_string(),
+                        token: None,
+                        message: "Token generation failed".to_string(),
+                    }))
+                }
+            }
+        }
+        Ok(None) => {
+            println!("🚫 Failed login attempt for '{}'", credentials.username);
+            Ok(HttpResponse::Unauthorized().json(LoginResponse {
+                status: "failure".to_string(),
+                token: None,
+                message: "Invalid username or password".to_string(),
+            }))
+        }
+        Err(e) => {
+            eprintln!("❌ Authentication error: {}", e);
+            Ok(HttpResponse::InternalServerError().json(LoginResponse {
+                status: "failure".to_string(),
+                token: None,
+                message: "Authentication system error".to_string(),
+            }))
+        }
+    }
+}
+
+// Bug Assignment (HTML)
+pub async fn bug_assign_form(app_state: web::Data<AppState>) -> Result<HttpResponse> {
+    let bugs = database::get_all_bugs(&app_state.db).await.unwrap_or_default();
+    let developers: Vec<DeveloperSummary> = database::get_developer_summaries(&app_state.db).await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|summary| summary.developer.active)
+        .collect();
+
+    Ok(templates::render(StatusCode::OK, &BugAssignFormTemplate {
+        bugs: &bugs,
+        developers: &developers,
+    }))
+}
+
+// Web UI (HTML)
+#[derive(Debug, serde::Deserialize)]
+pub struct NoticeQuery {
+    pub notice: Option<String>,
+}
+
+pub async fn ui_bug_list(
+    app_state: web::Data<AppState>,
+    filter: web::Query<BugFilter>,
+) -> Result<HttpResponse> {
+    let bugs = match database::get_bugs(&app_state.db, &filter).await {
+        Ok(bugs) => bugs,
+        Err(e) => {
+            eprintln!("❌ Failed to fetch bugs: {}", e);
+            return Ok(HttpResponse::InternalServerError().json("Failed to fetch bugs"));
+        }
+    };
+    let developers = database::get_all_developers(&app_state.db).await.unwrap_or_default();
+    let projects = app_state.projects.read().await.clone();
+
+    Ok(templates::render(StatusCode::OK, &BugListTemplate {
+        bugs: &bugs,
+        developers: &developers,
+        projects: &projects,
+        filter: &filter,
+    }))
+}
+
+pub async fn ui_bug_detail(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    query: web::Query<NoticeQuery>,
+) -> Result<HttpResponse> {
+    let bug_id = path.into_inner();
+
+    let bug = match database::get_bug_by_id(&app_state.db, bug_id).await {
+        Ok(Some(bug)) => bug,
+        Ok(None) => return Ok(HttpResponse::NotFound().json("Bug not found")),
+        Err(e) => {
+            eprintln!("❌ Database error retrieving bug #{}: {}", bug_id, e);
+            return Ok(HttpResponse::InternalServerError().json("Database error"));
+        }
+    };
+    let developers = database::get_all_developers(&app_state.db).await.unwrap_or_default();
+    let projects = app_state.projects.read().await.clone();
+    let links = database::get_bug_links(&app_state.db, bug_id).await.unwrap_or_default();
+    let attachments = database::get_attachments_for_bug(&app_state.db, bug_id).await.unwrap_or_default();
+    let comments = database::get_comments(&app_state.db, bug_id).await.unwrap_or_default();
+    let notice = match query.notice.as_deref() {
+        Some("attached") => Some("Attachment uploaded."),
+        Some("created") => Some("Bug reported successfully."),
+        Some("updated") => Some("Bug updated successfully."),
+        Some("updated_over_capacity") => {
+            Some("Bug updated. Note: the assignee is now over their open bug capacity.")
+        }
+        _ => None,
+    };
+
+    Ok(templates::render(StatusCode::OK, &BugDetailTemplate {
+        bug: &bug,
+        developers: &developers,
+        projects: &projects,
+        links: &links,
+        attachments: &attachments,
+        comments: &comments,
+        notice,
+    }))
+}
+
+pub async fn ui_new_bug_form(app_state: web::Data<AppState>) -> Result<HttpResponse> {
+    let developers = database::get_all_developers(&app_state.db).await.unwrap_or_default();
+    let projects = app_state.projects.read().await.clone();
+
+    Ok(templates::render(StatusCode::OK, &BugFormTemplate {
+        heading: "Report a Bug",
+        action: "/ui/bugs/new".to_string(),
+        submit_label: "Submit Bug",
+        bug_id: None,
+        form: &BugForm::default(),
+        errors: &[],
+        developers: &developers,
+        projects: &projects,
+    }))
+}
+
+pub async fn ui_create_bug(
+    app_state: web::Data<AppState>,
+    form: web::Form<BugForm>,
+) -> Result<HttpResponse> {
+    let form = form.into_inner();
+    let developers = database::get_all_developers(&app_state.db).await.unwrap_or_default();
+    let projects = app_state.projects.read().await.clone();
+
+    let mut errors = form.validate(&developers, &projects);
+    if errors.is_empty() {
+        match bugs::create(&app_state, &form.to_new_bug()).await {
+            Ok(created) => {
+                let bug_id = created.bug.id.unwrap_or(0);
+                return Ok(templates::redirect(&format!("/ui/bugs/{}?notice=created", bug_id)));
+            }
+            Err(BugError::Invalid(error)) => errors.push(error),
+            Err(e) => {
+                eprintln!("❌ Failed to create bug: {:?}", e);
+                return Ok(HttpResponse::InternalServerError().json("Failed to create bug"));
+            }
+        }
+    }
+
+    Ok(templates::render(StatusCode::UNPROCESSABLE_ENTITY, &BugFormTemplate {
+        heading: "Report a Bug",
+        action: "/ui/bugs/new".to_string(),
+        submit_label: "Submit Bug",
+        bug_id: None,
+        form: &form,
+        errors: &errors,
+        developers: &developers,
+        projects: &projects,
+    }))
+}
+
+pub async fn ui_edit_bug_form(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse> {
+    let bug_id = path.into_inner();
+
+    let bug = match database::get_bug_by_id(&app_state.db, bug_id).await {
+        Ok(Some(bug)) => bug,
+        Ok(None) => return Ok(HttpResponse::NotFound().json("Bug not found")),
+        Err(e) => {
+            eprintln!("❌ Database error retrieving bug #{}: {}", bug_id, e);
+            return Ok(HttpResponse::InternalServerError().json("Database error"));
+        }
+    };
+    let developers = database::get_all_developers(&app_state.db).await.unwrap_or_default();
+    let projects = app_state.projects.read().await.clone();
+
+    Ok(templates::render(StatusCode::OK, &BugFormTemplate {
+        heading: &format!("Edit Bug #{}", bug_id),
+        action: format!("/ui/bugs/{}/edit", bug_id),
+        submit_label: "Save Changes",
+        bug_id: Some(bug_id),
+        form: &BugForm::from_bug(&bug),
+        errors: &[],
+        developers: &developers,
+        projects: &projects,
+    }))
+}
+
+pub async fn ui_update_bug(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    form: web::Form<BugForm>,
+) -> Result<HttpResponse> {
+    let bug_id = path.into_inner();
+    let form = form.into_inner();
+    let developers = database::get_all_developers(&app_state.db).await.unwrap_or_default();
+    let projects = app_state.projects.read().await.clone();
+
+    let mut errors = form.validate(&developers, &projects);
+    if errors.is_empty() {
+        match bugs::update(&app_state, bug_id, &form.to_bug(bug_id)).await {
+            Ok(updated) => {
+                let notice = if updated.warning.is_some() { "updated_over_capacity" } else { "updated" };
+                return Ok(templates::redirect(&format!("/ui/bugs/{}?notice={}", bug_id, notice)));
+            }
+            Err(BugError::Invalid(error)) => errors.push(error),
+            Err(BugError::NotFound | BugError::Assignment(AssignmentError::BugNotFound(_))) => {
+                return Ok(HttpResponse::NotFound().json("Bug not found"));
+            }
+            Err(BugError::Assignment(error)) => errors.push(FieldError::new("developer_id", &error.message())),
+            Err(BugError::Database(e)) => {
+                eprintln!("❌ Failed to update bug #{}: {}", bug_id, e);
+                return Ok(HttpResponse::InternalServerError().json("Failed to update bug"));
+            }
+        }
+    }
+
+    Ok(templates::render(StatusCode::UNPROCESSABLE_ENTITY, &BugFormTemplate {
+        heading: &format!("Edit Bug #{}", bug_id),
+        action: format!("/ui/bugs/{}/edit", bug_id),
+        submit_label: "Save Changes",
+        bug_id: Some(bug_id),
+        form: &form,
+        errors: &errors,
+        developers: &developers,
+        projects: &projects,
+    }))
+}
+
+pub async fn ui_upload_attachments(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    payload: Multipart,
+) -> Result<HttpResponse> {
+    let bug_id = path.into_inner();
+
+    match attachments::receive(&app_state, bug_id, payload).await {
+        Ok(stored) => {
+            println!("📎 Stored {} attachment(s) on bug #{} via web form", stored.len(), bug_id);
+            Ok(templates::redirect(&format!("/ui/bugs/{}?notice=attached", bug_id)))
+        }
+        Err(error) => Ok(upload_error_response(bug_id, &error)),
+    }
+}
+
+pub async fn ui_delete_bug(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let bug_id = path.into_inner();
+    let deleted_by = deleted_by(&req);
+
+    match database::delete_bug(&app_state.db, bug_id, &deleted_by).await {
+        Ok(true) => {
+            println!("🗑️  Moved bug #{} to the trash via web form", bug_id);
+            events::bug_deleted(&app_state, bug_id, &deleted_by).await;
+            Ok(templates::redirect("/ui/bugs"))
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json("Bug not found")),
+        Err(e) => {
+            eprintln!("❌ Failed to delete bug #{}: {}", bug_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to delete bug"))
+        }
+    }
+}
+
+// Add this simple function to src/handlers.rs
 pub async fn fix_admin(app_state: web::Data<AppState>) -> Result<HttpResponse> {
     // Get the correct hash using your auth function
     let correct_hash = crate::auth::hash_password("admin123").unwrap();
@@ -499,18 +2174,7 @@ pub async fn fix_admin(app_state: web::Data<AppState>) -> Result<HttpResponse> {
 }
 
 pub async fn protected_endpoint(req: HttpRequest) -> Result<HttpResponse> {
-    let auth_header = match req.headers().get("Authorization") {
-        Some(header) => header.to_str().unwrap_or(""),
-        None => return Ok(HttpResponse::Unauthorized().json("Missing Authorization header")),
-    };
-    
-    if !auth_header.starts_with("Bearer ") {
-        return Ok(HttpResponse::Unauthorized().json("Invalid Authorization format"));
-    }
-    
-    let token = &auth_header[7..];
-    
-    match crate::auth::verify_jwt(token) {
+    match auth::claims_from_request(&req) {
         Ok(claims) => {
             Ok(HttpResponse::Ok().json(serde_json::json!({
                 "message": "Access granted!",
@@ -519,79 +2183,17 @@ pub async fn protected_endpoint(req: HttpRequest) -> Result<HttpResponse> {
                 "expires": claims.exp
             })))
         }
-        Err(_) => {
-            Ok(HttpResponse::Unauthorized().json("Invalid or expired token"))
-        }
+        Err(message) => Ok(HttpResponse::Unauthorized().json(message)),
     }
 }
 
-// Add these to src/handlers.rs (after the import fix above)
-
-pub async fn debug_users(app_state: web::Data<AppState>) -> Result<HttpResponse> {
-    let users = sqlx::query("SELECT id, username, role, password_hash FROM users")
-        .fetch_all(&app_state.db)
-        .await
-        .unwrap_or_default();
-    
-    let user_info: Vec<serde_json::Value> = users.iter().map(|row| {
-        serde_json::json!({
-            "id": row.get::<i64, _>("id"),
-            "username": row.get::<String, _>("username"),
-            "role": row.get::<String, _>("role"),
-            "password_hash_length": row.get::<String, _>("password_hash").len()
-        })
-    }).collect();
-    
-    Ok(HttpResponse::Ok().json(user_info))
-}
-
-pub async fn debug_password() -> Result<HttpResponse> {
-    let test_password = "admin123";
-    let salt = "bugtrack2025";
-    let salted = format!("{}{}", salt, test_password);
-    
-    let hash1 = bcrypt::hash(&salted, bcrypt::DEFAULT_COST).unwrap();
-    let hash2 = bcrypt::hash(&salted, bcrypt::DEFAULT_COST).unwrap();
-    
-    let verify1 = bcrypt::verify(&salted, &hash1).unwrap();
-    let verify2 = bcrypt::verify(&salted, &hash2).unwrap();
-    
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "original_password": test_password,
-        "salt": salt,
-        "salted_password": salted,
-        "hash1": hash1,
-        "hash2": hash2,
-        "verify1": verify1,
-        "verify2": verify2,
-        "hashes_different": hash1 != hash2
-    })))
-}
-
-pub async fn reset_admin(app_state: web::Data<AppState>) -> Result<HttpResponse> {
-    // Delete existing admin
-    sqlx::query("DELETE FROM users WHERE username = 'admin'")
-        .execute(&app_state.db)
-        .await
-        .unwrap();
-    
-    // Create new admin with correct hash
-    let password_hash = crate::auth::hash_password("admin123").unwrap();
-    
-    sqlx::query("INSERT INTO users (username, password_hash, role) VALUES (?, ?, ?)")
-        .bind("admin")
-        .bind(&password_hash)
-        .bind("admin")
-        .execute(&app_state.db)
-        .await
-        .unwrap();
-    
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "message": "Admin user reset successfully",
-        "username": "admin",
-        "password": "admin123",
-        "hash": password_hash
-    })))
+// 401 without a valid token, 403 for a valid token that isn't an admin's.
+fn require_admin(req: &HttpRequest) -> std::result::Result<Claims, HttpResponse> {
+    match auth::claims_from_request(req) {
+        Ok(claims) if claims.role == "admin" => Ok(claims),
+        Ok(_) => Err(HttpResponse::Forbidden().json("Admin role required")),
+        Err(message) => Err(HttpResponse::Unauthorized().json(message)),
+    }
 }
 
 pub async fn assign_bug(
@@ -600,43 +2202,288 @@ pub async fn assign_bug(
 ) -> Result<HttpResponse> {
     let assignment = form.into_inner();
     
-    match database::assign_bug(&app_state.db, assignment.bug_id, assignment.developer_id).await {
-        Ok(true) => {
+    let policy = app_state.config.capacity_policy;
+    match database::assign_bug(&app_state.db, assignment.bug_id, assignment.developer_id, policy).await {
+        Ok(Ok(warning)) => {
             println!("✅ Bug #{} assigned to developer #{}", assignment.bug_id, assignment.developer_id);
-            
-            let html = format!(r#"
-<!DOCTYPE html>
-<html>
-<head><title>Assignment Successful</title></head>
-<body style="font-family: Arial, sans-serif; max-width: 600px; margin: 50px auto; padding: 20px;">
-    <h1 style="color: green;">✅ Assignment Successful</h1>
-    <p>Bug #{} has been successfully assigned to developer #{}</p>
-    <a href="/bugs/assign" style="display: inline-block; padding: 10px 20px; background-color: #007bff; color: white; text-decoration: none; border-radius: 4px;">Back to Assignment Form</a>
-    <a href="/" style="display: inline-block; padding: 10px 20px; background-color: #6c757d; color: white; text-decoration: none; border-radius: 4px; margin-left: 10px;">Back to Homepage</a>
-</body>
-</html>
-            "#, assignment.bug_id, assignment.developer_id);
-            
-            Ok(HttpResponse::Ok().content_type("text/html").body(html))
+            events::bug_event_by_id(&app_state, EventKind::BugAssigned, assignment.bug_id).await;
+            let mut response = templates::render(StatusCode::OK, &AssignResultTemplate {
+                success: true,
+                message: format!(
+                    "Bug #{} has been successfully assigned to developer #{}",
+                    assignment.bug_id, assignment.developer_id
+                ),
+                warning: warning.clone(),
+            });
+            if let Some(warning) = warning
+                && let Ok(value) = HeaderValue::from_str(&warning)
+            {
+                response.headers_mut().insert(HeaderName::from_static(CAPACITY_WARNING_HEADER), value);
+            }
+            Ok(response)
         }
-        Ok(false) => {
-            let html = r#"
-<!DOCTYPE html>
-<html>
-<head><title>Assignment Failed</title></head>
-<body style="font-family: Arial, sans-serif; max-width: 600px; margin: 50px auto; padding: 20px;">
-    <h1 style="color: red;">❌ Assignment Failed</h1>
-    <p>Bug or developer not found</p>
-    <a href="/bugs/assign" style="display: inline-block; padding: 10px 20px; background-color: #007bff; color: white; text-decoration: none; border-radius: 4px;">Back to Assignment Form</a>
-    <a href="/" style="display: inline-block; padding: 10px 20px; background-color: #6c757d; color: white; text-decoration: none; border-radius: 4px; margin-left: 10px;">Back to Homepage</a>
-</body>
-</html>
-            "#;
-            Ok(HttpResponse::BadRequest().content_type("text/html").body(html))
+        Ok(Err(error)) => {
+            Ok(templates::render(assignment_error_status(&error), &AssignResultTemplate {
+                success: false,
+                message: error.message(),
+                warning: None,
+            }))
         }
         Err(e) => {
             eprintln!("❌ Assignment failed: {}", e);
             Ok(HttpResponse::InternalServerError().json("Assignment failed"))
         }
     }
-}
\ No newline at end of file
+}
+
+// Webhooks. Admin only, since they expose every change to bugs.
+fn webhook_url_error(url: &str) -> Option<&'static str> {
+    let url = url.trim();
+    let valid = (url.starts_with("http://") || url.starts_with("https://")) && !url.contains(char::is_whitespace);
+    (!valid).then_some("url must be an http:// or https:// URL")
+}
+
+// Event names in the given order without repeats; unknown names are the error.
+fn webhook_events(events: &[String]) -> std::result::Result<Vec<String>, String> {
+    if events.is_empty() {
+        return Err(format!("Give at least one event: {}", events::EVENT_TYPES.join(", ")));
+    }
+    let mut subscribed = Vec::new();
+    for event in events {
+        let Some(name) = events::EVENT_TYPES.iter().find(|name| name.eq_ignore_ascii_case(event.trim())) else {
+            return Err(format!("Unknown event '{}'; expected one of: {}", event, events::EVENT_TYPES.join(", ")));
+        };
+        if !subscribed.iter().any(|s| s == name) {
+            subscribed.push(name.to_string());
+        }
+    }
+    Ok(subscribed)
+}
+
+pub async fn get_webhooks(app_state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req) {
+        return Ok(response);
+    }
+
+    match database::get_webhooks(&app_state.db).await {
+        Ok(webhooks) => Ok(HttpResponse::Ok().json(webhooks)),
+        Err(e) => {
+            eprintln!("❌ Failed to fetch webhooks: {}", e);
+            Ok(HttpResponse::InternalServerError().json("Failed to fetch webhooks"))
+        }
+    }
+}
+
+pub async fn create_webhook(
+    app_state: web::Data<AppState>,
+    body: web::Json<NewWebhook>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req) {
+        return Ok(response);
+    }
+    let webhook = body.into_inner();
+
+    if let Some(message) = webhook_url_error(&webhook.url) {
+        return Ok(HttpResponse::BadRequest().json(message));
+    }
+    let subscribed = match webhook_events(&webhook.events) {
+        Ok(subscribed) => subscribed,
+        Err(message) => return Ok(HttpResponse::BadRequest().json(message)),
+    };
+    let secret = match webhook.secret.as_deref().map(str::trim) {
+        Some("") => return Ok(HttpResponse::BadRequest().json("secret cannot be empty")),
+        Some(secret) => secret.to_string(),
+        None => uuid::Uuid::new_v4().simple().to_string(),
+    };
+
+    match database::create_webhook(
+        &app_state.db, webhook.url.trim(), &secret, &subscribed, webhook.active.unwrap_or(true),
+    ).await {
+        Ok(created) => {
+            println!("🪝 Created webhook #{} for {}: {}", created.id, created.url, created.events.join(", "));
+            Ok(HttpResponse::Created().json(created))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to create webhook: {}", e);
+            Ok(HttpResponse::InternalServerError().json("Failed to create webhook"))
+        }
+    }
+}
+
+pub async fn get_webhook(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req) {
+        return Ok(response);
+    }
+    let webhook_id = path.into_inner();
+
+    match database::get_webhook(&app_state.db, webhook_id).await {
+        Ok(Some(webhook)) => Ok(HttpResponse::Ok().json(webhook)),
+        Ok(None) => Ok(HttpResponse::NotFound().json("Webhook not found")),
+        Err(e) => {
+            eprintln!("❌ Database error retrieving webhook #{}: {}", webhook_id, e);
+            Ok(HttpResponse::InternalServerError().json("Database error"))
+        }
+    }
+}
+
+pub async fn update_webhook(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    body: web::Json<UpdateWebhook>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req) {
+        return Ok(response);
+    }
+    let webhook_id = path.into_inner();
+    let mut changes = body.into_inner();
+
+    if let Some(message) = changes.url.as_deref().and_then(webhook_url_error) {
+        return Ok(HttpResponse::BadRequest().json(message));
+    }
+    if let Some(events) = &changes.events {
+        match webhook_events(events) {
+            Ok(subscribed) => changes.events = Some(subscribed),
+            Err(message) => return Ok(HttpResponse::BadRequest().json(message)),
+        }
+    }
+    if changes.secret.as_deref().is_some_and(|secret| secret.trim().is_empty()) {
+        return Ok(HttpResponse::BadRequest().json("secret cannot be empty"));
+    }
+
+    match database::update_webhook(&app_state.db, webhook_id, &changes).await {
+        Ok(Some(webhook)) => {
+            println!("✏️  Updated webhook #{}", webhook_id);
+            if webhook.active {
+                // Deliveries held while it was inactive can go now.
+                app_state.webhook_wakeup.notify_one();
+            }
+            Ok(HttpResponse::Ok().json(webhook))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json("Webhook not found")),
+        Err(e) => {
+            eprintln!("❌ Failed to update webhook #{}: {}", webhook_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to update webhook"))
+        }
+    }
+}
+
+pub async fn delete_webhook(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req) {
+        return Ok(response);
+    }
+    let webhook_id = path.into_inner();
+
+    match database::delete_webhook(&app_state.db, webhook_id).await {
+        Ok(true) => {
+            println!("🗑️  Deleted webhook #{}", webhook_id);
+            Ok(HttpResponse::Ok().json("Webhook deleted successfully"))
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json("Webhook not found")),
+        Err(e) => {
+            eprintln!("❌ Failed to delete webhook #{}: {}", webhook_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to delete webhook"))
+        }
+    }
+}
+
+const DEFAULT_DELIVERY_LIMIT: i64 = 50;
+const MAX_DELIVERY_LIMIT: i64 = 500;
+
+// The delivery log, newest first; `?status=pending|delivered|failed`.
+pub async fn get_webhook_deliveries(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+    query: web::Query<WebhookDeliveryQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req) {
+        return Ok(response);
+    }
+    let webhook_id = path.into_inner();
+
+    let status = query.status.as_deref().map(str::trim).filter(|s| !s.is_empty());
+    if let Some(status) = status
+        && !["pending", "delivered", "failed"].contains(&status)
+    {
+        return Ok(HttpResponse::BadRequest().json("status must be pending, delivered or failed"));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_DELIVERY_LIMIT).clamp(1, MAX_DELIVERY_LIMIT);
+
+    match database::get_webhook(&app_state.db, webhook_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Ok(HttpResponse::NotFound().json("Webhook not found")),
+        Err(e) => {
+            eprintln!("❌ Database error retrieving webhook #{}: {}", webhook_id, e);
+            return Ok(HttpResponse::InternalServerError().json("Database error"));
+        }
+    }
+    match database::get_webhook_deliveries(&app_state.db, webhook_id, status, limit).await {
+        Ok(deliveries) => Ok(HttpResponse::Ok().json(deliveries)),
+        Err(e) => {
+            eprintln!("❌ Failed to fetch deliveries for webhook #{}: {}", webhook_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to fetch deliveries"))
+        }
+    }
+}
+
+// One delivery with the payload that was (or will be) sent.
+pub async fn get_webhook_delivery(
+    app_state: web::Data<AppState>,
+    path: web::Path<(i64, i64)>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req) {
+        return Ok(response);
+    }
+    let (webhook_id, delivery_id) = path.into_inner();
+
+    match database::get_webhook_delivery(&app_state.db, webhook_id, delivery_id).await {
+        Ok(Some(delivery)) => {
+            let mut body = serde_json::to_value(&delivery).unwrap_or_default();
+            body["payload"] = serde_json::from_str(&delivery.payload)
+                .unwrap_or(serde_json::Value::String(delivery.payload.clone()));
+            Ok(HttpResponse::Ok().json(body))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json("Delivery not found")),
+        Err(e) => {
+            eprintln!("❌ Database error retrieving delivery #{}: {}", delivery_id, e);
+            Ok(HttpResponse::InternalServerError().json("Database error"))
+        }
+    }
+}
+
+// Sends a delivery again as a new delivery with the same payload; the
+// original stays in the log.
+pub async fn redeliver_webhook(
+    app_state: web::Data<AppState>,
+    path: web::Path<(i64, i64)>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req) {
+        return Ok(response);
+    }
+    let (webhook_id, delivery_id) = path.into_inner();
+
+    match database::redeliver_webhook(&app_state.db, webhook_id, delivery_id).await {
+        Ok(Some(delivery)) => {
+            println!("🔁 Queued delivery #{} again as #{}", delivery_id, delivery.id);
+            app_state.webhook_wakeup.notify_one();
+            Ok(HttpResponse::Accepted().json(delivery))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json("Delivery not found")),
+        Err(e) => {
+            eprintln!("❌ Failed to redeliver delivery #{}: {}", delivery_id, e);
+            Ok(HttpResponse::InternalServerError().json("Failed to redeliver"))
+        }
+    }
+}
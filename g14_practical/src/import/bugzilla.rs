@@ -0,0 +1,321 @@
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use crate::models::{validate_bug_fields, ExternalBug, ExternalComment, NewLabel};
+use super::mapping::{Defaults, Mapper};
+use super::TrackerImport;
+
+// Bugzilla `show_bug.cgi?ctype=xml` files, holding one or more `<bug>`
+// elements. The first `<long_desc>` is the description and the rest are
+// comments; keywords become labels. Private (`isprivate="1"`) comments
+// are left out, since comments here are public. Bugs are keyed as
+// `bugzilla:host#id`, comments as `bugzilla:host/comments/id`.
+
+pub const DEFAULTS: Defaults = Defaults {
+    severities: &[
+        ("blocker", "Critical"),
+        ("critical", "Critical"),
+        ("major", "High"),
+        ("normal", "Medium"),
+        ("minor", "Low"),
+        ("trivial", "Low"),
+        ("enhancement", "Low"),
+    ],
+    statuses: &[
+        ("UNCONFIRMED", "Open"),
+        ("NEW", "Open"),
+        ("CONFIRMED", "Open"),
+        ("REOPENED", "Open"),
+        ("ASSIGNED", "In Progress"),
+        ("IN_PROGRESS", "In Progress"),
+        ("RESOLVED", "Resolved"),
+        ("VERIFIED", "Resolved"),
+        ("CLOSED", "Closed"),
+    ],
+};
+
+#[derive(Default)]
+struct RawBug {
+    bug_id: String,
+    // Set for ids Bugzilla couldn't export, e.g. `NotFound`.
+    error: Option<String>,
+    short_desc: String,
+    bug_status: String,
+    bug_severity: String,
+    keywords: String,
+    reporter: Person,
+    assigned_to: Person,
+    long_descs: Vec<RawComment>,
+}
+
+#[derive(Default)]
+struct RawComment {
+    commentid: String,
+    private: bool,
+    who: Person,
+    bug_when: String,
+    thetext: String,
+}
+
+#[derive(Default)]
+struct Person {
+    login: String,
+    // The `name` attribute: the person's real name, when they set one.
+    name: String,
+}
+
+impl Person {
+    fn names(&self) -> [&str; 2] {
+        [&self.login, &self.name]
+    }
+}
+
+// Converts one XML file. `source` names the Bugzilla instance in external
+// refs and defaults to the host in the file's `urlbase`.
+pub fn convert(
+    xml: &str,
+    source: Option<&str>,
+    mapper: &mut Mapper,
+    project_id: Option<i64>,
+) -> Result<TrackerImport, String> {
+    let (urlbase, bugs) = parse(xml)?;
+    let source = match source {
+        Some(source) => source.to_string(),
+        None => urlbase.as_deref().and_then(host).ok_or_else(|| {
+            "The file has no urlbase to name the Bugzilla instance; pass --source name".to_string()
+        })?,
+    };
+
+    let mut import = TrackerImport::default();
+    for bug in bugs {
+        let key = format!("{}#{}", source, bug.bug_id.trim());
+        if let Some(error) = &bug.error {
+            import.skipped.push(format!("{}: Bugzilla reported {}", key, error));
+            continue;
+        }
+        let severity = mapper.severity(&bug.bug_severity);
+        if let Some(error) = validate_bug_fields(&bug.short_desc, Some(severity)).first() {
+            import.skipped.push(format!("{}: {}", key, error.message));
+            continue;
+        }
+
+        let mut long_descs = bug.long_descs.into_iter();
+        let first = long_descs.next();
+        let private_description = first.as_ref().is_some_and(|c| c.private);
+        let description = first.filter(|c| !c.private).map(|c| c.thetext).unwrap_or_default();
+        let (private, public): (Vec<_>, Vec<_>) = long_descs.enumerate().partition(|(_, c)| c.private);
+        if private_description || !private.is_empty() {
+            import.skipped.push(format!(
+                "{}: {} private comment(s)", key, private.len() + usize::from(private_description)
+            ));
+        }
+        let comments = public.into_iter()
+            .map(|(index, comment)| ExternalComment {
+                // Bugzilla before 4.0 doesn't export comment ids.
+                external_ref: match comment.commentid.trim() {
+                    "" => format!("bugzilla:{}/comments/{}", key, index + 1),
+                    id => format!("bugzilla:{}/comments/{}", source, id),
+                },
+                author: mapper.author(&comment.who.names()),
+                body: comment.thetext,
+                created_at: timestamp(&comment.bug_when),
+            })
+            .collect();
+        let labels = bug.keywords.split(',')
+            .map(str::trim)
+            .filter(|k| !k.is_empty())
+            .map(|name| NewLabel { name: name.to_string(), color: None, description: None })
+            .collect();
+
+        import.bugs.push(ExternalBug {
+            external_ref: format!("bugzilla:{}", key),
+            title: bug.short_desc.trim().to_string(),
+            description,
+            reported_by: mapper.reporter(&bug.reporter.names()),
+            severity: severity.to_string(),
+            status: mapper.status(&bug.bug_status).to_string(),
+            developer_id: mapper.assignee(&bug.assigned_to.names()),
+            project_id,
+            labels,
+            comments,
+        });
+    }
+    Ok(import)
+}
+
+// The `urlbase` attribute and the `<bug>` elements, keeping only the
+// fields the import uses.
+fn parse(xml: &str) -> Result<(Option<String>, Vec<RawBug>), String> {
+    let mut reader = Reader::from_str(xml);
+    let mut urlbase = None;
+    let mut bugs = Vec::new();
+    let mut bug: Option<RawBug> = None;
+    let mut comment: Option<RawComment> = None;
+    // Open elements, and the text of the innermost one.
+    let mut path: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut name_attribute = String::new();
+
+    loop {
+        let event = reader.read_event()
+            .map_err(|e| format!("Not a Bugzilla XML file (at byte {}): {}", reader.buffer_position(), e))?;
+        match event {
+            Event::Start(element) => {
+                let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+                match name.as_str() {
+                    "bugzilla" => urlbase = attribute(&element, "urlbase"),
+                    "bug" => bug = Some(RawBug { error: attribute(&element, "error"), ..RawBug::default() }),
+                    "long_desc" if bug.is_some() => {
+                        let private = attribute(&element, "isprivate").is_some_and(|v| v.trim() == "1");
+                        comment = Some(RawComment { private, ..RawComment::default() });
+                    }
+                    _ => {}
+                }
+                name_attribute = attribute(&element, "name").unwrap_or_default();
+                text.clear();
+                path.push(name);
+            }
+            Event::Empty(element) if element.name().as_ref() == b"bug" => {
+                // `<bug error="NotFound" />` from older versions.
+                bugs.push(RawBug { error: attribute(&element, "error"), ..RawBug::default() });
+            }
+            Event::Text(content) => {
+                let content = content.unescape().map_err(|e| format!("Bad text in Bugzilla XML: {}", e))?;
+                text.push_str(&content);
+            }
+            Event::CData(content) => text.push_str(&String::from_utf8_lossy(&content.into_inner())),
+            Event::End(_) => {
+                let name = path.pop().unwrap_or_default();
+                let parent = path.last().map(String::as_str);
+                let value = std::mem::take(&mut text);
+                if let (Some("long_desc"), Some(comment)) = (parent, comment.as_mut()) {
+                    match name.as_str() {
+                        "commentid" => comment.commentid = value,
+                        "who" => comment.who = Person { login: value, name: std::mem::take(&mut name_attribute) },
+                        "bug_when" => comment.bug_when = value,
+                        "thetext" => comment.thetext = value,
+                        _ => {}
+                    }
+                } else if let (Some("bug"), Some(bug)) = (parent, bug.as_mut()) {
+                    match name.as_str() {
+                        "bug_id" => bug.bug_id = value,
+                        "short_desc" => bug.short_desc = value,
+                        "bug_status" => bug.bug_status = value,
+                        "bug_severity" => bug.bug_severity = value,
+                        "keywords" => bug.keywords = value,
+                        "reporter" => bug.reporter = Person { login: value, name: std::mem::take(&mut name_attribute) },
+                        "assigned_to" => bug.assigned_to = Person { login: value, name: std::mem::take(&mut name_attribute) },
+                        "long_desc" => bug.long_descs.extend(comment.take()),
+                        _ => {}
+                    }
+                } else if name == "bug" {
+                    bugs.extend(bug.take());
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if bugs.is_empty() && urlbase.is_none() {
+        return Err("Not a Bugzilla XML file: no <bugzilla> or <bug> elements".to_string());
+    }
+    Ok((urlbase, bugs))
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Option<String> {
+    let attribute = element.try_get_attribute(name).ok()??;
+    Some(attribute.unescape_value().ok()?.into_owned())
+}
+
+// `https://bugs.example.org/bugzilla/` -> `bugs.example.org`
+fn host(urlbase: &str) -> Option<String> {
+    let rest = urlbase.split("://").nth(1).unwrap_or(urlbase);
+    Some(rest.split('/').next().filter(|h| !h.is_empty())?.to_string())
+}
+
+// Bugzilla writes `2021-03-04 10:15:00 +0000`, which SQLite can't read.
+// Named zones (`PST`) are dropped and the local time kept.
+fn timestamp(value: &str) -> Option<String> {
+    let value = value.trim();
+    chrono::DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S %z")
+        .map(|t| t.naive_utc().format("%Y-%m-%d %H:%M:%S").to_string())
+        .ok()
+        .or_else(|| value.get(..19).map(str::to_string))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::mapping::MappingFile;
+
+    const XML: &str = r#"<?xml version="1.0"?>
+<bugzilla urlbase="https://bugs.example.org/bugzilla/">
+  <bug>
+    <bug_id>101</bug_id>
+    <short_desc>Crash on save</short_desc>
+    <bug_status>ASSIGNED</bug_status>
+    <bug_severity>blocker</bug_severity>
+    <keywords>regression, ui</keywords>
+    <reporter name="Dana">dana@example.org</reporter>
+    <assigned_to name="Nobody">nobody@example.org</assigned_to>
+    <long_desc isprivate="0">
+      <commentid>1</commentid><who name="Dana">dana@example.org</who>
+      <bug_when>2021-03-04 10:15:00 +0100</bug_when><thetext>It crashes.</thetext>
+    </long_desc>
+    <long_desc isprivate="1">
+      <commentid>2</commentid><who>qa@example.org</who>
+      <bug_when>2021-03-04 11:00:00 +0000</bug_when><thetext>Customer data attached.</thetext>
+    </long_desc>
+    <long_desc>
+      <commentid>3</commentid><who>qa@example.org</who>
+      <bug_when>2021-03-05 09:00:00 PST</bug_when><thetext>Reproduced.</thetext>
+    </long_desc>
+  </bug>
+  <bug error="NotFound"><bug_id>102</bug_id></bug>
+</bugzilla>"#;
+
+    #[test]
+    fn converts_bugs_without_private_comments() {
+        let mut mapper = Mapper::new(&DEFAULTS, MappingFile::default(), &[], &[]);
+        let import = convert(XML, None, &mut mapper, Some(2)).unwrap();
+
+        assert_eq!(import.bugs.len(), 1);
+        let bug = &import.bugs[0];
+        assert_eq!(bug.external_ref, "bugzilla:bugs.example.org#101");
+        assert_eq!(bug.title, "Crash on save");
+        assert_eq!(bug.description, "It crashes.");
+        assert_eq!(bug.severity, "Critical");
+        assert_eq!(bug.status, "In Progress");
+        assert_eq!(bug.project_id, Some(2));
+        assert_eq!(bug.labels.iter().map(|l| l.name.as_str()).collect::<Vec<_>>(), ["regression", "ui"]);
+
+        assert_eq!(bug.comments.len(), 1);
+        let comment = &bug.comments[0];
+        assert_eq!(comment.external_ref, "bugzilla:bugs.example.org/comments/3");
+        assert_eq!(comment.body, "Reproduced.");
+        assert_eq!(comment.created_at.as_deref(), Some("2021-03-05 09:00:00"));
+
+        assert_eq!(import.skipped, [
+            "bugs.example.org#101: 1 private comment(s)",
+            "bugs.example.org#102: Bugzilla reported NotFound",
+        ]);
+    }
+
+    #[test]
+    fn a_private_description_is_left_empty() {
+        let xml = XML.replace(r#"<long_desc isprivate="0">"#, r#"<long_desc isprivate="1">"#);
+        let mut mapper = Mapper::new(&DEFAULTS, MappingFile::default(), &[], &[]);
+        let import = convert(&xml, Some("bz"), &mut mapper, None).unwrap();
+
+        assert_eq!(import.bugs[0].external_ref, "bugzilla:bz#101");
+        assert_eq!(import.bugs[0].description, "");
+        assert_eq!(import.skipped[0], "bz#101: 2 private comment(s)");
+    }
+
+    #[test]
+    fn timestamps_are_converted_to_utc() {
+        assert_eq!(timestamp("2021-03-04 10:15:00 +0100").as_deref(), Some("2021-03-04 09:15:00"));
+        assert_eq!(timestamp("2021-03-04 10:15:00 PST").as_deref(), Some("2021-03-04 10:15:00"));
+        assert_eq!(host("https://bugs.example.org/bugzilla/").as_deref(), Some("bugs.example.org"));
+    }
+}
@@ -0,0 +1,206 @@
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use crate::models::{
+    is_valid_color, validate_bug_fields, Developer, ExternalBug, ExternalComment, NewLabel, SEVERITIES,
+};
+
+// GitHub Issues exports: the JSON arrays the REST API returns for
+// `GET /repos/{owner}/{repo}/issues?state=all` and
+// `GET /repos/{owner}/{repo}/issues/comments`, saved to local files.
+// Bugs are keyed as `github:owner/repo#number`, comments as
+// `github:owner/repo/comments/id`.
+
+#[derive(Deserialize)]
+struct Issue {
+    number: i64,
+    title: String,
+    #[serde(default)]
+    body: Option<String>,
+    state: String,
+    #[serde(default)]
+    labels: Vec<IssueLabel>,
+    #[serde(default)]
+    assignee: Option<User>,
+    #[serde(default)]
+    assignees: Vec<User>,
+    #[serde(default)]
+    user: Option<User>,
+    // Present on pull requests, which the issues API lists too.
+    #[serde(default)]
+    pull_request: Option<serde_json::Value>,
+    #[serde(default)]
+    repository_url: Option<String>,
+    #[serde(default)]
+    html_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IssueLabel {
+    Full {
+        name: String,
+        #[serde(default)]
+        color: Option<String>,
+        #[serde(default)]
+        description: Option<String>,
+    },
+    Name(String),
+}
+
+#[derive(Deserialize)]
+struct User {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct IssueComment {
+    id: i64,
+    issue_url: String,
+    #[serde(default)]
+    user: Option<User>,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    created_at: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct GithubImport {
+    pub bugs: Vec<ExternalBug>,
+    pub skipped_pull_requests: usize,
+    // `owner/repo#number: reason` for issues that failed validation.
+    pub skipped: Vec<String>,
+    // Comments whose issue isn't in the issues file.
+    pub orphaned_comments: usize,
+    // Assignee logins with no active developer of the same name.
+    pub unmatched_assignees: BTreeSet<String>,
+}
+
+// Converts the export files. `repo` overrides the repository named in the
+// files; `project_id` is set on every bug.
+pub fn convert(
+    issues_json: &str,
+    comments_json: Option<&str>,
+    repo: Option<&str>,
+    developers: &[Developer],
+    project_id: Option<i64>,
+) -> Result<GithubImport, String> {
+    let issues: Vec<Issue> = serde_json::from_str(issues_json)
+        .map_err(|e| format!("Issues file is not a GitHub issues JSON array: {}", e))?;
+    let comments: Vec<IssueComment> = match comments_json {
+        Some(json) => serde_json::from_str(json)
+            .map_err(|e| format!("Comments file is not a GitHub comments JSON array: {}", e))?,
+        None => Vec::new(),
+    };
+
+    let mut comments_by_issue: HashMap<(String, i64), Vec<ExternalComment>> = HashMap::new();
+    let mut import = GithubImport::default();
+    for comment in comments {
+        let Some((comment_repo, number)) = issue_from_url(&comment.issue_url) else {
+            import.orphaned_comments += 1;
+            continue;
+        };
+        let comment_repo = repo.map(str::to_string).unwrap_or(comment_repo);
+        comments_by_issue.entry((comment_repo.clone(), number)).or_default().push(ExternalComment {
+            external_ref: format!("github:{}/comments/{}", comment_repo, comment.id),
+            author: comment.user.map(|u| u.login).unwrap_or_else(|| "ghost".to_string()),
+            body: comment.body.unwrap_or_default(),
+            created_at: comment.created_at,
+        });
+    }
+
+    for issue in issues {
+        if issue.pull_request.is_some() {
+            import.skipped_pull_requests += 1;
+            continue;
+        }
+        let issue_repo = match repo {
+            Some(repo) => repo.to_string(),
+            None => issue_repo(&issue).ok_or_else(|| format!(
+                "Can't tell which repository issue #{} belongs to; pass --repo owner/name", issue.number
+            ))?,
+        };
+        let key = format!("{}#{}", issue_repo, issue.number);
+
+        let labels: Vec<NewLabel> = issue.labels.into_iter()
+            .map(|label| match label {
+                IssueLabel::Full { name, color, description } => NewLabel {
+                    name,
+                    color: color.map(|c| format!("#{}", c)).filter(|c| is_valid_color(c)),
+                    description: description.filter(|d| !d.is_empty()),
+                },
+                IssueLabel::Name(name) => NewLabel { name, color: None, description: None },
+            })
+            .filter(|label| !label.name.trim().is_empty())
+            .collect();
+        let severity = severity_from_labels(&labels);
+
+        if let Some(error) = validate_bug_fields(&issue.title, Some(severity)).first() {
+            import.skipped.push(format!("{}: {}", key, error.message));
+            continue;
+        }
+
+        let logins: Vec<String> = issue.assignee.into_iter()
+            .chain(issue.assignees)
+            .map(|u| u.login)
+            .collect();
+        let developer_id = logins.iter().find_map(|login| {
+            developers.iter()
+                .find(|d| d.active && d.name.eq_ignore_ascii_case(login))
+                .map(|d| d.id)
+        });
+        if developer_id.is_none() {
+            import.unmatched_assignees.extend(logins);
+        }
+
+        let comments = comments_by_issue.remove(&(issue_repo, issue.number)).unwrap_or_default();
+        import.bugs.push(ExternalBug {
+            external_ref: format!("github:{}", key),
+            title: issue.title.trim().to_string(),
+            description: issue.body.unwrap_or_default(),
+            reported_by: issue.user.map(|u| u.login).unwrap_or_else(|| "ghost".to_string()),
+            severity: severity.to_string(),
+            status: if issue.state.eq_ignore_ascii_case("closed") { "Closed" } else { "Open" }.to_string(),
+            developer_id,
+            project_id,
+            labels,
+            comments,
+        });
+    }
+    import.orphaned_comments += comments_by_issue.values().map(Vec::len).sum::<usize>();
+
+    Ok(import)
+}
+
+// GitHub has no severity; a label naming one (optionally as
+// `severity: high` or `severity/high`) sets it, otherwise it's Medium.
+fn severity_from_labels(labels: &[NewLabel]) -> &'static str {
+    labels.iter()
+        .find_map(|label| {
+            let name = label.name.trim();
+            let name = name.get(..9)
+                .filter(|prefix| prefix.eq_ignore_ascii_case("severity:") || prefix.eq_ignore_ascii_case("severity/"))
+                .map_or(name, |_| &name[9..]);
+            SEVERITIES.iter().copied().find(|s| s.eq_ignore_ascii_case(name.trim()))
+        })
+        .unwrap_or("Medium")
+}
+
+fn issue_repo(issue: &Issue) -> Option<String> {
+    if let Some(url) = &issue.repository_url
+        && let Some(repo) = url.split("/repos/").nth(1)
+    {
+        return Some(repo.trim_end_matches('/').to_string());
+    }
+    let url = issue.html_url.as_deref()?;
+    let path = url.split("github.com/").nth(1)?;
+    let mut parts = path.split('/');
+    Some(format!("{}/{}", parts.next()?, parts.next()?))
+}
+
+// `https://api.github.com/repos/owner/repo/issues/12` -> (`owner/repo`, 12)
+fn issue_from_url(url: &str) -> Option<(String, i64)> {
+    let path = url.split("/repos/").nth(1)?;
+    let (repo, number) = path.rsplit_once("/issues/")?;
+    Some((repo.to_string(), number.trim_end_matches('/').parse().ok()?))
+}
@@ -0,0 +1,206 @@
+use chrono::NaiveDateTime;
+use crate::models::{validate_bug_fields, ExternalBug, ExternalComment, NewLabel};
+use super::mapping::{Defaults, Mapper};
+use super::TrackerImport;
+
+// Jira "Export Excel CSV (all fields)" files. Jira repeats a column once
+// per value for labels and comments, so columns are looked up by name
+// rather than read into a map. Severity comes from a `Severity` custom
+// field when the project has one, otherwise from Priority. Bugs are keyed
+// as `jira:KEY-123`. The CSV has no comment ids, so comments are keyed by
+// when and by whom they were written (`jira:KEY-123/comments/<time>/<author>`),
+// which still holds when earlier comments are deleted; only comments
+// without a readable date fall back to their position.
+
+pub const DEFAULTS: Defaults = Defaults {
+    severities: &[
+        ("blocker", "Critical"),
+        ("highest", "Critical"),
+        ("critical", "High"),
+        ("high", "High"),
+        ("major", "High"),
+        ("medium", "Medium"),
+        ("low", "Low"),
+        ("minor", "Low"),
+        ("lowest", "Low"),
+        ("trivial", "Low"),
+    ],
+    statuses: &[
+        ("open", "Open"),
+        ("to do", "Open"),
+        ("backlog", "Open"),
+        ("selected for development", "Open"),
+        ("reopened", "Open"),
+        ("in progress", "In Progress"),
+        ("in review", "In Progress"),
+        ("resolved", "Resolved"),
+        ("done", "Closed"),
+        ("closed", "Closed"),
+    ],
+};
+
+const SEVERITY_COLUMNS: [&str; 2] = ["Custom field (Severity)", "Severity"];
+
+// Jira writes dates in the exporting user's format; these are the defaults.
+const DATE_FORMATS: [&str; 3] = ["%d/%b/%y %I:%M %p", "%d/%b/%Y %I:%M %p", "%Y-%m-%d %H:%M"];
+
+pub fn convert(csv: &[u8], mapper: &mut Mapper, project_id: Option<i64>) -> Result<TrackerImport, String> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(csv);
+    let headers: Vec<String> = reader.headers()
+        .map_err(|e| format!("Could not read the CSV header: {}", e))?
+        .iter()
+        .map(|h| h.trim().to_string())
+        .collect();
+    let column = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let columns = |name: &str| -> Vec<usize> {
+        headers.iter().enumerate().filter(|(_, h)| h.eq_ignore_ascii_case(name)).map(|(i, _)| i).collect()
+    };
+    let (Some(key_column), Some(summary_column)) = (column("Issue key"), column("Summary")) else {
+        return Err("Not a Jira CSV export: it needs 'Issue key' and 'Summary' columns".to_string());
+    };
+    let severity_columns: Vec<usize> = SEVERITY_COLUMNS.iter().filter_map(|name| column(name)).collect();
+    let label_columns = columns("Labels");
+    let comment_columns = columns("Comment");
+
+    let mut import = TrackerImport::default();
+    for (index, record) in reader.records().enumerate() {
+        // The header is line 1.
+        let line = index + 2;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                import.skipped.push(format!("line {}: unreadable row: {}", line, e));
+                continue;
+            }
+        };
+        let field = |column: Option<usize>| column.and_then(|c| record.get(c)).unwrap_or("").trim();
+
+        let key = field(Some(key_column));
+        if key.is_empty() {
+            import.skipped.push(format!("line {}: no Issue key", line));
+            continue;
+        }
+        let title = field(Some(summary_column));
+        let severity = severity_columns.iter()
+            .map(|c| field(Some(*c)))
+            .find(|value| !value.is_empty())
+            .unwrap_or_else(|| field(column("Priority")));
+        let severity = mapper.severity(severity);
+        if let Some(error) = validate_bug_fields(title, Some(severity)).first() {
+            import.skipped.push(format!("{}: {}", key, error.message));
+            continue;
+        }
+
+        let labels = label_columns.iter()
+            .map(|c| field(Some(*c)))
+            .filter(|name| !name.is_empty())
+            .map(|name| NewLabel { name: name.to_string(), color: None, description: None })
+            .collect();
+        let mut seen_refs: Vec<String> = Vec::new();
+        let comments = comment_columns.iter()
+            .map(|c| field(Some(*c)))
+            .filter(|value| !value.is_empty())
+            .enumerate()
+            .map(|(n, value)| {
+                // `17/Jan/23 10:15 AM;author;body`, where the body may
+                // itself contain semicolons.
+                let mut parts = value.splitn(3, ';');
+                let (created_at, author, body) = match (parts.next(), parts.next(), parts.next()) {
+                    (Some(date), Some(author), Some(body)) if timestamp(date).is_some() => (timestamp(date), author, body),
+                    _ => (None, "", value),
+                };
+                let base_ref = match &created_at {
+                    Some(created_at) => format!("jira:{}/comments/{}/{}", key, created_at.replace(' ', "T"), author.trim()),
+                    None => format!("jira:{}/comments/{}", key, n + 1),
+                };
+                // Dates are to the minute, so one author can repeat one.
+                let repeats = seen_refs.iter().filter(|r| **r == base_ref).count();
+                let external_ref = match repeats {
+                    0 => base_ref.clone(),
+                    _ => format!("{}#{}", base_ref, repeats + 1),
+                };
+                seen_refs.push(base_ref);
+                ExternalComment {
+                    external_ref,
+                    author: mapper.author(&[author]),
+                    body: body.trim().to_string(),
+                    created_at,
+                }
+            })
+            .collect();
+
+        import.bugs.push(ExternalBug {
+            external_ref: format!("jira:{}", key),
+            title: title.to_string(),
+            description: field(column("Description")).to_string(),
+            reported_by: mapper.reporter(&[field(column("Reporter")), field(column("Reporter Id"))]),
+            severity: severity.to_string(),
+            status: mapper.status(field(column("Status"))).to_string(),
+            developer_id: mapper.assignee(&[field(column("Assignee")), field(column("Assignee Id"))]),
+            project_id,
+            labels,
+            comments,
+        });
+    }
+    Ok(import)
+}
+
+fn timestamp(value: &str) -> Option<String> {
+    DATE_FORMATS.iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value.trim(), format).ok())
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::mapping::MappingFile;
+
+    const CSV: &str = "Issue key,Summary,Priority,Custom field (Severity),Status,Labels,Labels,Description,Comment,Comment,Comment\n\
+        WEB-1,Login fails,Low,Blocker,In Review,auth,,Steps...,\"17/Jan/23 10:15 AM;alice;First; with a semicolon\",\
+        17/Jan/23 10:15 AM;alice;Second,not a dated comment\n\
+        ,No key,High,,Open,,,,,,\n";
+
+    #[test]
+    fn converts_issues_and_keys_comments_by_date_and_author() {
+        let mut mapper = Mapper::new(&DEFAULTS, MappingFile::default(), &[], &[]);
+        let import = convert(CSV.as_bytes(), &mut mapper, None).unwrap();
+
+        assert_eq!(import.bugs.len(), 1);
+        let bug = &import.bugs[0];
+        assert_eq!(bug.external_ref, "jira:WEB-1");
+        assert_eq!(bug.severity, "Critical");
+        assert_eq!(bug.status, "In Progress");
+        assert_eq!(bug.labels.len(), 1);
+
+        let refs: Vec<&str> = bug.comments.iter().map(|c| c.external_ref.as_str()).collect();
+        assert_eq!(refs, [
+            "jira:WEB-1/comments/2023-01-17T10:15:00/alice",
+            "jira:WEB-1/comments/2023-01-17T10:15:00/alice#2",
+            "jira:WEB-1/comments/3",
+        ]);
+        assert_eq!(bug.comments[0].body, "First; with a semicolon");
+        assert_eq!(bug.comments[0].author, "alice");
+        assert_eq!(bug.comments[2].body, "not a dated comment");
+        assert_eq!(import.skipped, ["line 3: no Issue key"]);
+    }
+
+    #[test]
+    fn deleting_an_earlier_comment_keeps_the_other_refs() {
+        let csv = "Issue key,Summary,Comment,Comment\n\
+            WEB-2,Slow,01/Feb/23 9:00 AM;bob;One,02/Feb/23 9:00 AM;carol;Two\n";
+        let trimmed = "Issue key,Summary,Comment\n\
+            WEB-2,Slow,02/Feb/23 9:00 AM;carol;Two\n";
+        let mut mapper = Mapper::new(&DEFAULTS, MappingFile::default(), &[], &[]);
+        let before = convert(csv.as_bytes(), &mut mapper, None).unwrap();
+        let after = convert(trimmed.as_bytes(), &mut mapper, None).unwrap();
+
+        assert_eq!(before.bugs[0].comments[1].external_ref, after.bugs[0].comments[0].external_ref);
+    }
+
+    #[test]
+    fn files_without_the_key_columns_are_refused() {
+        let mut mapper = Mapper::new(&DEFAULTS, MappingFile::default(), &[], &[]);
+        assert!(convert(b"Title,Status\nx,Open\n", &mut mapper, None).is_err());
+    }
+}
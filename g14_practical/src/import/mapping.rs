@@ -0,0 +1,232 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use crate::models::{canonical_severity, canonical_status, Developer, SEVERITIES, STATUSES};
+
+// Maps another tracker's severities, statuses and people onto ours. Each
+// importer has built-in defaults; a JSON mapping file adds to or overrides
+// them:
+//
+//   {
+//     "severity": {"blocker": "Critical", "normal": "Medium"},
+//     "status": {"NEEDINFO": "Open"},
+//     "people": {"jsmith@example.com": "Jane Smith"}
+//   }
+//
+// Keys are matched case-insensitively. `people` maps logins, emails or
+// display names to a developer name (assignees) or username (reporters).
+// Anything left unmapped falls back to Medium / Open / unassigned and is
+// counted in `Unmapped`, so a dry run shows what the file still needs.
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MappingFile {
+    #[serde(default)]
+    pub severity: HashMap<String, String>,
+    #[serde(default)]
+    pub status: HashMap<String, String>,
+    #[serde(default)]
+    pub people: HashMap<String, String>,
+}
+
+impl MappingFile {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let file: MappingFile = serde_json::from_str(json)
+            .map_err(|e| format!("Mapping file is not valid: {}", e))?;
+        if let Some((from, to)) = file.severity.iter().find(|(_, to)| canonical_severity(to).is_none()) {
+            return Err(format!(
+                "Mapping file maps severity '{}' to '{}'; expected one of: {}", from, to, SEVERITIES.join(", ")
+            ));
+        }
+        if let Some((from, to)) = file.status.iter().find(|(_, to)| canonical_status(to).is_none()) {
+            return Err(format!(
+                "Mapping file maps status '{}' to '{}'; expected one of: {}", from, to, STATUSES.join(", ")
+            ));
+        }
+        Ok(file)
+    }
+}
+
+// An importer's built-in `(their value, ours)` pairs.
+pub struct Defaults {
+    pub severities: &'static [(&'static str, &'static str)],
+    pub statuses: &'static [(&'static str, &'static str)],
+}
+
+// Source values that had no mapping, with how many bugs used each.
+#[derive(Debug, Default)]
+pub struct Unmapped {
+    pub severities: BTreeMap<String, usize>,
+    pub statuses: BTreeMap<String, usize>,
+    // Assignees with no active developer of the mapped name; left unassigned.
+    pub assignees: BTreeMap<String, usize>,
+    // Reporters with no user account of the mapped name; kept as written.
+    pub reporters: BTreeMap<String, usize>,
+}
+
+impl Unmapped {
+    pub fn is_empty(&self) -> bool {
+        self.severities.is_empty() && self.statuses.is_empty()
+            && self.assignees.is_empty() && self.reporters.is_empty()
+    }
+}
+
+pub struct Mapper<'a> {
+    severities: HashMap<String, &'static str>,
+    statuses: HashMap<String, &'static str>,
+    people: HashMap<String, String>,
+    developers: &'a [Developer],
+    usernames: &'a [String],
+    pub unmapped: Unmapped,
+}
+
+impl<'a> Mapper<'a> {
+    pub fn new(defaults: &Defaults, file: MappingFile, developers: &'a [Developer], usernames: &'a [String]) -> Self {
+        // `parse` has checked the file's values, so the lookups can't miss.
+        let severities = defaults.severities.iter()
+            .map(|(from, to)| (from.to_lowercase(), *to))
+            .chain(file.severity.iter().filter_map(|(from, to)| Some((from.trim().to_lowercase(), canonical_severity(to)?))))
+            .collect();
+        let statuses = defaults.statuses.iter()
+            .map(|(from, to)| (from.to_lowercase(), *to))
+            .chain(file.status.iter().filter_map(|(from, to)| Some((from.trim().to_lowercase(), canonical_status(to)?))))
+            .collect();
+        let people = file.people.into_iter()
+            .map(|(from, to)| (from.trim().to_lowercase(), to.trim().to_string()))
+            .collect();
+
+        Self { severities, statuses, people, developers, usernames, unmapped: Unmapped::default() }
+    }
+
+    pub fn severity(&mut self, value: &str) -> &'static str {
+        let value = value.trim();
+        if value.is_empty() {
+            return "Medium";
+        }
+        if let Some(severity) = self.severities.get(&value.to_lowercase()).copied().or_else(|| canonical_severity(value)) {
+            return severity;
+        }
+        *self.unmapped.severities.entry(value.to_string()).or_default() += 1;
+        "Medium"
+    }
+
+    pub fn status(&mut self, value: &str) -> &'static str {
+        let value = value.trim();
+        if value.is_empty() {
+            return "Open";
+        }
+        if let Some(status) = self.statuses.get(&value.to_lowercase()).copied().or_else(|| canonical_status(value)) {
+            return status;
+        }
+        *self.unmapped.statuses.entry(value.to_string()).or_default() += 1;
+        "Open"
+    }
+
+    // The active developer a person maps to, trying the mapping file and
+    // then the developer names themselves.
+    pub fn assignee(&mut self, names: &[&str]) -> Option<i64> {
+        let names = present(names);
+        let first = *names.first()?;
+        let developer = self.mapped(&names).into_iter()
+            .chain(names.iter().map(|name| name.to_string()))
+            .find_map(|name| self.developers.iter().find(|d| d.active && d.name.eq_ignore_ascii_case(&name)));
+        if developer.is_none() {
+            *self.unmapped.assignees.entry(first.to_string()).or_default() += 1;
+        }
+        developer.map(|d| d.id)
+    }
+
+    // `reported_by` for a person: their mapped name, a username matching
+    // one of their names, or failing that the first name as written.
+    pub fn reporter(&mut self, names: &[&str]) -> String {
+        let names = present(names);
+        let Some(first) = names.first().copied() else {
+            return "unknown".to_string();
+        };
+        if let Some(name) = self.mapped(&names) {
+            return name;
+        }
+        if let Some(username) = names.iter().find_map(|name| self.usernames.iter().find(|u| u.eq_ignore_ascii_case(name))) {
+            return username.clone();
+        }
+        *self.unmapped.reporters.entry(first.to_string()).or_default() += 1;
+        first.to_string()
+    }
+
+    // Comment authors are mapped when the file names them, but otherwise
+    // kept as written without being reported.
+    pub fn author(&self, names: &[&str]) -> String {
+        let names = present(names);
+        self.mapped(&names)
+            .or_else(|| names.first().map(|name| name.to_string()))
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+
+    fn mapped(&self, names: &[&str]) -> Option<String> {
+        names.iter().find_map(|name| self.people.get(&name.to_lowercase()).cloned())
+    }
+}
+
+fn present<'n>(names: &[&'n str]) -> Vec<&'n str> {
+    names.iter().map(|name| name.trim()).filter(|name| !name.is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULTS: Defaults = Defaults {
+        severities: &[("blocker", "Critical"), ("normal", "Medium")],
+        statuses: &[("NEW", "Open"), ("RESOLVED", "Resolved")],
+    };
+
+    fn developer(id: i64, name: &str, active: bool) -> Developer {
+        Developer { id, name: name.to_string(), active, max_open_bugs: None, skills: Vec::new(), components: Vec::new() }
+    }
+
+    #[test]
+    fn parse_rejects_values_we_do_not_have() {
+        assert!(MappingFile::parse(r#"{"severity": {"S1": "critical"}}"#).is_ok());
+        assert!(MappingFile::parse(r#"{"severity": {"S1": "Urgent"}}"#).unwrap_err().contains("'S1' to 'Urgent'"));
+        assert!(MappingFile::parse(r#"{"status": {"NEEDINFO": "Waiting"}}"#).is_err());
+        assert!(MappingFile::parse(r#"{"priority": {}}"#).is_err());
+    }
+
+    #[test]
+    fn the_file_overrides_defaults_and_unmapped_values_are_counted() {
+        let file = MappingFile::parse(r#"{"severity": {"Normal": "Low", "S1": "Critical"}, "status": {"needinfo": "open"}}"#).unwrap();
+        let mut mapper = Mapper::new(&DEFAULTS, file, &[], &[]);
+
+        assert_eq!(mapper.severity("BLOCKER"), "Critical");
+        assert_eq!(mapper.severity("normal"), "Low");
+        assert_eq!(mapper.severity(" s1 "), "Critical");
+        assert_eq!(mapper.severity("high"), "High");
+        assert_eq!(mapper.severity(""), "Medium");
+        assert_eq!(mapper.severity("P2"), "Medium");
+        assert_eq!(mapper.severity("P2"), "Medium");
+        assert_eq!(mapper.status("NeedInfo"), "Open");
+        assert_eq!(mapper.status("resolved"), "Resolved");
+        assert_eq!(mapper.status("WONTFIX"), "Open");
+
+        assert_eq!(mapper.unmapped.severities.get("P2"), Some(&2));
+        assert_eq!(mapper.unmapped.statuses.get("WONTFIX"), Some(&1));
+    }
+
+    #[test]
+    fn people_map_to_active_developers_and_users() {
+        let developers = [developer(1, "Jane Smith", true), developer(2, "Old Timer", false)];
+        let usernames = ["carol".to_string()];
+        let file = MappingFile::parse(r#"{"people": {"JSmith@Example.org": "Jane Smith"}}"#).unwrap();
+        let mut mapper = Mapper::new(&DEFAULTS, file, &developers, &usernames);
+
+        assert_eq!(mapper.assignee(&["jsmith@example.org", ""]), Some(1));
+        assert_eq!(mapper.assignee(&["nobody", "jane smith"]), Some(1));
+        assert_eq!(mapper.assignee(&["Old Timer"]), None);
+        assert_eq!(mapper.reporter(&["", "Carol"]), "carol");
+        assert_eq!(mapper.reporter(&["dave@example.org"]), "dave@example.org");
+        assert_eq!(mapper.author(&["jsmith@example.org"]), "Jane Smith");
+        assert_eq!(mapper.author(&[" "]), "unknown");
+
+        assert_eq!(mapper.unmapped.assignees.get("Old Timer"), Some(&1));
+        assert_eq!(mapper.unmapped.reporters.get("dave@example.org"), Some(&1));
+    }
+}
@@ -0,0 +1,221 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use crate::models::{ExternalBug, NewBug};
+
+pub mod bugzilla;
+pub mod github;
+pub mod jira;
+pub mod mapping;
+
+// Bulk bug import from spreadsheets (CSV) or scripts (NDJSON). Parsing only
+// turns rows into `NewBug`s; validation and inserts happen in the handler
+// so imported bugs follow the same rules as `POST /bugs/new`.
+//
+// Exports from other trackers are converted by the submodules into
+// `ExternalBug`s and saved from the command line (see `cli.rs`).
+
+pub const FIELDS: [&str; 6] = ["title", "description", "reported_by", "severity", "project_id", "component"];
+
+// Large enough for several thousand legacy bugs.
+pub const MAX_IMPORT_BYTES: usize = 20 * 1024 * 1024;
+
+// Valid rows are inserted this many to a transaction.
+pub const BATCH_SIZE: usize = 500;
+
+// What the Bugzilla and Jira converters produce; what their values mapped
+// to is reported by the `mapping::Mapper` they were given.
+#[derive(Debug, Default)]
+pub struct TrackerImport {
+    pub bugs: Vec<ExternalBug>,
+    // `key: reason` for bugs that couldn't be imported.
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    Csv,
+    Ndjson,
+}
+
+impl ImportFormat {
+    // An explicit `format` wins; otherwise the request's content type
+    // decides, defaulting to CSV.
+    pub fn parse(format: Option<&str>, content_type: &str) -> Option<Self> {
+        match format.map(str::trim).map(str::to_ascii_lowercase).as_deref() {
+            Some("csv") => Some(ImportFormat::Csv),
+            Some("ndjson") => Some(ImportFormat::Ndjson),
+            Some("") | None if content_type.contains("ndjson") => Some(ImportFormat::Ndjson),
+            Some("") | None => Some(ImportFormat::Csv),
+            Some(_) => None,
+        }
+    }
+}
+
+// Parses `Source column:field` pairs. Source names are matched
+// case-insensitively; columns named after a field map to it unless mapped
+// elsewhere.
+pub fn parse_mapping(spec: Option<&str>) -> Result<Vec<(String, &'static str)>, String> {
+    let mut mapping = Vec::new();
+    for pair in spec.unwrap_or("").split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let Some((source, field)) = pair.rsplit_once(':') else {
+            return Err(format!("Mapping '{}' should look like 'Source column:field'", pair));
+        };
+        let Some(field) = FIELDS.iter().copied().find(|f| f.eq_ignore_ascii_case(field.trim())) else {
+            return Err(format!("Unknown field '{}'; expected one of: {}", field.trim(), FIELDS.join(", ")));
+        };
+        mapping.push((source.trim().to_lowercase(), field));
+    }
+    Ok(mapping)
+}
+
+fn target_field(column: &str, mapping: &[(String, &'static str)]) -> Option<&'static str> {
+    let column = column.trim().to_lowercase();
+    if let Some((_, field)) = mapping.iter().find(|(source, _)| *source == column) {
+        return Some(field);
+    }
+    FIELDS.iter()
+        .copied()
+        .find(|f| *f == column && !mapping.iter().any(|(_, mapped)| mapped == f))
+}
+
+// One result per data row, in order: the bug, or why the row couldn't be
+// read. Fails as a whole only when the input as a whole is unusable.
+pub fn parse_rows(
+    format: ImportFormat,
+    body: &[u8],
+    mapping: &[(String, &'static str)],
+) -> Result<Vec<Result<NewBug, Vec<String>>>, String> {
+    match format {
+        ImportFormat::Csv => parse_csv(body, mapping),
+        ImportFormat::Ndjson => parse_ndjson(body, mapping),
+    }
+}
+
+fn parse_csv(
+    body: &[u8],
+    mapping: &[(String, &'static str)],
+) -> Result<Vec<Result<NewBug, Vec<String>>>, String> {
+    let mut reader = csv::Reader::from_reader(body);
+    let targets: Vec<Option<&'static str>> = reader.headers()
+        .map_err(|e| format!("Could not read the CSV header: {}", e))?
+        .iter()
+        .map(|column| target_field(column, mapping))
+        .collect();
+    if !targets.contains(&Some("title")) {
+        return Err("No column maps to title; name one 'title' or add it to mapping".to_string());
+    }
+
+    Ok(reader.records()
+        .map(|record| {
+            let record = record.map_err(|e| vec![format!("Unreadable row: {}", e)])?;
+            let fields = targets.iter()
+                .zip(record.iter())
+                .filter_map(|(target, value)| Some(((*target)?, value.to_string())))
+                .collect();
+            to_new_bug(fields)
+        })
+        .collect())
+}
+
+fn parse_ndjson(
+    body: &[u8],
+    mapping: &[(String, &'static str)],
+) -> Result<Vec<Result<NewBug, Vec<String>>>, String> {
+    let text = std::str::from_utf8(body).map_err(|_| "NDJSON input must be UTF-8".to_string())?;
+
+    Ok(text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let object: serde_json::Map<String, Value> = serde_json::from_str(line)
+                .map_err(|e| vec![format!("Invalid JSON object: {}", e)])?;
+            let mut fields = HashMap::new();
+            for (key, value) in object {
+                let Some(field) = target_field(&key, mapping) else {
+                    continue;
+                };
+                let value = match value {
+                    Value::Null => continue,
+                    Value::String(s) => s,
+                    Value::Number(n) => n.to_string(),
+                    Value::Bool(b) => b.to_string(),
+                    _ => return Err(vec![format!("{} must be a string or number", key)]),
+                };
+                fields.insert(field, value);
+            }
+            to_new_bug(fields)
+        })
+        .collect())
+}
+
+fn to_new_bug(mut fields: HashMap<&'static str, String>) -> Result<NewBug, Vec<String>> {
+    let mut take = |field: &str| fields.remove(field).map(|v| v.trim().to_string()).unwrap_or_default();
+    let project_id = match take("project_id") {
+        id if id.is_empty() => None,
+        id => Some(id.parse::<i64>().map_err(|_| vec![format!("project_id '{}' is not a number", id)])?),
+    };
+    let component = Some(take("component")).filter(|c| !c.is_empty());
+
+    Ok(NewBug {
+        title: take("title"),
+        description: take("description"),
+        reported_by: take("reported_by"),
+        severity: take("severity"),
+        project_id,
+        component,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_comes_from_the_parameter_or_the_content_type() {
+        assert_eq!(ImportFormat::parse(Some("NDJSON"), "text/csv"), Some(ImportFormat::Ndjson));
+        assert_eq!(ImportFormat::parse(None, "application/x-ndjson"), Some(ImportFormat::Ndjson));
+        assert_eq!(ImportFormat::parse(Some(""), "text/plain"), Some(ImportFormat::Csv));
+        assert_eq!(ImportFormat::parse(Some("xlsx"), "text/csv"), None);
+    }
+
+    #[test]
+    fn parse_mapping_checks_each_pair() {
+        let mapping = parse_mapping(Some(" Summary:title, Ticket: Priority : Severity ")).unwrap();
+        assert_eq!(mapping, [("summary".to_string(), "title"), ("ticket: priority".to_string(), "severity")]);
+        assert!(parse_mapping(Some("Summary")).unwrap_err().contains("Source column:field"));
+        assert!(parse_mapping(Some("Owner:developer_id")).unwrap_err().contains("Unknown field"));
+        assert!(parse_mapping(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_mapped_field_no_longer_takes_its_own_column() {
+        let mapping = parse_mapping(Some("Summary:title")).unwrap();
+        assert_eq!(target_field("SUMMARY", &mapping), Some("title"));
+        assert_eq!(target_field("title", &mapping), None);
+        assert_eq!(target_field("Severity", &mapping), Some("severity"));
+        assert_eq!(target_field("Notes", &mapping), None);
+    }
+
+    #[test]
+    fn csv_rows_become_bugs() {
+        let mapping = parse_mapping(Some("Summary:title,Priority:severity")).unwrap();
+        let body = "Summary,Priority,project_id,Notes\nCrash , high,2,ignored\nTypo,,abc,\n";
+        let rows = parse_rows(ImportFormat::Csv, body.as_bytes(), &mapping).unwrap();
+
+        let bug = rows[0].as_ref().unwrap();
+        assert_eq!((bug.title.as_str(), bug.severity.as_str(), bug.project_id), ("Crash", "high", Some(2)));
+        assert_eq!(rows[1].as_ref().unwrap_err(), &["project_id 'abc' is not a number"]);
+        assert!(parse_rows(ImportFormat::Csv, b"Name\nx\n", &[]).is_err());
+    }
+
+    #[test]
+    fn ndjson_rows_become_bugs() {
+        let body = "{\"title\": \"Crash\", \"project_id\": 3, \"component\": null, \"extra\": [1]}\n\n[1]\n{\"title\": {\"a\": 1}}\n";
+        let rows = parse_rows(ImportFormat::Ndjson, body.as_bytes(), &[]).unwrap();
+
+        assert_eq!(rows.len(), 3);
+        let bug = rows[0].as_ref().unwrap();
+        assert_eq!((bug.title.as_str(), bug.project_id, bug.component.as_deref()), ("Crash", Some(3), None));
+        assert!(rows[1].as_ref().unwrap_err()[0].starts_with("Invalid JSON object"));
+        assert_eq!(rows[2].as_ref().unwrap_err(), &["title must be a string or number"]);
+    }
+}
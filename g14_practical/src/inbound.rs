@@ -0,0 +1,332 @@
+use mail_parser::{MessageParser, MimeHeaders};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use crate::assignment;
+use crate::attachments::{self, UploadError};
+use crate::database;
+use crate::events::{self, EventKind};
+use crate::models::{validate_bug_fields, Attachment, Bug, Comment, ExternalBug};
+use crate::state::AppState;
+
+// Bugs from inbound email: raw RFC 822 messages, as piped in by an MTA,
+// handed to `POST /inbound/email` or `g14_practical receive-email`.
+//
+// A message whose subject carries a bug token, `[Bug #12]` (as in the
+// subject of notification emails, so replies keep it), becomes a comment
+// on that bug with quoted text and signatures cut off. Any other message
+// opens a new bug: the subject is the title and the body the description.
+// Attachments are stored on the bug either way. Each message is recorded
+// by its Message-ID, so a message delivered twice is only saved once.
+
+// Raw messages larger than this are refused before parsing.
+pub const MAX_MESSAGE_BYTES: usize = 25 * 1024 * 1024;
+
+const TITLE_LIMIT: usize = 200;
+
+// Reply and forward markers, including common non-English ones.
+const SUBJECT_PREFIXES: [&str; 5] = ["re:", "fw:", "fwd:", "aw:", "sv:"];
+
+pub struct InboundEmail {
+    // Lowercased.
+    pub sender: String,
+    pub subject: String,
+    pub body: String,
+    pub attachments: Vec<(String, Vec<u8>)>,
+    // `email:<message-id>`, or a hash of the message when it has none.
+    pub external_ref: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum InboundOutcome {
+    Created { bug: Box<Bug>, attachments: Vec<Attachment> },
+    Commented { comment: Comment, attachments: Vec<Attachment> },
+    // This message was received before; nothing was saved.
+    Duplicate { bug_id: i64 },
+}
+
+#[derive(Debug)]
+pub enum InboundError {
+    Unreadable(String),
+    UnknownSender(String),
+    // A reply with nothing left once quoted text is removed.
+    Empty,
+    Upload(UploadError),
+    Database(sqlx::Error),
+}
+
+impl InboundError {
+    pub fn message(&self) -> String {
+        match self {
+            InboundError::Unreadable(message) => message.clone(),
+            InboundError::UnknownSender(sender) => format!("Mail from {} is not accepted", sender),
+            InboundError::Empty => "The reply has no text or attachments".to_string(),
+            InboundError::Upload(e) => e.message(),
+            InboundError::Database(_) => "Failed to save the message".to_string(),
+        }
+    }
+}
+
+impl From<sqlx::Error> for InboundError {
+    fn from(e: sqlx::Error) -> Self {
+        InboundError::Database(e)
+    }
+}
+
+impl From<UploadError> for InboundError {
+    fn from(e: UploadError) -> Self {
+        InboundError::Upload(e)
+    }
+}
+
+pub fn parse(raw: &[u8]) -> Result<InboundEmail, InboundError> {
+    if raw.len() > MAX_MESSAGE_BYTES {
+        return Err(InboundError::Unreadable(format!(
+            "The message is larger than the {} byte limit", MAX_MESSAGE_BYTES
+        )));
+    }
+    let message = MessageParser::default().parse(raw)
+        .ok_or_else(|| InboundError::Unreadable("Not an email message".to_string()))?;
+
+    let sender = message.from()
+        .and_then(|from| from.first())
+        .and_then(|address| address.address())
+        .map(|address| address.trim().to_lowercase())
+        .filter(|address| !address.is_empty())
+        .ok_or_else(|| InboundError::Unreadable("The message has no From address".to_string()))?;
+    let subject = message.subject().unwrap_or("").trim().to_string();
+    let body = message.body_text(0)
+        .map(|body| body.replace("\r\n", "\n").trim().to_string())
+        .unwrap_or_default();
+    let attachments = message.attachments()
+        .map(|part| (part.attachment_name().unwrap_or("attachment").to_string(), part.contents().to_vec()))
+        .collect();
+    let external_ref = match message.message_id() {
+        Some(id) if !id.trim().is_empty() => format!("email:{}", id.trim()),
+        _ => format!("email:sha256:{}", hex::encode(Sha256::digest(raw))),
+    };
+
+    Ok(InboundEmail { sender, subject, body, attachments, external_ref })
+}
+
+// Saves one raw message as a new bug (in `project_id`, if given) or a
+// comment, publishing the same events as the API would.
+pub async fn receive(
+    app_state: &AppState,
+    raw: &[u8],
+    project_id: Option<i64>,
+) -> Result<InboundOutcome, InboundError> {
+    let email = parse(raw)?;
+    if app_state.config.inbound_reject_unknown && !known_sender(app_state, &email.sender).await? {
+        println!("🚫 Rejected mail from unknown sender {}", email.sender);
+        return Err(InboundError::UnknownSender(email.sender));
+    }
+    attachments::check_sizes(app_state, &email.attachments)?;
+
+    if let Some(bug_id) = bug_token(&email.subject)
+        && database::get_bug_by_id(&app_state.db, bug_id).await?.is_some()
+    {
+        return add_reply(app_state, bug_id, email).await;
+    }
+    open_bug(app_state, email, project_id).await
+}
+
+async fn add_reply(app_state: &AppState, bug_id: i64, email: InboundEmail) -> Result<InboundOutcome, InboundError> {
+    if let Some(comment) = database::get_comment_by_external_ref(&app_state.db, &email.external_ref).await? {
+        return Ok(InboundOutcome::Duplicate { bug_id: comment.bug_id });
+    }
+
+    let mut body = strip_quoted(&email.body);
+    if body.is_empty() {
+        if email.attachments.is_empty() {
+            return Err(InboundError::Empty);
+        }
+        let names: Vec<&str> = email.attachments.iter().map(|(name, _)| name.as_str()).collect();
+        body = format!("Attached {}", names.join(", "));
+    }
+
+    let comment = database::create_comment(&app_state.db, bug_id, &email.sender, &body, Some(&email.external_ref))
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+    // Without the comment, the MTA's retry saves everything again rather
+    // than being taken for a duplicate.
+    let stored = match store_attachments(app_state, bug_id, email.attachments).await {
+        Ok(stored) => stored,
+        Err(e) => {
+            database::delete_comment(&app_state.db, comment.id).await?;
+            return Err(e);
+        }
+    };
+    println!("📧 {} replied to bug #{} by email", email.sender, bug_id);
+    events::comment_created(app_state, &comment).await;
+    Ok(InboundOutcome::Commented { comment, attachments: stored })
+}
+
+async fn open_bug(
+    app_state: &AppState,
+    email: InboundEmail,
+    project_id: Option<i64>,
+) -> Result<InboundOutcome, InboundError> {
+    if let Some(bug_id) = database::get_bug_id_by_external_ref(&app_state.db, &email.external_ref).await? {
+        return Ok(InboundOutcome::Duplicate { bug_id });
+    }
+
+    let mut title: String = clean_subject(&email.subject).chars().take(TITLE_LIMIT).collect();
+    if title.trim().is_empty() {
+        title = format!("Email from {}", email.sender);
+    }
+    if let Some(error) = validate_bug_fields(&title, Some("Medium")).first() {
+        return Err(InboundError::Unreadable(error.message.clone()));
+    }
+
+    let new_bug = ExternalBug {
+        external_ref: email.external_ref,
+        title: title.trim().to_string(),
+        description: email.body,
+        reported_by: email.sender,
+        severity: "Medium".to_string(),
+        status: "Open".to_string(),
+        developer_id: None,
+        project_id,
+        labels: Vec::new(),
+        comments: Vec::new(),
+    };
+    let saved = database::save_external_bugs(&app_state.db, std::slice::from_ref(&new_bug)).await?;
+    let bug_id = saved.first().map(|s| s.bug_id).ok_or(sqlx::Error::RowNotFound)?;
+    // As for replies, a failed message leaves nothing behind to retry into.
+    let stored = match store_attachments(app_state, bug_id, email.attachments).await {
+        Ok(stored) => stored,
+        Err(e) => {
+            database::remove_bug(&app_state.db, bug_id).await?;
+            return Err(e);
+        }
+    };
+    let mut bug = database::get_bug_by_id(&app_state.db, bug_id).await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+    println!("📧 Created bug #{} from email by {}: {}", bug_id, new_bug.reported_by, bug.title);
+
+    events::bug_event(app_state, EventKind::BugCreated, &bug).await;
+    match assignment::auto_assign(app_state, &bug).await {
+        Ok(Some(assigned)) => {
+            println!("🤖 Auto-assigned bug #{} to developer #{}: {}", bug_id, assigned.developer_id, assigned.reason);
+            bug.developer_id = Some(assigned.developer_id);
+            events::bug_event(app_state, EventKind::BugAssigned, &bug).await;
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("❌ Auto-assignment failed for bug #{}: {}", bug_id, e),
+    }
+    Ok(InboundOutcome::Created { bug: Box::new(bug), attachments: stored })
+}
+
+async fn store_attachments(
+    app_state: &AppState,
+    bug_id: i64,
+    files: Vec<(String, Vec<u8>)>,
+) -> Result<Vec<Attachment>, InboundError> {
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(attachments::store(app_state, bug_id, files).await?)
+}
+
+// Allowed addresses and `@domain`s, and developers' notification addresses.
+async fn known_sender(app_state: &AppState, sender: &str) -> Result<bool, sqlx::Error> {
+    let domain = sender.rsplit_once('@').map(|(_, domain)| format!("@{}", domain));
+    let allowed = &app_state.config.inbound_allowed_senders;
+    if allowed.iter().any(|entry| entry == sender || Some(entry) == domain.as_ref()) {
+        return Ok(true);
+    }
+    database::is_developer_email(&app_state.db, sender).await
+}
+
+// The id in a `[Bug #12]` token, in any case.
+fn bug_token(subject: &str) -> Option<i64> {
+    let lower = subject.to_ascii_lowercase();
+    let start = lower.find("[bug #")? + "[bug #".len();
+    let rest = &subject[start..];
+    let end = rest.find(']')?;
+    rest[..end].trim().parse().ok()
+}
+
+// The subject without reply markers or a bug token.
+fn clean_subject(subject: &str) -> String {
+    let mut subject = subject.trim().to_string();
+    if let Some(start) = subject.to_ascii_lowercase().find("[bug #")
+        && let Some(length) = subject[start..].find(']')
+    {
+        subject.replace_range(start..=start + length, "");
+    }
+
+    let mut rest = subject.trim();
+    while let Some(prefix) = SUBJECT_PREFIXES.iter().find(|prefix| rest.to_ascii_lowercase().starts_with(*prefix)) {
+        rest = rest[prefix.len()..].trim_start();
+    }
+    rest.to_string()
+}
+
+// Drops quoted lines, and everything from a signature separator or an
+// "On ... wrote:" / "Original Message" header onwards.
+fn strip_quoted(body: &str) -> String {
+    let mut kept = Vec::new();
+    for line in body.lines() {
+        let trimmed = line.trim_end();
+        if trimmed == "--"
+            || trimmed.starts_with("-----Original Message-----")
+            || (trimmed.starts_with("On ") && trimmed.ends_with("wrote:"))
+        {
+            break;
+        }
+        if !trimmed.starts_with('>') {
+            kept.push(line);
+        }
+    }
+    kept.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bug_token_reads_the_id_in_any_case() {
+        assert_eq!(bug_token("Re: [Bug #12] Login fails"), Some(12));
+        assert_eq!(bug_token("RE: [BUG # 7 ] Crash"), Some(7));
+        assert_eq!(bug_token("Bug #12 without brackets"), None);
+        assert_eq!(bug_token("[Bug #twelve] Typo"), None);
+        assert_eq!(bug_token("[Bug #12 never closed"), None);
+    }
+
+    #[test]
+    fn clean_subject_drops_reply_markers_and_the_token() {
+        assert_eq!(clean_subject("Re: Fwd: AW: [Bug #3] Button missing"), "Button missing");
+        assert_eq!(clean_subject("  Export is slow  "), "Export is slow");
+        assert_eq!(clean_subject("Reply button broken"), "Reply button broken");
+    }
+
+    #[test]
+    fn strip_quoted_keeps_only_the_new_text() {
+        let body = "Still broken on Firefox.\n> Can you try again?\n\nOn Mon, 1 Jan 2024, Support wrote:\n> older";
+        assert_eq!(strip_quoted(body), "Still broken on Firefox.");
+        assert_eq!(strip_quoted("Thanks!\n-- \nDana"), "Thanks!");
+        assert_eq!(strip_quoted("> only a quote"), "");
+    }
+
+    #[test]
+    fn parse_reads_the_sender_and_message_id() {
+        let raw = b"From: Dana <Dana@Example.org>\r\nSubject: [Bug #4] Re: Crash\r\nMessage-ID: <abc@example.org>\r\n\r\nIt crashes again.\r\n";
+        let email = parse(raw).unwrap();
+        assert_eq!(email.sender, "dana@example.org");
+        assert_eq!(email.subject, "[Bug #4] Re: Crash");
+        assert_eq!(email.body, "It crashes again.");
+        assert_eq!(email.external_ref, "email:abc@example.org");
+    }
+
+    #[test]
+    fn parse_hashes_messages_without_an_id() {
+        let raw = b"From: dana@example.org\r\nSubject: Crash\r\n\r\nBody\r\n";
+        let email = parse(raw).unwrap();
+        assert!(email.external_ref.starts_with("email:sha256:"));
+        assert_eq!(parse(raw).unwrap().external_ref, email.external_ref);
+        assert!(matches!(parse(b"Subject: No sender\r\n\r\nBody\r\n"), Err(InboundError::Unreadable(_))));
+    }
+}
@@ -3,13 +3,37 @@ mod handlers;
 mod database;
 mod auth;
 mod state;
+mod templates;
+mod board;
+mod bugs;
+mod config;
+mod assignment;
+mod graph;
+mod attachments;
+mod export;
+mod import;
+mod cli;
+mod events;
+mod webhooks;
+mod notifications;
+mod inbound;
+mod git;
+mod sse;
+mod presence;
+mod graphql;
 
 use actix_web::{web, App, HttpServer, middleware::Logger};
+use config::Config;
 use state::AppState;
 
 #[actix_rt::main]
 async fn main() -> std::io::Result<()> {
     env_logger::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args).await);
+    }
     
     println!("🚀 Starting Bug Tracker Server...");
     
@@ -18,8 +42,27 @@ async fn main() -> std::io::Result<()> {
     
     println!("✅ Database connection established");
     
-    let app_state = AppState::new(db_pool);
+    let app_state = AppState::new(db_pool, Config::from_env());
+    if let Err(e) = app_state.load_project_settings().await {
+        eprintln!("❌ Failed to load saved project settings: {}", e);
+    }
+
+    let known_project_ids: Vec<i64> = app_state.projects.read().await.iter().map(|p| p.id).collect();
+    match database::check_integrity(&app_state.db, &known_project_ids).await {
+        Ok(orphans) if orphans.is_empty() => println!("✅ Referential integrity check passed"),
+        Ok(orphans) => {
+            println!("⚠️  Referential integrity check found {} orphaned reference(s):", orphans.len());
+            for orphan in &orphans {
+                println!("   {} row #{} references a missing {} row", orphan.table, orphan.row_id, orphan.references);
+            }
+        }
+        Err(e) => eprintln!("❌ Referential integrity check failed: {}", e),
+    }
     
+    webhooks::spawn_worker(app_state.clone());
+    notifications::spawn_worker(app_state.clone());
+    let graphql_schema = graphql::schema(app_state.clone());
+
     let server_url = "127.0.0.1:8080";
     println!("🌐 Server starting at http://{}", server_url);
     println!("🏠 Homepage available at: http://{}/", server_url);
@@ -29,13 +72,67 @@ async fn main() -> std::io::Result<()> {
     println!("   GET  /bugs               - List all bugs");
     println!("   GET  /bugs/:id           - Get specific bug");
     println!("   PATCH /bugs/:id          - Update bug");
-    println!("   DELETE /bugs/:id         - Delete bug");
+    println!("   DELETE /bugs/:id         - Move bug to the trash");
+    println!("   POST /bugs/:id/restore   - Restore bug from the trash");
+    println!("   POST /bugs/bulk          - Assign, relabel, transition or delete many bugs at once");
+    println!("   GET  /bugs/export        - Download bugs (format=csv|json|ndjson, columns=..., GET /bugs filters)");
+    println!("   POST /bugs/import        - Import bugs from CSV or NDJSON (mapping=..., dry_run=true)");
+    println!("   POST /bugs/:id/transition - Move bug through the workflow");
+    println!("   POST /bugs/:id/suggest-assignee - Rank candidate assignees (dry run)");
+    println!("   POST /bugs/:id/labels    - Add labels to a bug");
+    println!("   DELETE /bugs/:id/labels  - Remove labels from a bug");
+    println!("   POST /bugs/:id/links     - Link bugs (duplicate_of, blocks, blocked_by, related)");
+    println!("   DELETE /bugs/:id/links/:link_id - Remove a link");
+    println!("   GET  /bugs/:id/comments  - List comments");
+    println!("   POST /bugs/:id/comments  - Add a comment");
+    println!("   POST /bugs/:id/attachments - Upload attachments (multipart)");
+    println!("   GET  /bugs/:id/attachments - List attachments");
+    println!("   GET  /bugs/:id/presence  - WebSocket: who is viewing/editing, typing and field changes (ticket=...)");
+    println!("   POST /bugs/:id/presence/ticket - Single-use ticket for the presence WebSocket (token)");
+    println!("   GET  /attachments/:id    - Download attachment");
+    println!("   DELETE /attachments/:id  - Delete attachment");
+    println!("   GET  /trash              - List deleted bugs");
+    println!("   POST /trash/purge        - Purge bugs past the retention period (admin)");
+    println!("   DELETE /trash/:id        - Permanently delete a trashed bug (admin)");
     println!("   GET  /bugs/assign        - Bug assignment form");
     println!("   POST /bugs/assign        - Submit bug assignment");
+    println!("   GET  /ui/bugs            - Bug list (HTML, filterable)");
+    println!("   GET  /ui/bugs/new        - Report a bug (HTML form)");
+    println!("   GET  /ui/bugs/:id        - Bug detail page");
+    println!("   GET  /ui/bugs/:id/edit   - Edit bug (HTML form)");
+    println!("   GET  /board              - Kanban board (HTML)");
+    println!("   GET  /board.json         - Kanban board (JSON)");
+    println!("   GET  /labels             - List labels");
+    println!("   POST /labels             - Create label");
+    println!("   PATCH /labels/:id        - Rename or recolour label");
+    println!("   DELETE /labels/:id       - Delete label (removed from all bugs)");
     println!("   GET  /developers         - List developers");
     println!("   POST /developers         - Add new developer");
+    println!("   GET  /developers/:id     - Get developer");
+    println!("   GET  /developers/:id/workload - Open bugs and capacity");
+    println!("   GET  /developers/:id/notifications - Email notification settings");
+    println!("   PUT  /developers/:id/notifications - Set email, delivery (immediate|digest|off) and triggers");
+    println!("   PATCH /developers/:id    - Rename or (de)activate developer");
+    println!("   DELETE /developers/:id   - Delete developer (policy=reject|unassign|reassign)");
     println!("   GET  /projects           - List projects");
     println!("   POST /projects           - Add new project (admin)");
+    println!("   GET  /projects/:id/assignment - Auto-assignment strategy");
+    println!("   PUT  /projects/:id/assignment - Set auto-assignment strategy and rules (admin)");
+    println!("   PUT  /projects/:id/components - Set a project's components (admin)");
+    println!("   GET  /projects/:id/dependency-graph - Blocking graph (format=json|dot)");
+    println!("   GET  /components/:name/owners - Who owns a component");
+    println!("   POST /inbound/email      - Open a bug or reply to one from a raw email (project_id=...)");
+    println!("   POST /integrations/git/push - GitHub/Gitea/GitLab push webhook: refs #N / fixes #N");
+    println!("   POST /integrations/git/commits - Same for commits as generic JSON (admin)");
+    println!("   POST /graphql            - GraphQL: bugs, developers, projects, users; create/update/assign bugs");
+    println!("   GET  /graphql            - GraphiQL explorer");
+    println!("   GET  /events             - Live bug changes as Server-Sent Events (project_id=..., Last-Event-ID)");
+    println!("   GET  /webhooks           - List webhooks (admin)");
+    println!("   POST /webhooks           - Subscribe a URL to bug events (admin)");
+    println!("   PATCH /webhooks/:id      - Change URL, secret, events or active (admin)");
+    println!("   DELETE /webhooks/:id     - Delete webhook and its delivery log (admin)");
+    println!("   GET  /webhooks/:id/deliveries - Delivery log (admin)");
+    println!("   POST /webhooks/:id/deliveries/:delivery_id/redeliver - Send a delivery again (admin)");
     println!("   POST /login              - User authentication");
     println!("   GET  /health             - Health check");
     println!("📖 Default admin credentials: admin/admin123");
@@ -43,6 +140,7 @@ async fn main() -> std::io::Result<()> {
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(app_state.clone()))
+            .app_data(web::Data::new(graphql_schema.clone()))
             .wrap(Logger::default())
             // Homepage route
             .route("/", web::get().to(handlers::homepage))
@@ -53,22 +151,124 @@ async fn main() -> std::io::Result<()> {
                     .route("/new", web::post().to(handlers::create_bug))
                     .route("/assign", web::get().to(handlers::bug_assign_form))     // Move this UP
                     .route("/assign", web::post().to(handlers::assign_bug))        // Move this UP
+                    .route("/bulk", web::post().to(handlers::bulk_update_bugs))
+                    .route("/export", web::get().to(handlers::export_bugs))
+                    .service(
+                        web::resource("/import")
+                            .app_data(web::PayloadConfig::new(import::MAX_IMPORT_BYTES))
+                            .route(web::post().to(handlers::import_bugs))
+                    )
                     .route("", web::get().to(handlers::get_bugs))
                     .route("/{id}", web::get().to(handlers::get_bug))              // Keep this AFTER assign
                     .route("/{id}", web::patch().to(handlers::update_bug))
                     .route("/{id}", web::delete().to(handlers::delete_bug))
+                    .route("/{id}/restore", web::post().to(handlers::restore_bug))
+                    .route("/{id}/transition", web::post().to(handlers::transition_bug))
+                    .route("/{id}/suggest-assignee", web::post().to(handlers::suggest_assignee))
+                    .route("/{id}/labels", web::post().to(handlers::add_bug_labels))
+                    .route("/{id}/labels", web::delete().to(handlers::remove_bug_labels))
+                    .route("/{id}/links", web::post().to(handlers::create_bug_link))
+                    .route("/{id}/links/{link_id}", web::delete().to(handlers::delete_bug_link))
+                    .route("/{id}/comments", web::get().to(handlers::get_bug_comments))
+                    .route("/{id}/comments", web::post().to(handlers::add_bug_comment))
+                    .route("/{id}/attachments", web::get().to(handlers::get_bug_attachments))
+                    .route("/{id}/attachments", web::post().to(handlers::upload_attachments))
+                    .route("/{id}/presence", web::get().to(handlers::bug_presence))
+                    .route("/{id}/presence/ticket", web::post().to(handlers::bug_presence_ticket))
+            )
+            .service(
+                web::scope("/attachments")
+                    .route("/{id}", web::get().to(handlers::download_attachment))
+                    .route("/{id}", web::delete().to(handlers::delete_attachment))
+            )
+            // Trash (soft-deleted bugs)
+            .service(
+                web::scope("/trash")
+                    .route("", web::get().to(handlers::get_trash))
+                    .route("/purge", web::post().to(handlers::purge_trash))
+                    .route("/{id}", web::delete().to(handlers::purge_bug))
+            )
+            // Label management routes
+            .service(
+                web::scope("/labels")
+                    .route("", web::get().to(handlers::get_labels))
+                    .route("", web::post().to(handlers::create_label))
+                    .route("/{id}", web::get().to(handlers::get_label))
+                    .route("/{id}", web::patch().to(handlers::update_label))
+                    .route("/{id}", web::delete().to(handlers::delete_label))
+            )
+            // Kanban board
+            .route("/board", web::get().to(handlers::board_page))
+            .route("/board.json", web::get().to(handlers::board_json))
+            .route("/board/move", web::post().to(handlers::board_move))
+            // Web UI routes
+            .service(
+                web::scope("/ui/bugs")
+                    .route("", web::get().to(handlers::ui_bug_list))
+                    .route("/new", web::get().to(handlers::ui_new_bug_form))
+                    .route("/new", web::post().to(handlers::ui_create_bug))
+                    .route("/{id}", web::get().to(handlers::ui_bug_detail))
+                    .route("/{id}/edit", web::get().to(handlers::ui_edit_bug_form))
+                    .route("/{id}/edit", web::post().to(handlers::ui_update_bug))
+                    .route("/{id}/delete", web::post().to(handlers::ui_delete_bug))
+                    .route("/{id}/attachments", web::post().to(handlers::ui_upload_attachments))
             )
             // Developer management routes
             .service(
                 web::scope("/developers")
                     .route("", web::get().to(handlers::get_developers))
                     .route("", web::post().to(handlers::create_developer))
+                    .route("/{id}", web::get().to(handlers::get_developer))
+                    .route("/{id}/workload", web::get().to(handlers::get_developer_workload))
+                    .route("/{id}/notifications", web::get().to(handlers::get_notification_settings))
+                    .route("/{id}/notifications", web::put().to(handlers::update_notification_settings))
+                    .route("/{id}", web::patch().to(handlers::update_developer))
+                    .route("/{id}", web::delete().to(handlers::delete_developer))
+            )
+            // Git integration
+            .service(
+                web::scope("/integrations/git")
+                    .app_data(web::PayloadConfig::new(git::MAX_PAYLOAD_BYTES))
+                    .app_data(web::JsonConfig::default().limit(git::MAX_PAYLOAD_BYTES))
+                    .route("/push", web::post().to(handlers::git_push))
+                    .route("/commits", web::post().to(handlers::git_commits))
+            )
+            // Inbound email
+            .service(
+                web::resource("/inbound/email")
+                    .app_data(web::PayloadConfig::new(inbound::MAX_MESSAGE_BYTES))
+                    .route(web::post().to(handlers::receive_email))
             )
             // Project management routes
             .service(
                 web::scope("/projects")
                     .route("", web::get().to(handlers::get_projects))
                     .route("", web::post().to(handlers::add_project))
+                    .route("/{id}/assignment", web::get().to(handlers::get_project_assignment))
+                    .route("/{id}/assignment", web::put().to(handlers::update_project_assignment))
+                    .route("/{id}/components", web::put().to(handlers::update_project_components))
+                    .route("/{id}/dependency-graph", web::get().to(handlers::get_dependency_graph))
+            )
+            // GraphQL API and its GraphiQL explorer
+            .route("/graphql", web::post().to(handlers::graphql))
+            .route("/graphql", web::get().to(handlers::graphiql))
+            // Live event stream (Server-Sent Events)
+            .route("/events", web::get().to(handlers::event_stream))
+            // Outgoing webhooks (admin)
+            .service(
+                web::scope("/webhooks")
+                    .route("", web::get().to(handlers::get_webhooks))
+                    .route("", web::post().to(handlers::create_webhook))
+                    .route("/{id}", web::get().to(handlers::get_webhook))
+                    .route("/{id}", web::patch().to(handlers::update_webhook))
+                    .route("/{id}", web::delete().to(handlers::delete_webhook))
+                    .route("/{id}/deliveries", web::get().to(handlers::get_webhook_deliveries))
+                    .route("/{id}/deliveries/{delivery_id}", web::get().to(handlers::get_webhook_delivery))
+                    .route("/{id}/deliveries/{delivery_id}/redeliver", web::post().to(handlers::redeliver_webhook))
+            )
+            .service(
+                web::scope("/components")
+                    .route("/{name}/owners", web::get().to(handlers::get_component_owners))
             )
             // Authentication routes (MOVED OUTSIDE PROJECTS SCOPE)
             .route("/login", web::post().to(handlers::login))
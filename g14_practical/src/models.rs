@@ -1,5 +1,47 @@
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use sqlx::FromRow;
+use sqlx::types::Json;
+use std::fmt;
+use std::str::FromStr;
+
+pub const SEVERITIES: [&str; 4] = ["Low", "Medium", "High", "Critical"];
+pub const STATUSES: [&str; 4] = ["Open", "In Progress", "Resolved", "Closed"];
+
+// Workflow: any open state can move forward or be closed; resolved and
+// closed bugs can only be reopened (or a resolved one closed).
+pub fn allowed_transitions(from: &str) -> &'static [&'static str] {
+    match from {
+        "Open" => &["In Progress", "Resolved", "Closed"],
+        "In Progress" => &["Open", "Resolved", "Closed"],
+        "Resolved" => &["Open", "Closed"],
+        "Closed" => &["Open"],
+        _ => &STATUSES,
+    }
+}
+
+pub fn canonical_status(status: &str) -> Option<&'static str> {
+    STATUSES.iter().copied().find(|s| s.eq_ignore_ascii_case(status.trim()))
+}
+
+pub fn canonical_severity(severity: &str) -> Option<&'static str> {
+    SEVERITIES.iter().copied().find(|s| s.eq_ignore_ascii_case(severity.trim()))
+}
+
+// Statuses that still count against a developer's workload.
+pub const OPEN_STATUSES: [&str; 2] = ["Open", "In Progress"];
+
+pub fn severity_weight(severity: Option<&str>) -> i64 {
+    match severity.map(str::to_ascii_lowercase).as_deref() {
+        Some("critical") => 5,
+        Some("high") => 3,
+        Some("medium") => 2,
+        _ => 1,
+    }
+}
+
+fn default_status() -> String {
+    STATUSES[0].to_string()
+}
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct Bug {
@@ -9,25 +51,866 @@ pub struct Bug {
     pub reported_by: Option<String>,
     pub severity: Option<String>,
     pub developer_id: Option<i64>,
+    // Only changed through `POST /bugs/{id}/transition`.
+    #[serde(default = "default_status")]
+    pub status: String,
+    pub project_id: Option<i64>,
+    pub component: Option<String>,
+    // Loaded from `bug_labels`; changed through `/bugs/{id}/labels`.
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub labels: Vec<Label>,
+    // Set while the bug is in the trash; only shown there.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_by: Option<String>,
+    // Where an imported bug came from, e.g. `github:owner/repo#12`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_ref: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NewBug {
     pub title: String,
     pub description: String,
     pub reported_by: String,
+    // May be left out (or blank) for a bug without a severity.
+    #[serde(default)]
+    pub severity: String,
+    #[serde(default)]
+    pub project_id: Option<i64>,
+    #[serde(default)]
+    pub component: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BugTransition {
+    pub status: String,
+}
+
+impl NewBug {
+    // Everything `POST /bugs/new` checks before inserting, including that
+    // the project exists and has the component.
+    pub fn validate(&self, projects: &[Project]) -> Vec<FieldError> {
+        let mut errors = validate_bug_fields(&self.title, Some(&self.severity));
+        if let Some(project_id) = self.project_id
+            && !projects.iter().any(|p| p.id == project_id)
+        {
+            errors.push(FieldError::new("project_id", &format!("Project #{} does not exist", project_id)));
+        } else if let Some(message) = validate_component(projects, self.project_id, self.component.as_deref()) {
+            errors.push(FieldError::new("component", &message));
+        }
+        errors
+    }
+}
+
+// Query string filters shared by `GET /bugs` and the HTML bug list.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BugFilter {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub severity: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub developer_id: Option<i64>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub unassigned: Option<bool>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub reported_by: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub q: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub status: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub project_id: Option<i64>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub component: Option<String>,
+    // Comma-separated label names.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub labels_any: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub labels_all: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub labels_none: Option<String>,
+}
+
+impl BugFilter {
+    pub fn is_empty(&self) -> bool {
+        self.severity.is_none()
+            && self.developer_id.is_none()
+            && self.unassigned.is_none()
+            && self.reported_by.is_none()
+            && self.q.is_none()
+            && self.status.is_none()
+            && self.project_id.is_none()
+            && self.component.is_none()
+            && self.labels_any.is_none()
+            && self.labels_all.is_none()
+            && self.labels_none.is_none()
+    }
+}
+
+// Label names match without regard to case, so `Bug,bug` is one name; the
+// `labels_all` filter counts them.
+pub fn split_label_names(names: &str) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for name in names.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        if !unique.iter().any(|u| u.eq_ignore_ascii_case(name)) {
+            unique.push(name.to_string());
+        }
+    }
+    unique
+}
+
+// Query string of `GET /bugs/export`: the `GET /bugs` filters plus the
+// output format and a comma-separated column list.
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(default)]
+    pub columns: Option<String>,
+    #[serde(flatten)]
+    pub filter: BugFilter,
+}
+
+// One exported bug. Project names live in memory, so `project_name` is
+// filled in by the exporter rather than the query.
+#[derive(Debug, FromRow)]
+pub struct ExportRow {
+    pub id: i64,
+    pub title: String,
+    pub description: Option<String>,
+    pub reported_by: Option<String>,
+    pub severity: Option<String>,
+    pub status: String,
+    pub component: Option<String>,
+    pub developer_id: Option<i64>,
+    pub developer_name: Option<String>,
+    pub project_id: Option<i64>,
+    #[sqlx(skip)]
+    pub project_name: Option<String>,
+    // Comma-separated label names.
+    pub labels: Option<String>,
+}
+
+// Query string of `POST /bugs/import`.
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    #[serde(default)]
+    pub format: Option<String>,
+    // `Source column:field` pairs separated by commas.
+    #[serde(default)]
+    pub mapping: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub dry_run: Option<bool>,
+}
+
+// Query string of `POST /inbound/email`: the project new bugs go into.
+#[derive(Debug, Deserialize)]
+pub struct InboundEmailQuery {
+    #[serde(default)]
+    pub project_id: Option<i64>,
+}
+
+// Query string of `GET /bugs/{id}/presence`. Browsers can't set headers on
+// a WebSocket, so they sign in with a ticket from
+// `POST /bugs/{id}/presence/ticket` instead of `Authorization`. The JWT
+// itself never goes in the URL, where access logs would keep it.
+#[derive(Debug, Deserialize)]
+pub struct PresenceQuery {
+    #[serde(default)]
+    pub ticket: Option<String>,
+}
+
+// Query string of `GET /events`: only events of this project.
+#[derive(Debug, Deserialize)]
+pub struct EventStreamQuery {
+    #[serde(default)]
+    pub project_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportRowReport {
+    // 1-based, not counting a CSV header.
+    pub row: usize,
+    // `imported`, `valid` (dry run), `invalid` or `failed` (valid, but
+    // its batch could not be saved).
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bug_id: Option<i64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub total: usize,
+    pub valid: usize,
+    pub invalid: usize,
+    pub imported: usize,
+    pub failed: usize,
+    pub rows: Vec<ImportRowReport>,
+}
+
+// HTML bug form; browsers submit blank selects as empty strings.
+#[derive(Debug, Default, Deserialize)]
+pub struct BugForm {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub reported_by: String,
+    #[serde(default)]
     pub severity: String,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub developer_id: Option<i64>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub project_id: Option<i64>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub component: Option<String>,
+}
+
+impl BugForm {
+    pub fn from_bug(bug: &Bug) -> Self {
+        Self {
+            title: bug.title.clone(),
+            description: bug.description.clone().unwrap_or_default(),
+            reported_by: bug.reported_by.clone().unwrap_or_default(),
+            severity: bug.severity.clone().unwrap_or_default(),
+            developer_id: bug.developer_id,
+            project_id: bug.project_id,
+            component: bug.component.clone(),
+        }
+    }
+
+    // Unlike the API, the form insists on a severity.
+    pub fn validate(&self, developers: &[Developer], projects: &[Project]) -> Vec<FieldError> {
+        let mut errors = validate_bug_fields(&self.title, Some(&self.severity));
+        if self.severity.trim().is_empty() {
+            errors.push(FieldError::new("severity", "Severity is required"));
+        }
+        if let Some(developer_id) = self.developer_id
+            && !developers.iter().any(|d| d.id == developer_id)
+        {
+            errors.push(FieldError::new("developer_id", "Selected developer does not exist"));
+        }
+        if let Some(project_id) = self.project_id
+            && !projects.iter().any(|p| p.id == project_id)
+        {
+            errors.push(FieldError::new("project_id", "Selected project does not exist"));
+        }
+        if let Some(message) = validate_component(projects, self.project_id, self.component.as_deref()) {
+            errors.push(FieldError::new("component", &message));
+        }
+        errors
+    }
+
+    pub fn to_new_bug(&self) -> NewBug {
+        NewBug {
+            title: self.title.trim().to_string(),
+            description: self.description.trim().to_string(),
+            reported_by: self.reported_by.trim().to_string(),
+            severity: self.severity.clone(),
+            project_id: self.project_id,
+            component: normalize_component(self.component.as_deref()),
+        }
+    }
+
+    pub fn to_bug(&self, bug_id: i64) -> Bug {
+        Bug {
+            id: Some(bug_id),
+            title: self.title.trim().to_string(),
+            description: Some(self.description.trim().to_string()),
+            reported_by: Some(self.reported_by.trim().to_string()),
+            severity: Some(self.severity.clone()),
+            developer_id: self.developer_id,
+            status: default_status(),
+            project_id: self.project_id,
+            component: normalize_component(self.component.as_deref()),
+            labels: Vec::new(),
+            deleted_at: None,
+            deleted_by: None,
+            external_ref: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &'static str, message: &str) -> Self {
+        Self {
+            field,
+            message: message.to_string(),
+        }
+    }
+}
+
+// A missing or blank severity is allowed; any other must be one of
+// `SEVERITIES`, in any case.
+pub fn validate_bug_fields(title: &str, severity: Option<&str>) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    if title.trim().is_empty() {
+        errors.push(FieldError::new("title", "Title is required"));
+    } else if title.trim().chars().count() > 200 {
+        errors.push(FieldError::new("title", "Title must be 200 characters or fewer"));
+    }
+    if let Some(s) = severity.map(str::trim)
+        && !s.is_empty()
+        && canonical_severity(s).is_none()
+    {
+        errors.push(FieldError::new(
+            "severity",
+            &format!("Severity must be one of: {}", SEVERITIES.join(", ")),
+        ));
+    }
+    errors
+}
+
+pub fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+// Query strings only carry strings, but the same filters also arrive as
+// JSON (bulk operations), where numbers and booleans are bare.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+}
+
+pub fn empty_string_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let value = Option::<Scalar>::deserialize(deserializer)?.map(|scalar| match scalar {
+        Scalar::String(s) => s,
+        Scalar::Integer(i) => i.to_string(),
+        Scalar::Bool(b) => b.to_string(),
+    });
+    match value.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(s) => s.parse::<T>().map(Some).map_err(de::Error::custom),
+    }
+}
+
+// Body of `POST /bugs/bulk`. Bugs are picked either by `ids` or by
+// `filter` (the same filters as `GET /bugs`).
+#[derive(Debug, Deserialize)]
+pub struct BulkRequest {
+    #[serde(default)]
+    pub ids: Option<Vec<i64>>,
+    #[serde(default)]
+    pub filter: Option<BugFilter>,
+    pub operation: BulkOperation,
+    // Roll the whole batch back if any bug fails.
+    #[serde(default)]
+    pub all_or_nothing: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BulkOperation {
+    Assign { developer_id: i64 },
+    SetSeverity { severity: String },
+    AddLabels { labels: Vec<String> },
+    Transition { status: String },
+    Delete,
+}
+
+impl BulkOperation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BulkOperation::Assign { .. } => "assign",
+            BulkOperation::SetSeverity { .. } => "set_severity",
+            BulkOperation::AddLabels { .. } => "add_labels",
+            BulkOperation::Transition { .. } => "transition",
+            BulkOperation::Delete => "delete",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkItemResult {
+    pub bug_id: i64,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+    // Set on transitions: the status the bug moved from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_status: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkResult {
+    pub operation: &'static str,
+    pub all_or_nothing: bool,
+    // False when `all_or_nothing` rolled the batch back.
+    pub committed: bool,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BulkItemResult>,
+}
+
+// Links as they're requested. `blocked_by` is stored as `blocks` from the
+// other bug, so only three kinds reach the database.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    DuplicateOf,
+    Blocks,
+    BlockedBy,
+    Related,
+}
+
+impl LinkKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LinkKind::DuplicateOf => "duplicate_of",
+            LinkKind::Blocks => "blocks",
+            LinkKind::BlockedBy => "blocked_by",
+            LinkKind::Related => "related",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewBugLink {
+    pub kind: LinkKind,
+    pub target_id: i64,
+}
+
+// A link seen from one bug: `relation` reads "this bug <relation> bug_id",
+// e.g. `blocked_by` or `duplicated_by` for incoming links.
+#[derive(Debug, Serialize, Clone)]
+pub struct BugLinkView {
+    pub id: i64,
+    pub relation: String,
+    pub bug_id: i64,
+    pub title: String,
+    pub status: String,
+}
+
+// `GET /bugs/{id}` response: the bug plus its links.
+#[derive(Debug, Serialize)]
+pub struct BugDetail {
+    #[serde(flatten)]
+    pub bug: Bug,
+    pub links: Vec<BugLinkView>,
+}
+
+#[derive(Debug)]
+pub enum LinkError {
+    SelfLink,
+    BugNotFound(i64),
+    TargetNotFound(i64),
+    AlreadyLinked,
+    AlreadyDuplicate { of: i64 },
+    Cycle(Vec<i64>),
+    // The workflow doesn't allow closing a bug in this status.
+    CannotClose(String),
+}
+
+impl LinkError {
+    pub fn message(&self) -> String {
+        match self {
+            LinkError::SelfLink => "A bug can't be linked to itself".to_string(),
+            LinkError::BugNotFound(id) => format!("Bug #{} not found", id),
+            LinkError::TargetNotFound(id) => format!("Bug #{} does not exist", id),
+            LinkError::AlreadyLinked => "These bugs are already linked that way".to_string(),
+            LinkError::AlreadyDuplicate { of } => format!("Bug is already a duplicate of #{}", of),
+            LinkError::Cycle(path) => format!(
+                "Link would create a cycle: {}",
+                path.iter().map(|id| format!("#{}", id)).collect::<Vec<_>>().join(" -> ")
+            ),
+            LinkError::CannotClose(status) => format!("Cannot close a bug that is {}", status),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Attachment {
+    pub id: i64,
+    pub bug_id: i64,
+    pub filename: String,
+    // Sniffed from the file's contents, not taken from the upload.
+    pub content_type: String,
+    pub size: i64,
+    pub sha256: String,
+    pub uploaded_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Comment {
+    pub id: i64,
+    pub bug_id: i64,
+    pub author: String,
+    pub body: String,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_ref: Option<String>,
+}
+
+// Body of `POST /bugs/{id}/comments`; the author is the token's user.
+#[derive(Debug, Deserialize)]
+pub struct NewComment {
+    pub body: String,
+}
+
+// A bug from another tracker, already mapped onto our fields. Saving it
+// again (matched on `external_ref`) updates the bug instead of adding one.
+#[derive(Debug, Clone)]
+pub struct ExternalBug {
+    pub external_ref: String,
+    pub title: String,
+    pub description: String,
+    pub reported_by: String,
+    pub severity: String,
+    pub status: String,
+    pub developer_id: Option<i64>,
+    pub project_id: Option<i64>,
+    pub labels: Vec<NewLabel>,
+    pub comments: Vec<ExternalComment>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SavedExternalBug {
+    pub bug_id: i64,
+    pub created: bool,
+    pub new_comments: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExternalComment {
+    pub external_ref: String,
+    pub author: String,
+    pub body: String,
+    pub created_at: Option<String>,
+}
+
+// An outgoing webhook subscription. The secret signs each delivery and is
+// only shown when the webhook is created.
+#[derive(Debug, Serialize, Clone)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+// Body of `POST /webhooks`. A secret is generated when none is given.
+#[derive(Debug, Deserialize)]
+pub struct NewWebhook {
+    pub url: String,
+    #[serde(default)]
+    pub secret: Option<String>,
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWebhook {
+    pub url: Option<String>,
+    pub secret: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub active: Option<bool>,
+}
+
+// Query for `GET /webhooks/{id}/deliveries`.
+#[derive(Debug, Deserialize)]
+pub struct WebhookDeliveryQuery {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+// One event queued for one webhook. `status` is `pending` until it is
+// delivered or runs out of attempts (`failed`); the response columns
+// describe the latest attempt.
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event: String,
+    pub status: String,
+    pub attempts: i64,
+    pub next_attempt_at: Option<String>,
+    pub last_attempt_at: Option<String>,
+    pub response_status: Option<i64>,
+    pub response_body: Option<String>,
+    pub error: Option<String>,
+    pub redelivery_of: Option<i64>,
+    pub created_at: String,
+    #[serde(skip_serializing)]
+    pub payload: String,
+}
+
+// A delivery that is due, with what's needed to send it.
+#[derive(Debug, FromRow)]
+pub struct DueWebhookDelivery {
+    pub id: i64,
+    pub event: String,
+    pub payload: String,
+    pub attempts: i64,
+    pub url: String,
+    pub secret: String,
+}
+
+// An event as kept in the event log and sent to live streams.
+#[derive(Debug, Clone, FromRow)]
+pub struct LoggedEvent {
+    pub id: i64,
+    pub event: String,
+    pub payload: String,
+}
+
+// The result of one delivery attempt. `retry_in_seconds` is set when the
+// attempt failed and another one is allowed.
+#[derive(Debug)]
+pub struct WebhookAttempt {
+    pub delivered: bool,
+    pub response_status: Option<i64>,
+    pub response_body: Option<String>,
+    pub error: Option<String>,
+    pub retry_in_seconds: Option<i64>,
+}
+
+pub const NOTIFICATION_DELIVERIES: [&str; 3] = ["immediate", "digest", "off"];
+
+// A developer's email notification preferences. Developers without a
+// stored row get `NotificationSettings::defaults`, which has no address
+// and so sends nothing.
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct NotificationSettings {
+    pub developer_id: i64,
+    pub email: Option<String>,
+    // One of `NOTIFICATION_DELIVERIES`: every notification on its own,
+    // collected into a periodic digest, or none at all.
+    pub delivery: String,
+    pub on_assigned: bool,
+    pub on_status_change: bool,
+    pub on_comment: bool,
+}
+
+impl NotificationSettings {
+    pub fn defaults(developer_id: i64) -> Self {
+        Self {
+            developer_id,
+            email: None,
+            delivery: "immediate".to_string(),
+            on_assigned: true,
+            on_status_change: true,
+            on_comment: true,
+        }
+    }
+}
+
+// Body of `PUT /developers/{id}/notifications`; missing fields are kept.
+#[derive(Debug, Deserialize)]
+pub struct UpdateNotificationSettings {
+    pub email: Option<String>,
+    pub delivery: Option<String>,
+    pub on_assigned: Option<bool>,
+    pub on_status_change: Option<bool>,
+    pub on_comment: Option<bool>,
+}
+
+// An unsent notification with what's needed to email it. `due` is false
+// for digest notifications younger than the digest interval.
+#[derive(Debug, FromRow)]
+pub struct PendingNotification {
+    pub id: i64,
+    pub developer_id: i64,
+    pub developer_name: String,
+    pub email: String,
+    pub delivery: String,
+    pub bug_id: i64,
+    pub bug_title: String,
+    pub summary: String,
+    pub detail: Option<String>,
+    // Failed sends so far.
+    pub attempts: i64,
+    pub due: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DependencyGraphQuery {
+    // `json` (default) or `dot`.
+    pub format: Option<String>,
+}
+
+// Blocking links around one project. `external` nodes belong to another
+// project (or none) but block or are blocked by one of its bugs.
+#[derive(Debug, Serialize)]
+pub struct DependencyGraph {
+    pub project_id: i64,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    // Open bugs, blockers before the bugs they block; most severe first
+    // when several are ready.
+    pub fix_order: Vec<i64>,
+    pub cycles: Vec<Vec<i64>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphNode {
+    pub id: i64,
+    pub title: String,
+    pub status: String,
+    pub severity: Option<String>,
+    pub project_id: Option<i64>,
+    pub open: bool,
+    pub external: bool,
+}
+
+// `from` blocks `to`.
+#[derive(Debug, Serialize)]
+pub struct GraphEdge {
+    pub from: i64,
+    pub to: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Label {
+    pub id: i64,
+    pub name: String,
+    pub color: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NewLabel {
+    pub name: String,
+    pub color: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateLabel {
+    pub name: Option<String>,
+    pub color: Option<String>,
+    // Absent leaves the description alone; `null` clears it.
+    #[serde(default, deserialize_with = "double_option")]
+    pub description: Option<Option<String>>,
+}
+
+// Body of `POST` and `DELETE /bugs/{id}/labels`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BugLabels {
+    pub labels: Vec<String>,
+}
+
+pub const DEFAULT_LABEL_COLOR: &str = "#6c757d";
+
+// Colours are rendered into the page, so only `#rgb` / `#rrggbb` hex.
+pub fn is_valid_color(color: &str) -> bool {
+    let Some(hex) = color.strip_prefix('#') else {
+        return false;
+    };
+    (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit())
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct Developer {
     pub id: i64,
     pub name: String,
+    // Inactive developers are kept for history but can't take new bugs.
+    pub active: bool,
+    // Most open bugs this developer should carry; `None` means no limit.
+    pub max_open_bugs: Option<i64>,
+    // Loaded from `developer_skills` and `component_owners`.
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub skills: Vec<String>,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub components: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DeveloperSummary {
+    #[serde(flatten)]
+    pub developer: Developer,
+    pub open_bugs: i64,
+    pub weighted_load: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DeveloperWorkload {
+    pub developer: Developer,
+    pub open_bugs: i64,
+    pub weighted_load: i64,
+    pub remaining_capacity: Option<i64>,
+    pub by_severity: std::collections::BTreeMap<String, i64>,
+    pub by_status: std::collections::BTreeMap<String, i64>,
+    pub bugs: Vec<Bug>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NewDeveloper {
     pub name: String,
+    #[serde(default)]
+    pub skills: Vec<String>,
+    #[serde(default)]
+    pub components: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateDeveloper {
+    pub name: Option<String>,
+    pub active: Option<bool>,
+    // Absent leaves the limit alone; `null` removes it.
+    #[serde(default, deserialize_with = "double_option")]
+    pub max_open_bugs: Option<Option<i64>>,
+    // Replace the whole list when present.
+    pub skills: Option<Vec<String>>,
+    pub components: Option<Vec<String>>,
+}
+
+// Answer to "who owns this area": developers owning the component first,
+// then anyone tagged with it as a skill.
+#[derive(Debug, Serialize)]
+pub struct ComponentOwners {
+    pub component: String,
+    pub projects: Vec<i64>,
+    pub owners: Vec<Developer>,
+    pub skilled: Vec<Developer>,
+}
+
+// What happens to a developer's bugs when the developer is deleted.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DeletePolicy {
+    #[default]
+    Reject,
+    Unassign,
+    Reassign,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeveloperDeleteQuery {
+    #[serde(default)]
+    pub policy: DeletePolicy,
+    pub reassign_to: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -36,12 +919,117 @@ pub struct Project {
     pub name: String,
     pub description: String,
     pub active: bool,
+    #[serde(default)]
+    pub assignment: AssignmentConfig,
+    // Areas bugs in this project can be filed against; empty allows any.
+    #[serde(default)]
+    pub components: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NewProject {
     pub name: String,
     pub description: String,
+    #[serde(default)]
+    pub components: Vec<String>,
+}
+
+// Skills and components are compared case-insensitively, so they're stored
+// trimmed, lowercased and without duplicates.
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+pub fn normalize_component(component: Option<&str>) -> Option<String> {
+    component
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.is_empty())
+}
+
+// A bug's component must be one its project lists, unless the project
+// doesn't list any (or the bug has no project).
+pub fn validate_component(
+    projects: &[Project],
+    project_id: Option<i64>,
+    component: Option<&str>,
+) -> Option<String> {
+    let component = normalize_component(component)?;
+    let project = projects.iter().find(|p| Some(p.id) == project_id)?;
+    if project.components.is_empty() || project.components.contains(&component) {
+        None
+    } else {
+        Some(format!(
+            "Component '{}' is not part of {} (expected one of: {})",
+            component, project.name, project.components.join(", ")
+        ))
+    }
+}
+
+// How new bugs filed against a project pick a developer. `Manual` leaves
+// them unassigned for triage.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AssignmentStrategyKind {
+    #[default]
+    Manual,
+    RoundRobin,
+    LeastLoaded,
+    Rules,
+}
+
+// Sends bugs whose title or description mentions any of `keywords` to
+// `developer_id`. Matching is case-insensitive.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RoutingRule {
+    pub keywords: Vec<String>,
+    pub developer_id: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AssignmentConfig {
+    #[serde(default)]
+    pub strategy: AssignmentStrategyKind,
+    #[serde(default)]
+    pub rules: Vec<RoutingRule>,
+}
+
+// What `PUT /projects/{id}/assignment` and `/components` saved for a
+// project, applied over the in-memory project list at startup.
+#[derive(Debug, FromRow)]
+pub struct ProjectSettings {
+    pub project_id: i64,
+    pub assignment: Option<Json<AssignmentConfig>>,
+    pub components: Option<Json<Vec<String>>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuggestAssigneeQuery {
+    pub strategy: Option<AssignmentStrategyKind>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AssigneeCandidate {
+    pub rank: usize,
+    pub developer_id: i64,
+    pub name: String,
+    pub open_bugs: i64,
+    pub weighted_load: i64,
+    pub over_capacity: bool,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssigneeSuggestion {
+    pub bug_id: i64,
+    pub strategy: AssignmentStrategyKind,
+    pub candidates: Vec<AssigneeCandidate>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -63,6 +1051,88 @@ pub struct BugAssignment {
     pub developer_id: i64,
 }
 
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct BoardQuery {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub group_by: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub project_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BoardMove {
+    pub bug_id: i64,
+    pub group_by: String,
+    pub target: String,
+    // The board's project filter, kept for the redirect back.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub project_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Board {
+    pub group_by: String,
+    pub columns: Vec<BoardColumn>,
+    pub swimlanes: Vec<BoardSwimlane>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BoardColumn {
+    pub key: String,
+    pub title: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BoardSwimlane {
+    pub project_id: Option<i64>,
+    pub project_name: String,
+    pub cells: Vec<BoardCell>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BoardCell {
+    pub column: String,
+    pub bugs: Vec<Bug>,
+}
+
+// Why an assignment was refused; the caller picks the HTTP status.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssignmentError {
+    BugNotFound(i64),
+    DeveloperNotFound(i64),
+    DeveloperInactive(i64),
+    OverCapacity { developer_id: i64, open_bugs: i64, max_open_bugs: i64 },
+}
+
+impl AssignmentError {
+    pub fn message(&self) -> String {
+        match self {
+            AssignmentError::BugNotFound(id) => format!("Bug #{} not found", id),
+            AssignmentError::DeveloperNotFound(id) => format!("Developer #{} does not exist", id),
+            AssignmentError::DeveloperInactive(id) => {
+                format!("Developer #{} is inactive and can't be assigned bugs", id)
+            }
+            AssignmentError::OverCapacity { developer_id, open_bugs, max_open_bugs } => {
+                capacity_message(*developer_id, *open_bugs, *max_open_bugs)
+            }
+        }
+    }
+}
+
+pub fn capacity_message(developer_id: i64, open_bugs: i64, max_open_bugs: i64) -> String {
+    format!(
+        "Developer #{} already has {} open bug(s) with a capacity of {}",
+        developer_id, open_bugs, max_open_bugs
+    )
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct OrphanedReference {
+    pub table: String,
+    pub row_id: i64,
+    pub references: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
     pub success: bool,
@@ -72,15 +1142,6 @@ pub struct ApiResponse<T> {
 }
 
 impl<T> ApiResponse<T> {
-    pub fn success(data: T, message: &str) -> Self {
-        Self {
-            success: true,
-            message: message.to_string(),
-            data: Some(data),
-            bug_id: None,
-        }
-    }
-    
     pub fn success_with_id(data: T, message: &str, bug_id: i64) -> Self {
         Self {
             success: true,
@@ -114,4 +1175,20 @@ pub struct Claims {
     pub sub: String,
     pub role: String,
     pub exp: usize,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_names_are_trimmed_and_blanks_dropped() {
+        assert_eq!(split_label_names(" ui , ,backend,"), vec!["ui", "backend"]);
+        assert!(split_label_names(" , ").is_empty());
+    }
+
+    #[test]
+    fn label_names_differing_only_in_case_count_once() {
+        assert_eq!(split_label_names("Bug,bug,UI,BUG"), vec!["Bug", "UI"]);
+    }
+}
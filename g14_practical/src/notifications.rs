@@ -0,0 +1,263 @@
+use askama::Template;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Address, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::time::Duration;
+use crate::config::{SmtpConfig, SmtpTls};
+use crate::database;
+use crate::models::{Bug, Comment, PendingNotification};
+use crate::state::AppState;
+use crate::templates::{NotificationHtmlTemplate, NotificationTextTemplate};
+use crate::webhooks;
+
+// Email notifications for developers: a bug assigned to them, a status
+// change on one of their bugs, or a new comment on one. Each developer
+// chooses in their notification settings which of these they want and
+// whether to get them one email at a time or as a digest.
+//
+// Notifications are queued in SQLite by the event hub (`events.rs`) and
+// sent by a background worker, so a slow or unreachable SMTP server never
+// holds up a request. Nothing is queued unless `SMTP_HOST` is set.
+
+const KIND_ASSIGNED: &str = "assigned";
+const KIND_STATUS_CHANGED: &str = "status_changed";
+const KIND_COMMENT: &str = "comment";
+
+// How often the queue is checked when nothing wakes the worker; this is
+// also how late a digest or a retry can be.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+// A notification is dropped after this many failed sends. Retries wait
+// `RETRY_BASE_SECONDS`, doubling each time, so the last one comes about two
+// hours after the first failure.
+const MAX_ATTEMPTS: i64 = 8;
+const RETRY_BASE_SECONDS: i64 = 60;
+
+// Longer comments are cut in the email; the link leads to the rest.
+const MAX_EXCERPT_CHARS: usize = 1000;
+
+// One notification as shown in an email.
+pub struct EmailItem {
+    pub bug_id: i64,
+    pub bug_title: String,
+    pub summary: String,
+    pub detail: Option<String>,
+    pub url: String,
+}
+
+pub fn valid_address(email: &str) -> bool {
+    email.parse::<Address>().is_ok()
+}
+
+pub async fn bug_assigned(app_state: &AppState, bug: &Bug) {
+    let (Some(bug_id), Some(developer_id)) = (bug.id, bug.developer_id) else {
+        return;
+    };
+    let summary = format!(
+        "This bug was assigned to you (severity: {}, status: {}).",
+        bug.severity.as_deref().unwrap_or("none"),
+        bug.status
+    );
+    let detail = bug.description.as_deref().map(excerpt).filter(|d| !d.is_empty());
+    queue(app_state, developer_id, bug_id, KIND_ASSIGNED, &summary, detail.as_deref()).await;
+}
+
+pub async fn status_changed(app_state: &AppState, bug: &Bug, previous_status: &str) {
+    let (Some(bug_id), Some(developer_id)) = (bug.id, bug.developer_id) else {
+        return;
+    };
+    if previous_status == bug.status {
+        return;
+    }
+    let summary = format!("Status changed from {} to {}.", previous_status, bug.status);
+    queue(app_state, developer_id, bug_id, KIND_STATUS_CHANGED, &summary, None).await;
+}
+
+pub async fn comment_added(app_state: &AppState, comment: &Comment) {
+    let developer_id = match database::get_bug_by_id(&app_state.db, comment.bug_id).await {
+        Ok(Some(bug)) => bug.developer_id,
+        Ok(None) => None,
+        Err(e) => {
+            eprintln!("❌ Failed to load bug #{} for comment notification: {}", comment.bug_id, e);
+            None
+        }
+    };
+    let Some(developer_id) = developer_id else {
+        return;
+    };
+    let summary = format!("{} commented:", comment.author);
+    queue(app_state, developer_id, comment.bug_id, KIND_COMMENT, &summary, Some(&excerpt(&comment.body))).await;
+}
+
+async fn queue(
+    app_state: &AppState,
+    developer_id: i64,
+    bug_id: i64,
+    kind: &str,
+    summary: &str,
+    detail: Option<&str>,
+) {
+    if app_state.config.smtp.is_none() {
+        return;
+    }
+    match database::queue_notification(&app_state.db, developer_id, bug_id, kind, summary, detail).await {
+        Ok(true) => {
+            println!("✉️  Queued {} notification for developer #{} (bug #{})", kind, developer_id, bug_id);
+            app_state.notification_wakeup.notify_one();
+        }
+        Ok(false) => {}
+        Err(e) => eprintln!("❌ Failed to queue {} notification for developer #{}: {}", kind, developer_id, e),
+    }
+}
+
+// Starts the email worker, if SMTP is configured.
+pub fn spawn_worker(app_state: AppState) {
+    let Some(smtp) = app_state.config.smtp.clone() else {
+        println!("✉️  Email notifications are off (SMTP_HOST is not set)");
+        return;
+    };
+    let mailer = match transport(&smtp) {
+        Ok(mailer) => mailer,
+        Err(e) => {
+            eprintln!("❌ Email notifications could not start: {}", e);
+            return;
+        }
+    };
+    let from: Mailbox = match smtp.from.parse() {
+        Ok(from) => from,
+        Err(e) => {
+            eprintln!("❌ Email notifications could not start: invalid SMTP_FROM '{}': {}", smtp.from, e);
+            return;
+        }
+    };
+    println!("✉️  Email notifications via {}:{}", smtp.host, smtp.port);
+
+    tokio::spawn(async move {
+        loop {
+            let digest_minutes = app_state.config.notification_digest_minutes;
+            match database::pending_notifications(&app_state.db, MAX_ATTEMPTS, digest_minutes).await {
+                Ok(pending) => {
+                    for group in pending.chunk_by(|a, b| a.developer_id == b.developer_id) {
+                        if group[0].delivery == "digest" {
+                            // Everything waiting goes out together once the
+                            // oldest notification is due.
+                            if group.iter().any(|n| n.due) {
+                                send(&app_state, &mailer, &from, group, true).await;
+                            }
+                        } else {
+                            for notification in group {
+                                send(&app_state, &mailer, &from, std::slice::from_ref(notification), false).await;
+                            }
+                        }
+                    }
+                }
+                Err(e) => eprintln!("❌ Failed to load pending notifications: {}", e),
+            }
+            tokio::select! {
+                _ = app_state.notification_wakeup.notified() => {}
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+fn transport(smtp: &SmtpConfig) -> Result<AsyncSmtpTransport<Tokio1Executor>, lettre::transport::smtp::Error> {
+    let builder = match smtp.tls {
+        SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host),
+        SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)?,
+        SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp.host)?,
+    };
+    let mut builder = builder.port(smtp.port).timeout(Some(SEND_TIMEOUT));
+    if let Some((username, password)) = &smtp.credentials {
+        builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    Ok(builder.build())
+}
+
+// Sends one email covering `notifications`, all for the same developer,
+// and records the outcome on each of them.
+async fn send(
+    app_state: &AppState,
+    mailer: &AsyncSmtpTransport<Tokio1Executor>,
+    from: &Mailbox,
+    notifications: &[PendingNotification],
+    digest: bool,
+) {
+    let recipient = &notifications[0];
+    let ids: Vec<i64> = notifications.iter().map(|n| n.id).collect();
+    let result = match compose(app_state, from, notifications, digest) {
+        Ok(message) => mailer.send(message).await.map(|_| ()).map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+
+    let error = result.err();
+    let attempts = notifications.iter().map(|n| n.attempts).max().unwrap_or_default() + 1;
+    let retry_in_seconds = webhooks::retry_delay(RETRY_BASE_SECONDS, attempts);
+    match &error {
+        None => println!(
+            "✉️  Emailed {} notification(s) to {} <{}>",
+            ids.len(), recipient.developer_name, recipient.email
+        ),
+        Some(e) if attempts < MAX_ATTEMPTS => eprintln!(
+            "⚠️  Failed to email {} notification(s) to {} <{}> (attempt {}): {}; retrying in {}s",
+            ids.len(), recipient.developer_name, recipient.email, attempts, e, retry_in_seconds
+        ),
+        Some(e) => eprintln!(
+            "❌ Failed to email {} notification(s) to {} <{}> after {} attempt(s): {}",
+            ids.len(), recipient.developer_name, recipient.email, attempts, e
+        ),
+    }
+    let result = database::record_notification_attempt(&app_state.db, &ids, error.as_deref(), retry_in_seconds).await;
+    if let Err(e) = result {
+        eprintln!("❌ Failed to record notification email for developer #{}: {}", recipient.developer_id, e);
+    }
+}
+
+fn compose(
+    app_state: &AppState,
+    from: &Mailbox,
+    notifications: &[PendingNotification],
+    digest: bool,
+) -> Result<Message, String> {
+    let recipient = &notifications[0];
+    let address = recipient.email.parse::<Address>()
+        .map_err(|e| format!("invalid address '{}': {}", recipient.email, e))?;
+    let items: Vec<EmailItem> = notifications.iter()
+        .map(|n| EmailItem {
+            bug_id: n.bug_id,
+            bug_title: n.bug_title.clone(),
+            summary: n.summary.clone(),
+            detail: n.detail.clone(),
+            url: format!("{}/ui/bugs/{}", app_state.config.public_url, n.bug_id),
+        })
+        .collect();
+
+    let subject = if digest {
+        format!("Bug tracker digest: {} update(s)", items.len())
+    } else {
+        format!("[Bug #{}] {}", recipient.bug_id, recipient.bug_title)
+    };
+    let text = NotificationTextTemplate { developer_name: &recipient.developer_name, items: &items, digest }
+        .render()
+        .map_err(|e| e.to_string())?;
+    let html = NotificationHtmlTemplate { developer_name: &recipient.developer_name, items: &items, digest }
+        .render()
+        .map_err(|e| e.to_string())?;
+
+    Message::builder()
+        .from(from.clone())
+        .to(Mailbox::new(Some(recipient.developer_name.clone()), address))
+        .subject(subject)
+        .multipart(MultiPart::alternative_plain_html(text, html))
+        .map_err(|e| e.to_string())
+}
+
+fn excerpt(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(MAX_EXCERPT_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
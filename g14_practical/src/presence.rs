@@ -0,0 +1,510 @@
+use actix_ws::{AggregatedMessage, CloseCode, CloseReason, MessageStream, ProtocolError, Session};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{mpsc, Mutex};
+use crate::models::LoggedEvent;
+use crate::state::AppState;
+
+// Who else has a bug open. Each page showing a bug connects a WebSocket to
+// `/bugs/{id}/presence` and joins that bug's room; everyone in the room
+// sees the others come and go, whether they are viewing or editing, which
+// field they are typing in, and field values they change before saving.
+// Changes saved through any other route (the event hub's bug events and
+// comments) are pushed to the room as well, so an open edit form can warn
+// that the bug changed underneath it.
+//
+// Messages are JSON text frames with a "type":
+//   client → server: viewing, editing, typing {field}, field_changed {field, value}
+//   server → client: welcome {session, members}, joined {member},
+//                    presence {member}, left {session, username},
+//                    field_changed {session, username, field, value},
+//                    bug_changed {event, data}, resync, error {message}
+
+// The server pings this often and drops clients silent for `CLIENT_TIMEOUT`.
+const HEARTBEAT: Duration = Duration::from_secs(10);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// A typing indicator not refreshed for this long is cleared.
+const TYPING_TIMEOUT: Duration = Duration::from_secs(10);
+
+const MAX_MESSAGE_BYTES: usize = 64 * 1024;
+
+// Messages from the rest of the room wait in a member's outbox until its
+// own task writes them out. A member whose outbox fills up isn't reading
+// and is dropped, so one stalled client never holds up the room; the same
+// goes for a write that takes longer than `SEND_TIMEOUT`.
+const OUTBOX_CAPACITY: usize = 64;
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+// A ticket must be redeemed this soon after it is issued.
+pub const TICKET_TTL: Duration = Duration::from_secs(30);
+
+// Fields a typing indicator or field change may name.
+pub const BUG_FIELDS: [&str; 8] = [
+    "title", "description", "severity", "status", "developer_id", "project_id", "component", "labels",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Activity {
+    Viewing,
+    Editing,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Member {
+    pub session: u64,
+    pub username: String,
+    pub activity: Activity,
+    // The field being typed in, if any.
+    pub typing: Option<String>,
+    #[serde(skip)]
+    typing_since: Option<Instant>,
+}
+
+struct Connection {
+    member: Member,
+    outbox: mpsc::Sender<String>,
+}
+
+// Lets a browser open one socket for one bug as `username`.
+struct Ticket {
+    bug_id: i64,
+    username: String,
+    issued: Instant,
+}
+
+// The open rooms, by bug id. A room is removed with its last member.
+#[derive(Default)]
+pub struct Rooms {
+    rooms: Mutex<HashMap<i64, HashMap<u64, Connection>>>,
+    next_session: AtomicU64,
+    tickets: Mutex<HashMap<String, Ticket>>,
+}
+
+impl Rooms {
+    // A random single-use ticket for `bug_id`, valid for `TICKET_TTL`.
+    pub async fn issue_ticket(&self, bug_id: i64, username: &str) -> String {
+        let ticket = uuid::Uuid::new_v4().simple().to_string();
+        let mut tickets = self.tickets.lock().await;
+        tickets.retain(|_, t| t.issued.elapsed() <= TICKET_TTL);
+        tickets.insert(ticket.clone(), Ticket { bug_id, username: username.to_string(), issued: Instant::now() });
+        ticket
+    }
+
+    // Uses up the ticket and returns whose it was, if it is for `bug_id`
+    // and hasn't expired.
+    pub async fn redeem_ticket(&self, bug_id: i64, ticket: &str) -> Option<String> {
+        let ticket = self.tickets.lock().await.remove(ticket)?;
+        (ticket.bug_id == bug_id && ticket.issued.elapsed() <= TICKET_TTL).then_some(ticket.username)
+    }
+
+    // Adds the connection and returns its member entry and everyone in the
+    // room, itself included.
+    async fn join(&self, bug_id: i64, username: &str, outbox: mpsc::Sender<String>) -> (Member, Vec<Member>) {
+        let member = Member {
+            session: self.next_session.fetch_add(1, Ordering::Relaxed) + 1,
+            username: username.to_string(),
+            activity: Activity::Viewing,
+            typing: None,
+            typing_since: None,
+        };
+        let mut rooms = self.rooms.lock().await;
+        let room = rooms.entry(bug_id).or_default();
+        room.insert(member.session, Connection { member: member.clone(), outbox });
+        let mut members: Vec<Member> = room.values().map(|c| c.member.clone()).collect();
+        members.sort_by_key(|m| m.session);
+        (member, members)
+    }
+
+    async fn leave(&self, bug_id: i64, session: u64) {
+        let mut rooms = self.rooms.lock().await;
+        let Some(room) = rooms.get_mut(&bug_id) else {
+            return;
+        };
+        room.remove(&session);
+        if room.is_empty() {
+            rooms.remove(&bug_id);
+        }
+    }
+
+    // Applies `change` to a member and returns the result.
+    async fn update(&self, bug_id: i64, session: u64, change: impl FnOnce(&mut Member)) -> Option<Member> {
+        let mut rooms = self.rooms.lock().await;
+        let connection = rooms.get_mut(&bug_id)?.get_mut(&session)?;
+        change(&mut connection.member);
+        Some(connection.member.clone())
+    }
+
+    // Clears a typing indicator older than `TYPING_TIMEOUT`, returning the
+    // member if it did.
+    async fn expire_typing(&self, bug_id: i64, session: u64) -> Option<Member> {
+        let mut rooms = self.rooms.lock().await;
+        let member = &mut rooms.get_mut(&bug_id)?.get_mut(&session)?.member;
+        if member.typing_since.is_none_or(|since| since.elapsed() <= TYPING_TIMEOUT) {
+            return None;
+        }
+        member.typing = None;
+        member.typing_since = None;
+        Some(member.clone())
+    }
+
+    // Queues a message for everyone in the room but `except`, without
+    // waiting on any of them. Members with a full outbox are removed; their
+    // tasks see the outbox close and disconnect them.
+    async fn broadcast(&self, bug_id: i64, except: u64, message: &ServerMessage<'_>) {
+        let text = message.to_json();
+        let mut rooms = self.rooms.lock().await;
+        let Some(room) = rooms.get_mut(&bug_id) else {
+            return;
+        };
+        room.retain(|&session, connection| {
+            session == except || match connection.outbox.try_send(text.clone()) {
+                Ok(()) => true,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    println!("👥 {} fell behind on bug #{}; dropping them", connection.member.username, bug_id);
+                    false
+                }
+                // Already gone; its own task removes it.
+                Err(mpsc::error::TrySendError::Closed(_)) => true,
+            }
+        });
+        if room.is_empty() {
+            rooms.remove(&bug_id);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Viewing,
+    Editing,
+    // No field (or null) means typing stopped.
+    Typing {
+        #[serde(default)]
+        field: Option<String>,
+    },
+    FieldChanged {
+        field: String,
+        #[serde(default)]
+        value: Value,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage<'a> {
+    Welcome { bug_id: i64, session: u64, members: &'a [Member] },
+    Joined { member: &'a Member },
+    Presence { member: &'a Member },
+    Left { session: u64, username: &'a str },
+    FieldChanged { session: u64, username: &'a str, field: &'a str, value: &'a Value },
+    BugChanged { event: &'a str, data: &'a Value },
+    // Changes to the bug may have been missed; reload it.
+    Resync,
+    Error { message: &'a str },
+}
+
+impl ServerMessage<'_> {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("presence messages always serialize")
+    }
+}
+
+// Runs one connection until either side closes it. `MessageStream` is not
+// `Send`, so this runs on the worker's local task set.
+pub fn spawn_session(app_state: AppState, bug_id: i64, username: String, session: Session, messages: MessageStream) {
+    actix_web::rt::spawn(async move {
+        let mut session = session;
+        let reason = run(&app_state, bug_id, &username, &mut session, messages).await;
+        let _ = session.close(reason).await;
+    });
+}
+
+async fn run(
+    app_state: &AppState,
+    bug_id: i64,
+    username: &str,
+    session: &mut Session,
+    messages: MessageStream,
+) -> Option<CloseReason> {
+    let rooms = &app_state.presence;
+    let mut messages = messages
+        .max_frame_size(MAX_MESSAGE_BYTES)
+        .aggregate_continuations()
+        .max_continuation_size(MAX_MESSAGE_BYTES);
+    let mut events = app_state.event_stream.subscribe();
+    let (outbox, mut inbox) = mpsc::channel(OUTBOX_CAPACITY);
+
+    let (me, members) = rooms.join(bug_id, username, outbox).await;
+    let id = me.session;
+    println!("👥 {} opened bug #{} ({} connected)", username, bug_id, members.len());
+    let welcome = ServerMessage::Welcome { bug_id, session: id, members: &members };
+    if !send(session, welcome.to_json()).await {
+        rooms.leave(bug_id, id).await;
+        return None;
+    }
+    rooms.broadcast(bug_id, id, &ServerMessage::Joined { member: &me }).await;
+    let reason = serve(app_state, bug_id, id, session, &mut messages, &mut events, &mut inbox).await;
+
+    // Announced even when the room has already dropped this member.
+    rooms.leave(bug_id, id).await;
+    println!("👥 {} left bug #{}", username, bug_id);
+    rooms.broadcast(bug_id, id, &ServerMessage::Left { session: id, username }).await;
+    reason
+}
+
+// Writes to this client, giving up if it takes longer than `SEND_TIMEOUT`.
+// `false` means the connection is done for.
+async fn send(session: &mut Session, text: String) -> bool {
+    matches!(tokio::time::timeout(SEND_TIMEOUT, session.text(text)).await, Ok(Ok(())))
+}
+
+async fn serve(
+    app_state: &AppState,
+    bug_id: i64,
+    id: u64,
+    session: &mut Session,
+    messages: &mut actix_ws::AggregatedMessageStream,
+    events: &mut tokio::sync::broadcast::Receiver<LoggedEvent>,
+    inbox: &mut mpsc::Receiver<String>,
+) -> Option<CloseReason> {
+    let rooms = &app_state.presence;
+    let mut last_heard = Instant::now();
+    let mut heartbeat = tokio::time::interval_at(tokio::time::Instant::now() + HEARTBEAT, HEARTBEAT);
+
+    loop {
+        tokio::select! {
+            message = messages.recv() => match message {
+                Some(Ok(AggregatedMessage::Text(text))) => {
+                    last_heard = Instant::now();
+                    if let Err(message) = handle(app_state, bug_id, id, &text).await
+                        && !send(session, ServerMessage::Error { message: &message }.to_json()).await
+                    {
+                        return None;
+                    }
+                }
+                Some(Ok(AggregatedMessage::Binary(_))) => {
+                    let error = ServerMessage::Error { message: "Only JSON text messages are accepted" };
+                    if !send(session, error.to_json()).await {
+                        return None;
+                    }
+                }
+                Some(Ok(AggregatedMessage::Ping(bytes))) => {
+                    last_heard = Instant::now();
+                    if !matches!(tokio::time::timeout(SEND_TIMEOUT, session.pong(&bytes)).await, Ok(Ok(()))) {
+                        return None;
+                    }
+                }
+                Some(Ok(AggregatedMessage::Pong(_))) => last_heard = Instant::now(),
+                Some(Ok(AggregatedMessage::Close(reason))) => return reason,
+                Some(Err(e)) => {
+                    let code = match e {
+                        ProtocolError::Overflow => CloseCode::Size,
+                        _ => CloseCode::Protocol,
+                    };
+                    return Some(CloseReason { code, description: Some(e.to_string()) });
+                }
+                None => return None,
+            },
+            text = inbox.recv() => match text {
+                Some(text) => {
+                    if !send(session, text).await {
+                        return None;
+                    }
+                }
+                None => {
+                    return Some(CloseReason { code: CloseCode::Again, description: Some("Fell behind".to_string()) });
+                }
+            },
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let Some(data) = change_to(&event, bug_id) else {
+                        continue;
+                    };
+                    let message = ServerMessage::BugChanged { event: &event.event, data: &data };
+                    if !send(session, message.to_json()).await {
+                        return None;
+                    }
+                    if event.event == "bug.deleted" {
+                        return Some(CloseReason { code: CloseCode::Normal, description: Some("Bug deleted".to_string()) });
+                    }
+                }
+                // Some of the missed events may have been about this bug.
+                Err(RecvError::Lagged(_)) => {
+                    if !send(session, ServerMessage::Resync.to_json()).await {
+                        return None;
+                    }
+                }
+                Err(RecvError::Closed) => return None,
+            },
+            _ = heartbeat.tick() => {
+                if last_heard.elapsed() > CLIENT_TIMEOUT {
+                    return Some(CloseReason { code: CloseCode::Away, description: Some("Timed out".to_string()) });
+                }
+                if !matches!(tokio::time::timeout(SEND_TIMEOUT, session.ping(b"")).await, Ok(Ok(()))) {
+                    return None;
+                }
+                if let Some(member) = rooms.expire_typing(bug_id, id).await {
+                    rooms.broadcast(bug_id, id, &ServerMessage::Presence { member: &member }).await;
+                }
+            }
+        }
+    }
+}
+
+// One message from the client; the error is sent back to it.
+async fn handle(app_state: &AppState, bug_id: i64, id: u64, text: &str) -> Result<(), String> {
+    let rooms = &app_state.presence;
+    let message: ClientMessage = serde_json::from_str(text).map_err(|e| format!("Unrecognised message: {}", e))?;
+    match message {
+        ClientMessage::Viewing | ClientMessage::Editing => {
+            let activity = match message {
+                ClientMessage::Editing => Activity::Editing,
+                _ => Activity::Viewing,
+            };
+            if let Some(member) = rooms.update(bug_id, id, |member| member.activity = activity).await {
+                rooms.broadcast(bug_id, id, &ServerMessage::Presence { member: &member }).await;
+            }
+        }
+        ClientMessage::Typing { field } => {
+            if let Some(field) = &field {
+                check_field(field)?;
+            }
+            if let Some(member) = rooms.update(bug_id, id, |member| {
+                member.typing_since = field.is_some().then(Instant::now);
+                member.typing = field;
+            }).await {
+                rooms.broadcast(bug_id, id, &ServerMessage::Presence { member: &member }).await;
+            }
+        }
+        ClientMessage::FieldChanged { field, value } => {
+            check_field(&field)?;
+            if let Some(member) = rooms.update(bug_id, id, |_| {}).await {
+                let message = ServerMessage::FieldChanged { session: id, username: &member.username, field: &field, value: &value };
+                rooms.broadcast(bug_id, id, &message).await;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_field(field: &str) -> Result<(), String> {
+    if BUG_FIELDS.contains(&field) {
+        Ok(())
+    } else {
+        Err(format!("Unknown field '{}'; use one of {}", field, BUG_FIELDS.join(", ")))
+    }
+}
+
+// The event's `data` if it is about `bug_id`: a bug event carries the bug,
+// a deletion or comment its id.
+fn change_to(event: &LoggedEvent, bug_id: i64) -> Option<Value> {
+    let mut payload: Value = serde_json::from_str(&event.payload).ok()?;
+    let data = payload.get_mut("data")?.take();
+    let about = data.get("bug_id")
+        .or_else(|| data.get("bug").and_then(|bug| bug.get("id")))
+        .and_then(Value::as_i64)?;
+    (about == bug_id).then_some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn logged(event: &str, data: Value) -> LoggedEvent {
+        LoggedEvent { id: 1, event: event.to_string(), payload: json!({ "event": event, "data": data }).to_string() }
+    }
+
+    async fn sessions(rooms: &Rooms, bug_id: i64) -> Vec<u64> {
+        let rooms = rooms.rooms.lock().await;
+        let mut sessions: Vec<u64> = rooms.get(&bug_id).into_iter().flat_map(|room| room.keys().copied()).collect();
+        sessions.sort_unstable();
+        sessions
+    }
+
+    #[tokio::test]
+    async fn tickets_are_single_use_and_per_bug() {
+        let rooms = Rooms::default();
+        let ticket = rooms.issue_ticket(7, "dana").await;
+        assert_eq!(rooms.redeem_ticket(7, &ticket).await.as_deref(), Some("dana"));
+        assert_eq!(rooms.redeem_ticket(7, &ticket).await, None);
+
+        // A ticket tried on another bug is used up all the same.
+        let ticket = rooms.issue_ticket(7, "dana").await;
+        assert_eq!(rooms.redeem_ticket(8, &ticket).await, None);
+        assert_eq!(rooms.redeem_ticket(7, &ticket).await, None);
+    }
+
+    #[tokio::test]
+    async fn expired_tickets_are_refused_and_pruned() {
+        let rooms = Rooms::default();
+        let issued = Instant::now() - TICKET_TTL - Duration::from_secs(1);
+        for name in ["old", "older"] {
+            let ticket = Ticket { bug_id: 7, username: "dana".to_string(), issued };
+            rooms.tickets.lock().await.insert(name.to_string(), ticket);
+        }
+
+        assert_eq!(rooms.redeem_ticket(7, "old").await, None);
+        rooms.issue_ticket(7, "dana").await;
+        assert!(!rooms.tickets.lock().await.contains_key("older"));
+    }
+
+    #[tokio::test]
+    async fn broadcast_drops_members_whose_outbox_is_full() {
+        let rooms = Rooms::default();
+        let (slow_outbox, _slow_inbox) = mpsc::channel(1);
+        let (fast_outbox, mut fast_inbox) = mpsc::channel(8);
+        let (gone_outbox, gone_inbox) = mpsc::channel(1);
+        let (slow, _) = rooms.join(7, "slow", slow_outbox).await;
+        let (fast, _) = rooms.join(7, "fast", fast_outbox).await;
+        let (gone, _) = rooms.join(7, "gone", gone_outbox).await;
+        drop(gone_inbox);
+        let (sender, _) = rooms.join(7, "sender", mpsc::channel(1).0).await;
+
+        rooms.broadcast(7, sender.session, &ServerMessage::Resync).await;
+        assert_eq!(sessions(&rooms, 7).await, vec![slow.session, fast.session, gone.session, sender.session]);
+        rooms.broadcast(7, sender.session, &ServerMessage::Resync).await;
+        // The closed outbox is left for its own task to remove.
+        assert_eq!(sessions(&rooms, 7).await, vec![fast.session, gone.session, sender.session]);
+        assert_eq!(fast_inbox.recv().await.as_deref(), Some(r#"{"type":"resync"}"#));
+        assert_eq!(fast_inbox.try_recv().as_deref(), Ok(r#"{"type":"resync"}"#));
+    }
+
+    #[tokio::test]
+    async fn rooms_close_with_their_last_member() {
+        let rooms = Rooms::default();
+        let (member, members) = rooms.join(7, "dana", mpsc::channel(1).0).await;
+        assert_eq!(members.len(), 1);
+        rooms.leave(7, member.session).await;
+        assert!(rooms.rooms.lock().await.is_empty());
+    }
+
+    #[test]
+    fn changes_are_matched_to_the_bug_they_are_about() {
+        let updated = logged("bug.updated", json!({ "bug": { "id": 7, "title": "Crash" } }));
+        assert_eq!(change_to(&updated, 7), Some(json!({ "bug": { "id": 7, "title": "Crash" } })));
+        assert_eq!(change_to(&updated, 8), None);
+
+        let comment = logged("comment.created", json!({ "bug_id": 7, "comment": { "id": 3 } }));
+        assert_eq!(change_to(&comment, 7).unwrap()["comment"]["id"], 3);
+        assert_eq!(change_to(&logged("bug.deleted", json!({ "bug_id": 8 })), 7), None);
+
+        let garbled = LoggedEvent { id: 1, event: "bug.updated".to_string(), payload: "not json".to_string() };
+        assert_eq!(change_to(&garbled, 7), None);
+    }
+
+    #[test]
+    fn only_bug_fields_may_be_named() {
+        assert!(check_field("description").is_ok());
+        assert!(check_field("labels").is_ok());
+        assert!(check_field("password").unwrap_err().starts_with("Unknown field 'password'"));
+        assert!(check_field("Title").is_err());
+    }
+}
@@ -0,0 +1,198 @@
+use actix_web::web::Bytes;
+use futures_util::Stream;
+use std::time::Duration;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::mpsc;
+use crate::database;
+use crate::models::LoggedEvent;
+use crate::state::AppState;
+
+// `GET /events`: a Server-Sent Events stream of everything published by
+// the event hub (`events.rs`), optionally only for one project. Each event
+// is sent as
+//
+//   id: 42
+//   event: bug.updated
+//   data: {"id":"...","event":"bug.updated","occurred_at":"...","data":{...}}
+//
+// with the same JSON as the webhook payload. The `id` is the event's place
+// in the event log: a client that reconnects with `Last-Event-ID` (as
+// browsers' EventSource does by itself) is first sent what it missed, as
+// far back as the log goes. Idle streams get a comment line every
+// `KEEP_ALIVE` so proxies don't close them.
+
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+// How long clients wait before reconnecting, sent as the stream's `retry`.
+const RETRY_MILLISECONDS: u64 = 3000;
+
+// Missed events are read from the log this many at a time.
+const REPLAY_PAGE: i64 = 500;
+
+// Subscribes straight away, so nothing published while the stream is
+// being set up is lost. Events after `last_event_id` are replayed first;
+// without one the stream starts with the next event.
+pub async fn stream(
+    app_state: AppState,
+    project_id: Option<i64>,
+    last_event_id: Option<i64>,
+) -> Result<impl Stream<Item = Result<Bytes, actix_web::Error>> + 'static, sqlx::Error> {
+    let after = match last_event_id {
+        Some(id) => id,
+        None => database::latest_event_id(&app_state.db).await?,
+    };
+    let mut events = app_state.event_stream.subscribe();
+    let (sender, receiver) = mpsc::channel::<Bytes>(64);
+
+    tokio::spawn(async move {
+        if sender.send(Bytes::from(format!("retry: {}\n\n", RETRY_MILLISECONDS))).await.is_err() {
+            return;
+        }
+        // Everything up to `replayed` has been sent (or wasn't wanted).
+        let Some(mut replayed) = replay(&app_state, &sender, after, project_id).await else {
+            return;
+        };
+        let mut keep_alive = tokio::time::interval_at(tokio::time::Instant::now() + KEEP_ALIVE, KEEP_ALIVE);
+
+        loop {
+            tokio::select! {
+                // The channel only says that something was published: ids
+                // are taken when an event is logged, but concurrent
+                // publishers can broadcast them out of order, so events are
+                // always read from the log, where they are in order.
+                received = events.recv() => {
+                    match received {
+                        Ok(_) => {}
+                        Err(RecvError::Lagged(missed)) => {
+                            println!("⚠️  Event stream fell {} event(s) behind; catching up from the log", missed);
+                        }
+                        Err(RecvError::Closed) => return,
+                    }
+                    // One read covers whatever else is already waiting.
+                    while !matches!(events.try_recv(), Err(TryRecvError::Empty | TryRecvError::Closed)) {}
+                    match replay(&app_state, &sender, replayed, project_id).await {
+                        Some(last) => replayed = last,
+                        None => return,
+                    }
+                }
+                _ = keep_alive.tick() => {
+                    if sender.send(Bytes::from_static(b": ping\n\n")).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(futures_util::stream::unfold(receiver, |mut receiver| async move {
+        let chunk = receiver.recv().await?;
+        Some((Ok(chunk), receiver))
+    }))
+}
+
+// Sends the logged events after `after_id` and returns the id it got up
+// to, or `None` once the client has gone.
+async fn replay(
+    app_state: &AppState,
+    sender: &mpsc::Sender<Bytes>,
+    after_id: i64,
+    project_id: Option<i64>,
+) -> Option<i64> {
+    let mut last = after_id;
+    loop {
+        let page = match database::events_since(&app_state.db, last, project_id, REPLAY_PAGE).await {
+            Ok(page) => page,
+            Err(e) => {
+                eprintln!("❌ Failed to replay events after #{}: {}", last, e);
+                return Some(last);
+            }
+        };
+        for event in &page {
+            sender.send(frame(event)).await.ok()?;
+            last = event.id;
+        }
+        if (page.len() as i64) < REPLAY_PAGE {
+            return Some(last);
+        }
+    }
+}
+
+// Payloads are single-line JSON, so one `data:` line holds each.
+fn frame(event: &LoggedEvent) -> Bytes {
+    Bytes::from(format!("id: {}\nevent: {}\ndata: {}\n\n", event.id, event.event, event.payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use futures_util::StreamExt;
+
+    async fn app_state() -> AppState {
+        AppState::new(database::test_pool().await, Config::from_env())
+    }
+
+    async fn log(app_state: &AppState, project_id: Option<i64>) -> i64 {
+        database::log_event(&app_state.db, "bug.updated", project_id, "{}").await.unwrap()
+    }
+
+    fn frame_id(frame: &Bytes) -> i64 {
+        let text = std::str::from_utf8(frame).unwrap();
+        text.strip_prefix("id: ").and_then(|rest| rest.split('\n').next()).unwrap().parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn replay_sends_missed_events_in_order_across_pages() {
+        let app_state = app_state().await;
+        for n in 0..REPLAY_PAGE + 5 {
+            log(&app_state, Some(1 + n % 2)).await;
+        }
+        let (sender, mut receiver) = mpsc::channel(2 * REPLAY_PAGE as usize);
+
+        let last = replay(&app_state, &sender, 3, None).await;
+        drop(sender);
+        let mut ids = Vec::new();
+        while let Some(frame) = receiver.recv().await {
+            ids.push(frame_id(&frame));
+        }
+        assert_eq!(ids, (4..=REPLAY_PAGE + 5).collect::<Vec<_>>());
+        assert_eq!(last, Some(REPLAY_PAGE + 5));
+    }
+
+    #[tokio::test]
+    async fn replay_skips_other_projects() {
+        let app_state = app_state().await;
+        for project_id in [Some(1), Some(2), None, Some(1)] {
+            log(&app_state, project_id).await;
+        }
+        let (sender, mut receiver) = mpsc::channel(8);
+
+        assert_eq!(replay(&app_state, &sender, 0, Some(1)).await, Some(4));
+        drop(sender);
+        assert_eq!(frame_id(&receiver.recv().await.unwrap()), 1);
+        assert_eq!(frame_id(&receiver.recv().await.unwrap()), 4);
+        assert!(receiver.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn live_events_follow_log_order_when_broadcast_out_of_order() {
+        let app_state = app_state().await;
+        for _ in 0..3 {
+            log(&app_state, None).await;
+        }
+        let mut events = Box::pin(stream(app_state.clone(), None, Some(1)).await.unwrap());
+        let mut next_frame = async || {
+            tokio::time::timeout(Duration::from_secs(5), events.next()).await.unwrap().unwrap().unwrap()
+        };
+
+        assert!(next_frame().await.starts_with(b"retry: "));
+        assert_eq!(frame_id(&next_frame().await), 2);
+        assert_eq!(frame_id(&next_frame().await), 3);
+
+        // Event 5 is broadcast before event 4; the stream still sends 4 first.
+        let (fourth, fifth) = (log(&app_state, None).await, log(&app_state, None).await);
+        let _ = app_state.event_stream.send(LoggedEvent { id: fifth, event: "bug.updated".to_string(), payload: "{}".to_string() });
+        assert_eq!(frame_id(&next_frame().await), fourth);
+        assert_eq!(frame_id(&next_frame().await), fifth);
+    }
+}
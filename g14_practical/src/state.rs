@@ -1,45 +1,108 @@
-use crate::models::Project;
+use crate::config::Config;
+use crate::database;
+use crate::models::{AssignmentConfig, LoggedEvent, Project};
+use crate::presence::Rooms;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, Mutex, Notify, RwLock};
+
+// Events buffered per live stream; a stream that falls further behind
+// catches up from the event log.
+const EVENT_STREAM_CAPACITY: usize = 256;
 
 #[derive(Clone)]
 pub struct AppState {
     pub projects: Arc<RwLock<Vec<Project>>>,
     pub db: sqlx::SqlitePool,
+    pub config: Arc<Config>,
+    // Last developer auto-assigned per project, for round-robin rotation.
+    pub round_robin: Arc<Mutex<HashMap<i64, i64>>>,
+    // Wakes the webhook worker when deliveries are queued.
+    pub webhook_wakeup: Arc<Notify>,
+    // Wakes the email worker when notifications are queued.
+    pub notification_wakeup: Arc<Notify>,
+    // Every published event, for the `GET /events` streams.
+    pub event_stream: broadcast::Sender<LoggedEvent>,
+    // Who has which bug open, for the presence WebSockets.
+    pub presence: Arc<Rooms>,
+    // Guards attachment files, which uploads share by hash: uploads hold it
+    // for reading until their rows are saved, removals for writing.
+    pub blobs: Arc<RwLock<()>>,
 }
 
 impl AppState {
-    pub fn new(db: sqlx::SqlitePool) -> Self {
+    pub fn new(db: sqlx::SqlitePool, config: Config) -> Self {
         let initial_projects = vec![
             Project {
                 id: 1,
                 name: "Frontend Development".to_string(),
                 description: "User interface and UX improvements".to_string(),
                 active: true,
+                assignment: AssignmentConfig::default(),
+                components: vec!["auth".to_string(), "web-ui".to_string()],
             },
             Project {
                 id: 2,
                 name: "Backend API".to_string(),
                 description: "Server-side development and database optimization".to_string(),
                 active: true,
+                assignment: AssignmentConfig::default(),
+                components: vec!["api".to_string(), "auth".to_string(), "database".to_string()],
             },
             Project {
                 id: 3,
                 name: "Mobile App".to_string(),
                 description: "iOS and Android application development".to_string(),
                 active: true,
+                assignment: AssignmentConfig::default(),
+                components: vec!["mobile-ui".to_string(), "push-notifications".to_string()],
             },
         ];
 
         Self {
             projects: Arc::new(RwLock::new(initial_projects)),
             db,
+            config: Arc::new(config),
+            round_robin: Arc::new(Mutex::new(HashMap::new())),
+            webhook_wakeup: Arc::new(Notify::new()),
+            notification_wakeup: Arc::new(Notify::new()),
+            event_stream: broadcast::channel(EVENT_STREAM_CAPACITY).0,
+            presence: Arc::new(Rooms::default()),
+            blobs: Arc::new(RwLock::new(())),
         }
     }
     
+    // Applies the settings saved in `project_settings` to the projects that
+    // exist. Projects added with `POST /projects` only live in memory, so
+    // after a restart their settings are skipped until the id is reused.
+    pub async fn load_project_settings(&self) -> Result<(), sqlx::Error> {
+        let saved = database::get_project_settings(&self.db).await?;
+        let mut projects = self.projects.write().await;
+        for settings in saved {
+            let Some(project) = projects.iter_mut().find(|p| p.id == settings.project_id) else {
+                continue;
+            };
+            if let Some(assignment) = settings.assignment {
+                project.assignment = assignment.0;
+            }
+            if let Some(components) = settings.components {
+                project.components = components.0;
+            }
+        }
+        Ok(())
+    }
+
     pub async fn get_next_project_id(&self) -> i64 {
         let projects = self.projects.read().await;
         let max_id = projects.iter().map(|p| p.id).max().unwrap_or(0);
         max_id + 1  // Don't hold the lock while returning
     }
+
+    pub async fn get_project(&self, project_id: i64) -> Option<Project> {
+        self.projects.read().await.iter().find(|p| p.id == project_id).cloned()
+    }
+
+    pub async fn has_project(&self, project_id: i64) -> bool {
+        self.projects.read().await.iter().any(|p| p.id == project_id)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,265 @@
+use actix_web::{http::StatusCode, HttpResponse};
+use askama::Template;
+use crate::board::{self, GROUP_BY_DEVELOPER};
+use crate::notifications::EmailItem;
+use crate::models::{
+    allowed_transitions, Attachment, Board, Comment, Bug, BugFilter, BugLinkView, BugForm, Developer, DeveloperSummary, FieldError,
+    Project,
+    SEVERITIES, STATUSES,
+};
+
+// Page templates live in `templates/` and are checked at compile time.
+// Askama escapes every `{{ }}` expression in `.html` templates, so user
+// supplied titles and names can be rendered directly.
+
+#[derive(Template)]
+#[template(path = "homepage.html")]
+pub struct HomepageTemplate;
+
+#[derive(Template)]
+#[template(path = "bugs/assign_form.html")]
+pub struct BugAssignFormTemplate<'a> {
+    pub bugs: &'a [Bug],
+    pub developers: &'a [DeveloperSummary],
+}
+
+#[derive(Template)]
+#[template(path = "bugs/assign_result.html")]
+pub struct AssignResultTemplate {
+    pub success: bool,
+    pub message: String,
+    pub warning: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "bugs/list.html")]
+pub struct BugListTemplate<'a> {
+    pub bugs: &'a [Bug],
+    pub developers: &'a [Developer],
+    pub projects: &'a [Project],
+    pub filter: &'a BugFilter,
+}
+
+impl BugListTemplate<'_> {
+    pub fn developer_name(&self, developer_id: &Option<i64>) -> String {
+        developer_label(self.developers, *developer_id)
+    }
+
+    pub fn project_name(&self, project_id: &Option<i64>) -> String {
+        project_label(self.projects, *project_id)
+    }
+
+    pub fn statuses(&self) -> &'static [&'static str] {
+        &STATUSES
+    }
+
+    pub fn status_selected(&self, status: &str) -> bool {
+        self.filter.status.as_deref().is_some_and(|s| s.eq_ignore_ascii_case(status))
+    }
+
+    pub fn project_selected(&self, project_id: &i64) -> bool {
+        self.filter.project_id == Some(*project_id)
+    }
+
+    pub fn severities(&self) -> &'static [&'static str] {
+        &SEVERITIES
+    }
+
+    pub fn severity_selected(&self, severity: &str) -> bool {
+        self.filter.severity.as_deref().is_some_and(|s| s.eq_ignore_ascii_case(severity))
+    }
+
+    pub fn developer_selected(&self, developer_id: &i64) -> bool {
+        self.filter.developer_id == Some(*developer_id)
+    }
+
+    pub fn unassigned_checked(&self) -> bool {
+        self.filter.unassigned == Some(true)
+    }
+
+    pub fn q(&self) -> &str {
+        self.filter.q.as_deref().unwrap_or("")
+    }
+
+    pub fn reported_by(&self) -> &str {
+        self.filter.reported_by.as_deref().unwrap_or("")
+    }
+
+    pub fn component(&self) -> &str {
+        self.filter.component.as_deref().unwrap_or("")
+    }
+
+    pub fn labels_any(&self) -> &str {
+        self.filter.labels_any.as_deref().unwrap_or("")
+    }
+}
+
+#[derive(Template)]
+#[template(path = "bugs/detail.html")]
+pub struct BugDetailTemplate<'a> {
+    pub bug: &'a Bug,
+    pub developers: &'a [Developer],
+    pub projects: &'a [Project],
+    pub links: &'a [BugLinkView],
+    pub attachments: &'a [Attachment],
+    pub comments: &'a [Comment],
+    pub notice: Option<&'static str>,
+}
+
+impl BugDetailTemplate<'_> {
+    pub fn developer_name(&self, developer_id: &Option<i64>) -> String {
+        developer_label(self.developers, *developer_id)
+    }
+
+    pub fn project_name(&self, project_id: &Option<i64>) -> String {
+        project_label(self.projects, *project_id)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "bugs/form.html")]
+pub struct BugFormTemplate<'a> {
+    pub heading: &'a str,
+    pub action: String,
+    pub submit_label: &'a str,
+    pub bug_id: Option<i64>,
+    pub form: &'a BugForm,
+    pub errors: &'a [FieldError],
+    pub developers: &'a [Developer],
+    pub projects: &'a [Project],
+}
+
+impl BugFormTemplate<'_> {
+    pub fn error_for(&self, field: &str) -> Option<&str> {
+        self.errors.iter()
+            .find(|e| e.field == field)
+            .map(|e| e.message.as_str())
+    }
+
+    pub fn severities(&self) -> &'static [&'static str] {
+        &SEVERITIES
+    }
+
+    pub fn severity_selected(&self, severity: &str) -> bool {
+        self.form.severity.eq_ignore_ascii_case(severity)
+    }
+
+    pub fn developer_selected(&self, developer_id: &i64) -> bool {
+        self.form.developer_id == Some(*developer_id)
+    }
+
+    pub fn project_selected(&self, project_id: &i64) -> bool {
+        self.form.project_id == Some(*project_id)
+    }
+
+    pub fn component(&self) -> &str {
+        self.form.component.as_deref().unwrap_or("")
+    }
+
+    // Every component any project lists, for the input's suggestions.
+    pub fn known_components(&self) -> Vec<&str> {
+        let mut components: Vec<&str> = self.projects.iter()
+            .flat_map(|p| p.components.iter().map(String::as_str))
+            .collect();
+        components.sort_unstable();
+        components.dedup();
+        components
+    }
+}
+
+#[derive(Template)]
+#[template(path = "board.html")]
+pub struct BoardTemplate<'a> {
+    pub board: &'a Board,
+    pub developers: &'a [Developer],
+    pub projects: &'a [Project],
+    pub project_id: Option<i64>,
+}
+
+impl BoardTemplate<'_> {
+    pub fn is_developer_view(&self) -> bool {
+        self.board.group_by == GROUP_BY_DEVELOPER
+    }
+
+    pub fn project_selected(&self, project_id: &i64) -> bool {
+        self.project_id == Some(*project_id)
+    }
+
+    pub fn column_title(&self, key: &str) -> String {
+        self.board.columns.iter()
+            .find(|c| c.key == key)
+            .map(|c| c.title.clone())
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    // Options for the no-JavaScript "Move" form on each card.
+    pub fn move_targets(&self, bug: &Bug) -> Vec<(String, String)> {
+        let current = board::column_key(bug, &self.board.group_by);
+        if self.is_developer_view() {
+            self.developers.iter()
+                .filter(|d| d.active && d.id.to_string() != current)
+                .map(|d| (d.id.to_string(), d.name.clone()))
+                .collect()
+        } else {
+            allowed_transitions(&bug.status).iter()
+                .map(|s| (s.to_string(), s.to_string()))
+                .collect()
+        }
+    }
+}
+
+// Notification emails: a plain text and an HTML part with the same
+// content. The text template is not escaped.
+#[derive(Template)]
+#[template(path = "email/notification.txt")]
+pub struct NotificationTextTemplate<'a> {
+    pub developer_name: &'a str,
+    pub items: &'a [EmailItem],
+    pub digest: bool,
+}
+
+#[derive(Template)]
+#[template(path = "email/notification.html")]
+pub struct NotificationHtmlTemplate<'a> {
+    pub developer_name: &'a str,
+    pub items: &'a [EmailItem],
+    pub digest: bool,
+}
+
+fn developer_label(developers: &[Developer], developer_id: Option<i64>) -> String {
+    match developer_id {
+        None => "Unassigned".to_string(),
+        Some(id) => developers.iter()
+            .find(|d| d.id == id)
+            .map(|d| d.name.clone())
+            .unwrap_or_else(|| format!("Developer #{}", id)),
+    }
+}
+
+fn project_label(projects: &[Project], project_id: Option<i64>) -> String {
+    match project_id {
+        None => "No project".to_string(),
+        Some(id) => projects.iter()
+            .find(|p| p.id == id)
+            .map(|p| p.name.clone())
+            .unwrap_or_else(|| format!("Project #{}", id)),
+    }
+}
+
+pub fn redirect(location: &str) -> HttpResponse {
+    HttpResponse::SeeOther()
+        .insert_header((actix_web::http::header::LOCATION, location))
+        .finish()
+}
+
+pub fn render<T: Template>(status: StatusCode, template: &T) -> HttpResponse {
+    match template.render() {
+        Ok(html) => HttpResponse::build(status)
+            .content_type("text/html; charset=utf-8")
+            .body(html),
+        Err(e) => {
+            eprintln!("❌ Failed to render template: {}", e);
+            HttpResponse::InternalServerError().json("Failed to render page")
+        }
+    }
+}
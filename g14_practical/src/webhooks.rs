@@ -0,0 +1,199 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::Duration;
+use crate::database;
+use crate::models::{DueWebhookDelivery, WebhookAttempt};
+use crate::state::AppState;
+
+// Delivers queued webhook events. Deliveries live in SQLite, so anything
+// still pending when the server stops is sent after it starts again; a
+// delivery interrupted mid-request may arrive twice, which receivers
+// detect by the event id.
+//
+// Each request is a JSON POST with:
+//   X-Webhook-Event: bug.created
+//   X-Webhook-Delivery: 42
+//   X-Webhook-Signature-256: sha256=<hex HMAC-SHA256 of the body with the webhook's secret>
+// Any 2xx response counts as delivered. Redirects aren't followed (a 3xx
+// is a failure), so a receiver can't bounce the signed payload elsewhere.
+
+const SIGNATURE_HEADER: &str = "X-Webhook-Signature-256";
+
+// How often the queue is checked when nothing wakes the worker.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+const BATCH_SIZE: i64 = 20;
+
+// Retries never wait longer than this.
+const MAX_RETRY_SECONDS: i64 = 60 * 60;
+
+// Only the start of the receiver's response is read and kept in the log.
+const MAX_LOGGED_BODY: usize = 2048;
+
+pub fn spawn_worker(app_state: AppState) {
+    tokio::spawn(async move {
+        let client = match reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .user_agent("g14-bug-tracker-webhooks")
+            .build()
+        {
+            Ok(client) => client,
+            Err(e) => {
+                eprintln!("❌ Webhook worker could not start: {}", e);
+                return;
+            }
+        };
+
+        loop {
+            // Keep going while whole batches come back, then wait.
+            loop {
+                match database::due_webhook_deliveries(&app_state.db, BATCH_SIZE).await {
+                    Ok(due) => {
+                        let full = due.len() as i64 == BATCH_SIZE;
+                        for delivery in due {
+                            deliver(&app_state, &client, delivery).await;
+                        }
+                        if !full {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("❌ Failed to load webhook deliveries: {}", e);
+                        break;
+                    }
+                }
+            }
+            tokio::select! {
+                _ = app_state.webhook_wakeup.notified() => {}
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+async fn deliver(app_state: &AppState, client: &reqwest::Client, delivery: DueWebhookDelivery) {
+    let response = client.post(&delivery.url)
+        .header("Content-Type", "application/json")
+        .header("X-Webhook-Event", &delivery.event)
+        .header("X-Webhook-Delivery", delivery.id.to_string())
+        .header(SIGNATURE_HEADER, signature(&delivery.secret, delivery.payload.as_bytes()))
+        .body(delivery.payload.clone())
+        .send()
+        .await;
+
+    let mut attempt = match response {
+        Ok(response) => {
+            let status = response.status();
+            let body = read_start(response, MAX_LOGGED_BODY).await;
+            WebhookAttempt {
+                delivered: status.is_success(),
+                response_status: Some(i64::from(status.as_u16())),
+                response_body: Some(truncate(body, MAX_LOGGED_BODY)),
+                error: (!status.is_success()).then(|| format!("Receiver answered {}", status)),
+                retry_in_seconds: None,
+            }
+        }
+        Err(e) => WebhookAttempt {
+            delivered: false,
+            response_status: None,
+            response_body: None,
+            error: Some(e.to_string()),
+            retry_in_seconds: None,
+        },
+    };
+
+    let attempts = delivery.attempts + 1;
+    if !attempt.delivered && attempts < app_state.config.webhook_max_attempts {
+        attempt.retry_in_seconds = Some(retry_delay(app_state.config.webhook_retry_base_seconds, attempts));
+    }
+    match (&attempt.error, attempt.retry_in_seconds) {
+        (None, _) => println!("📨 Delivered {} #{} to {}", delivery.event, delivery.id, delivery.url),
+        (Some(error), Some(retry)) => println!(
+            "⚠️  Webhook delivery #{} to {} failed (attempt {}): {}; retrying in {}s",
+            delivery.id, delivery.url, attempts, error, retry
+        ),
+        (Some(error), None) => eprintln!(
+            "❌ Webhook delivery #{} to {} failed after {} attempt(s): {}",
+            delivery.id, delivery.url, attempts, error
+        ),
+    }
+
+    if let Err(e) = database::record_webhook_attempt(&app_state.db, delivery.id, &attempt).await {
+        eprintln!("❌ Failed to record webhook delivery #{}: {}", delivery.id, e);
+    }
+}
+
+// `sha256=<hex>`, as sent in `X-Webhook-Signature-256`.
+fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+// base, 2×base, 4×base, ... capped at an hour. Notification emails back
+// off the same way.
+pub fn retry_delay(base_seconds: i64, attempts: i64) -> i64 {
+    let doublings = (attempts - 1).clamp(0, 20) as u32;
+    base_seconds.saturating_mul(1 << doublings).min(MAX_RETRY_SECONDS)
+}
+
+// Reads at most `max` bytes of the body and drops the rest unread,
+// so a huge or endless response can't fill memory.
+async fn read_start(mut response: reqwest::Response, max: usize) -> String {
+    let mut bytes = Vec::new();
+    while bytes.len() < max {
+        match response.chunk().await {
+            Ok(Some(chunk)) => bytes.extend_from_slice(&chunk[..chunk.len().min(max - bytes.len())]),
+            Ok(None) | Err(_) => break,
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn truncate(mut text: String, max: usize) -> String {
+    if text.len() > max {
+        let mut end = max;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_are_hmac_sha256_in_hex() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn retries_back_off_by_doubling_up_to_an_hour() {
+        let delays: Vec<i64> = (1..=5).map(|attempts| retry_delay(30, attempts)).collect();
+        assert_eq!(delays, vec![30, 60, 120, 240, 480]);
+        assert_eq!(retry_delay(30, 0), 30);
+        assert_eq!(retry_delay(30, 8), MAX_RETRY_SECONDS);
+        assert_eq!(retry_delay(30, i64::MAX), MAX_RETRY_SECONDS);
+        assert_eq!(retry_delay(i64::MAX, 3), MAX_RETRY_SECONDS);
+    }
+
+    #[test]
+    fn truncation_keeps_whole_characters() {
+        assert_eq!(truncate("short".to_string(), 10), "short");
+        assert_eq!(truncate("abcdef".to_string(), 3), "abc");
+        // "é" is two bytes; cutting through it keeps the character before.
+        assert_eq!(truncate("caféé".to_string(), 4), "caf");
+        assert_eq!(truncate("caféé".to_string(), 5), "café");
+        assert_eq!(truncate("✓".to_string(), 2), "");
+    }
+}